@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::fs;
+use std::path::Path;
+use xml_parser::Tools::lexical_analysis::{tokenize, Lexer, TokenizerOptions};
+
+#[path = "support/char_lexer.rs"]
+mod char_lexer;
+
+/// Every .cat/.ros file in example-data, skipping the temp_* files the tests write there
+fn example_files() -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = fs::read_dir(Path::new("example-data"))
+        .expect("example-data directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            !name.starts_with("temp_")
+                && matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("cat" | "ros" | "gst")
+                )
+        })
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+            let content = fs::read_to_string(&path).ok()?;
+            Some((name, content))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn bench_lexers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    for (name, content) in example_files() {
+        group.throughput(Throughput::Bytes(content.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("char_lexer", &name), &content, |b, xml| {
+            b.iter(|| char_lexer::tokenize(xml).unwrap().len())
+        });
+        group.bench_with_input(BenchmarkId::new("byte_lexer", &name), &content, |b, xml| {
+            b.iter(|| tokenize(xml).unwrap().len())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("byte_lexer_streaming", &name),
+            &content,
+            |b, xml| {
+                b.iter(|| {
+                    Lexer::new(xml, &TokenizerOptions::default())
+                        .try_fold(0usize, |count, token| token.map(|_| count + 1))
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexers);
+criterion_main!(benches);
@@ -0,0 +1,689 @@
+// The char-by-char tokenizer the byte lexer replaced, kept only as a benchmark baseline
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use xml_parser::models::DocumentType::DocumentType;
+use xml_parser::models::Position::Position;
+use xml_parser::models::Span::Span;
+use xml_parser::models::XmlDeclaration::XmlDeclaration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    OpenTag(String),
+    CloseTag(String),
+    SelfClosingTag(String),
+    XmlDeclaration(XmlDeclaration),
+    ProcessingInstruction { target: String, data: String },
+    Doctype(DocumentType),
+    Attribute(String, String),
+    Text(String),
+    Comment(String),
+    CData(String),
+    EndOfFile,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum TokenizeError {
+    UnexpectedEndOfInput {
+        position: Position,
+    },
+    MalformedTag {
+        position: Position,
+    },
+    MalformedAttribute {
+        position: Position,
+    },
+    MalformedDeclaration {
+        position: Position,
+    },
+    InvalidReference {
+        reference: String,
+        position: Position,
+    },
+}
+
+impl TokenizeError {
+    pub fn position(&self) -> Position {
+        match self {
+            TokenizeError::UnexpectedEndOfInput { position }
+            | TokenizeError::MalformedTag { position }
+            | TokenizeError::MalformedAttribute { position }
+            | TokenizeError::MalformedDeclaration { position }
+            | TokenizeError::InvalidReference { position, .. } => *position,
+        }
+    }
+}
+
+impl std::fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizeError::UnexpectedEndOfInput { position } => {
+                write!(f, "{}: unexpected end of input", position)
+            }
+            TokenizeError::MalformedTag { position } => write!(f, "{}: malformed tag", position),
+            TokenizeError::MalformedAttribute { position } => {
+                write!(f, "{}: malformed attribute", position)
+            }
+            TokenizeError::MalformedDeclaration { position } => {
+                write!(f, "{}: malformed XML declaration", position)
+            }
+            TokenizeError::InvalidReference {
+                reference,
+                position,
+            } => write!(f, "{}: invalid reference {}", position, reference),
+        }
+    }
+}
+
+// Character scanner that keeps track of where in the input it is
+struct CharScanner<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+}
+
+impl<'a> CharScanner<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            position: Position::start(),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        self.position.advance(ch);
+        Some(ch)
+    }
+
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespaceMode {
+    // Trim text and drop whitespace-only text between tags
+    #[default]
+    Trim,
+    // Trim text and collapse inner whitespace runs to a single space
+    Collapse,
+    // Keep every text run, including whitespace between tags, exactly as written
+    Preserve,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TokenizerOptions {
+    pub whitespace: WhitespaceMode,
+}
+
+impl TokenizerOptions {
+    pub fn preserve_whitespace() -> Self {
+        Self {
+            whitespace: WhitespaceMode::Preserve,
+        }
+    }
+
+    pub fn collapse_whitespace() -> Self {
+        Self {
+            whitespace: WhitespaceMode::Collapse,
+        }
+    }
+}
+
+pub fn tokenize(xml_string: &str) -> Result<Vec<SpannedToken>, TokenizeError> {
+    tokenize_with(xml_string, &TokenizerOptions::default())
+}
+
+pub fn tokenize_with(
+    xml_string: &str,
+    options: &TokenizerOptions,
+) -> Result<Vec<SpannedToken>, TokenizeError> {
+    let mut tokens = Vec::new();
+    let mut chars = CharScanner::new(xml_string);
+    // Whether each open element is under xml:space="preserve"
+    let mut preserve_stack: Vec<bool> = Vec::new();
+
+    while let Some(ch) = chars.peek() {
+        let preserve = options.whitespace == WhitespaceMode::Preserve
+            || preserve_stack.last().copied().unwrap_or(false);
+
+        if ch == '<' {
+            let tag_tokens = parse_tag_with_attributes(&mut chars, preserve)?;
+            match tag_tokens.last().map(|spanned| &spanned.token) {
+                Some(Token::OpenTag(_)) => {
+                    let xml_space = tag_tokens.iter().find_map(|spanned| match &spanned.token {
+                        Token::Attribute(name, value) if name == "xml:space" => Some(value),
+                        _ => None,
+                    });
+                    preserve_stack.push(match xml_space.map(String::as_str) {
+                        Some("preserve") => true,
+                        Some("default") => false,
+                        _ => preserve_stack.last().copied().unwrap_or(false),
+                    });
+                }
+                Some(Token::CloseTag(_)) => {
+                    preserve_stack.pop();
+                }
+                _ => {}
+            }
+            tokens.extend(tag_tokens);
+        } else if let Some(text) = parse_text(&mut chars, options.whitespace, preserve)? {
+            tokens.push(text);
+        }
+    }
+
+    let end = chars.position();
+    tokens.push(SpannedToken {
+        token: Token::EndOfFile,
+        span: Span::new(end, end),
+    });
+    Ok(tokens)
+}
+
+fn parse_tag_with_attributes(
+    chars: &mut CharScanner,
+    preserve: bool,
+) -> Result<Vec<SpannedToken>, TokenizeError> {
+    let start = chars.position();
+    chars.next(); // Consume '<'
+
+    let token = match chars.peek() {
+        Some('/') => parse_close_tag(chars)?,
+        Some('!') => parse_markup_declaration(chars, preserve)?,
+        Some('?') => parse_processing_instruction(chars)?,
+        Some(_) => return parse_open_tag_with_attributes(chars, start),
+        None => {
+            return Err(TokenizeError::UnexpectedEndOfInput {
+                position: chars.position(),
+            })
+        }
+    };
+
+    Ok(vec![SpannedToken {
+        token,
+        span: Span::new(start, chars.position()),
+    }])
+}
+
+fn parse_open_tag_with_attributes(
+    chars: &mut CharScanner,
+    start: Position,
+) -> Result<Vec<SpannedToken>, TokenizeError> {
+    let mut tokens = Vec::new();
+    let mut tag_name = String::new();
+
+    // Parse tag name
+    while let Some(ch) = chars.peek() {
+        match ch {
+            ' ' | '\t' | '\n' | '\r' => {
+                chars.next();
+                break;
+            }
+            '>' => {
+                chars.next();
+                tokens.push(SpannedToken {
+                    token: Token::OpenTag(tag_name),
+                    span: Span::new(start, chars.position()),
+                });
+                return Ok(tokens);
+            }
+            '/' => {
+                return parse_self_closing_end(chars, tag_name, start, tokens);
+            }
+            _ => {
+                tag_name.push(ch);
+                chars.next();
+            }
+        }
+    }
+
+    // Parse attributes
+    while let Some(ch) = chars.peek() {
+        match ch {
+            '>' => {
+                chars.next();
+                tokens.push(SpannedToken {
+                    token: Token::OpenTag(tag_name),
+                    span: Span::new(start, chars.position()),
+                });
+                return Ok(tokens);
+            }
+            '/' => {
+                return parse_self_closing_end(chars, tag_name, start, tokens);
+            }
+            ' ' | '\t' | '\n' | '\r' => {
+                chars.next();
+            }
+            _ => {
+                // Parse attribute and add it to tokens
+                let attr_token = parse_attribute(chars)?;
+                tokens.push(attr_token);
+            }
+        }
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn parse_self_closing_end(
+    chars: &mut CharScanner,
+    tag_name: String,
+    start: Position,
+    mut tokens: Vec<SpannedToken>,
+) -> Result<Vec<SpannedToken>, TokenizeError> {
+    let slash = chars.position();
+    chars.next(); // Consume '/'
+    if chars.next() == Some('>') {
+        tokens.push(SpannedToken {
+            token: Token::SelfClosingTag(tag_name),
+            span: Span::new(start, chars.position()),
+        });
+        return Ok(tokens);
+    }
+    Err(TokenizeError::MalformedTag { position: slash })
+}
+
+fn parse_close_tag(chars: &mut CharScanner) -> Result<Token, TokenizeError> {
+    chars.next(); // Consume '/'
+    let mut tag_name = String::new();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '>' => {
+                return Ok(Token::CloseTag(tag_name));
+            }
+            _ => {
+                tag_name.push(ch);
+            }
+        }
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn parse_markup_declaration(
+    chars: &mut CharScanner,
+    preserve: bool,
+) -> Result<Token, TokenizeError> {
+    let bang = chars.position();
+    chars.next(); // Consume '!'
+
+    match chars.peek() {
+        Some('-') => parse_comment(chars, bang, preserve),
+        Some('[') => parse_cdata(chars, bang),
+        Some('D') => parse_doctype(chars, bang),
+        Some(_) => Err(TokenizeError::MalformedTag { position: bang }),
+        None => Err(TokenizeError::UnexpectedEndOfInput {
+            position: chars.position(),
+        }),
+    }
+}
+
+fn expect_literal(
+    chars: &mut CharScanner,
+    literal: &str,
+    position: Position,
+) -> Result<(), TokenizeError> {
+    for expected in literal.chars() {
+        match chars.next() {
+            Some(ch) if ch == expected => {}
+            Some(_) => return Err(TokenizeError::MalformedTag { position }),
+            None => {
+                return Err(TokenizeError::UnexpectedEndOfInput {
+                    position: chars.position(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_comment(
+    chars: &mut CharScanner,
+    bang: Position,
+    preserve: bool,
+) -> Result<Token, TokenizeError> {
+    // Check for <!--
+    expect_literal(chars, "--", bang)?;
+
+    let mut comment = String::new();
+    let mut prev_chars = [' ', ' '];
+
+    while let Some(ch) = chars.next() {
+        comment.push(ch);
+        prev_chars[0] = prev_chars[1];
+        prev_chars[1] = ch;
+
+        if prev_chars == ['-', '-'] && chars.next() == Some('>') {
+            comment.pop(); // Remove last '-'
+            comment.pop(); // Remove second to last '-'
+            if !preserve {
+                comment = comment.trim().to_string();
+            }
+            return Ok(Token::Comment(comment));
+        }
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn parse_cdata(chars: &mut CharScanner, bang: Position) -> Result<Token, TokenizeError> {
+    expect_literal(chars, "[CDATA[", bang)?;
+
+    // CDATA content is taken verbatim up to the first ]]>
+    let mut content = String::new();
+    while let Some(ch) = chars.next() {
+        content.push(ch);
+        if content.ends_with("]]>") {
+            content.truncate(content.len() - 3);
+            return Ok(Token::CData(content));
+        }
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn parse_processing_instruction(chars: &mut CharScanner) -> Result<Token, TokenizeError> {
+    let start = chars.position();
+    chars.next(); // Consume '?'
+
+    let mut content = String::new();
+    while let Some(ch) = chars.next() {
+        content.push(ch);
+        if content.ends_with("?>") {
+            content.truncate(content.len() - 2);
+            let (target, data) = match content.find(is_xml_whitespace) {
+                Some(index) => (&content[..index], content[index..].trim_start()),
+                None => (content.as_str(), ""),
+            };
+
+            if target.is_empty() {
+                return Err(TokenizeError::MalformedTag { position: start });
+            }
+            if target == "xml" {
+                return parse_xml_declaration(data, start).map(Token::XmlDeclaration);
+            }
+
+            return Ok(Token::ProcessingInstruction {
+                target: target.to_string(),
+                data: data.to_string(),
+            });
+        }
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+// Reads the version/encoding/standalone pseudo-attributes of an XML declaration
+fn parse_xml_declaration(data: &str, start: Position) -> Result<XmlDeclaration, TokenizeError> {
+    let malformed = || TokenizeError::MalformedDeclaration { position: start };
+    let mut version = None;
+    let mut encoding = None;
+    let mut standalone = None;
+    let mut rest = data.trim();
+
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=').ok_or_else(malformed)?;
+        let after_name = after_name.trim_start();
+        let quote = after_name.chars().next().filter(|&c| c == '"' || c == '\'');
+        let quote = quote.ok_or_else(malformed)?;
+        let (value, after_value) = after_name[1..].split_once(quote).ok_or_else(malformed)?;
+
+        match name.trim() {
+            "version" if version.is_none() => version = Some(value.to_string()),
+            "encoding" if encoding.is_none() => encoding = Some(value.to_string()),
+            "standalone" if standalone.is_none() => {
+                standalone = match value {
+                    "yes" => Some(true),
+                    "no" => Some(false),
+                    _ => return Err(malformed()),
+                }
+            }
+            _ => return Err(malformed()),
+        }
+
+        rest = after_value.trim_start();
+    }
+
+    Ok(XmlDeclaration {
+        version: version.ok_or_else(malformed)?,
+        encoding,
+        standalone,
+    })
+}
+
+fn parse_doctype(chars: &mut CharScanner, bang: Position) -> Result<Token, TokenizeError> {
+    expect_literal(chars, "DOCTYPE", bang)?;
+
+    let mut content = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_subset = false;
+
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '[') if !in_subset => in_subset = true,
+            (None, ']') if in_subset => in_subset = false,
+            (None, '>') if !in_subset => return split_doctype(&content, bang),
+            _ => {}
+        }
+        content.push(ch);
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn split_doctype(content: &str, bang: Position) -> Result<Token, TokenizeError> {
+    let content = content.trim();
+    let name_end = content
+        .find(|c: char| is_xml_whitespace(c) || c == '[')
+        .unwrap_or(content.len());
+    let name = &content[..name_end];
+    if name.is_empty() {
+        return Err(TokenizeError::MalformedTag { position: bang });
+    }
+
+    let rest = &content[name_end..];
+    let (external_id, internal_subset) = match (rest.find('['), rest.rfind(']')) {
+        (Some(open), Some(close)) if open < close => {
+            (&rest[..open], Some(rest[open + 1..close].to_string()))
+        }
+        _ => (rest, None),
+    };
+    let external_id = external_id.trim();
+
+    Ok(Token::Doctype(DocumentType {
+        name: name.to_string(),
+        external_id: (!external_id.is_empty()).then(|| external_id.to_string()),
+        internal_subset,
+    }))
+}
+
+fn is_xml_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\n' | '\r')
+}
+
+fn parse_attribute(chars: &mut CharScanner) -> Result<SpannedToken, TokenizeError> {
+    let start = chars.position();
+    let mut name = String::new();
+    let mut value = String::new();
+
+    // Parse attribute name
+    while let Some(ch) = chars.peek() {
+        match ch {
+            '=' => {
+                chars.next();
+                break;
+            }
+            ' ' | '\t' | '\n' | '\r' => {
+                chars.next();
+            }
+            _ => {
+                name.push(ch);
+                chars.next();
+            }
+        }
+    }
+
+    // Parse attribute value
+    let quote_position = chars.position();
+    let quote_char = chars.next().ok_or(TokenizeError::UnexpectedEndOfInput {
+        position: quote_position,
+    })?;
+    if quote_char != '"' && quote_char != '\'' {
+        return Err(TokenizeError::MalformedAttribute {
+            position: quote_position,
+        });
+    }
+
+    let value_start = chars.position();
+    while let Some(ch) = chars.next() {
+        if ch == quote_char {
+            let value = decode_references_at(&value, value_start)?.into_owned();
+            return Ok(SpannedToken {
+                token: Token::Attribute(name, value),
+                span: Span::new(start, chars.position()),
+            });
+        }
+        value.push(ch);
+    }
+
+    Err(TokenizeError::UnexpectedEndOfInput {
+        position: chars.position(),
+    })
+}
+
+fn parse_text(
+    chars: &mut CharScanner,
+    mode: WhitespaceMode,
+    preserve: bool,
+) -> Result<Option<SpannedToken>, TokenizeError> {
+    let start = chars.position();
+    let mut text = String::new();
+
+    while let Some(ch) = chars.peek() {
+        if ch == '<' {
+            break;
+        }
+        text.push(ch);
+        chars.next();
+    }
+
+    if preserve {
+        let decoded = decode_references_at(&text, start)?.into_owned();
+        return Ok(Some(SpannedToken {
+            token: Token::Text(decoded),
+            span: Span::new(start, chars.position()),
+        }));
+    }
+
+    let trimmed = text.trim_matches(is_xml_whitespace);
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let leading = text.len() - text.trim_start_matches(is_xml_whitespace).len();
+    let trimmed_start = start.advanced_by(&text[..leading]);
+    let span = Span::new(trimmed_start, trimmed_start.advanced_by(trimmed));
+    let decoded = match mode {
+        WhitespaceMode::Collapse => {
+            let collapsed = trimmed
+                .split(is_xml_whitespace)
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            decode_references_at(&collapsed, trimmed_start)?.into_owned()
+        }
+        _ => decode_references_at(trimmed, trimmed_start)?.into_owned(),
+    };
+
+    Ok(Some(SpannedToken {
+        token: Token::Text(decoded),
+        span,
+    }))
+}
+
+pub fn decode_references(raw: &str) -> Result<Cow<'_, str>, TokenizeError> {
+    decode_references_at(raw, Position::start())
+}
+
+// Decodes references in `raw`, reporting errors relative to where `raw` starts in the input
+fn decode_references_at(raw: &str, start: Position) -> Result<Cow<'_, str>, TokenizeError> {
+    if !raw.contains('&') {
+        return Ok(Cow::Borrowed(raw));
+    }
+
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        let reference = &rest[amp..];
+        let invalid = |text: &str| TokenizeError::InvalidReference {
+            reference: text.to_string(),
+            position: start.advanced_by(&raw[..raw.len() - reference.len()]),
+        };
+
+        // A reference runs up to the next ';' and may not contain whitespace or another '&'
+        let end = match reference[1..].find(|c: char| c == ';' || c == '&' || c.is_whitespace()) {
+            Some(offset) if reference[1 + offset..].starts_with(';') => 1 + offset,
+            Some(offset) => return Err(invalid(&reference[..1 + offset])),
+            None => return Err(invalid(reference)),
+        };
+
+        match resolve_reference(&reference[1..end]) {
+            Some(ch) => decoded.push(ch),
+            None => return Err(invalid(&reference[..=end])),
+        }
+
+        rest = &reference[end + 1..];
+    }
+
+    decoded.push_str(rest);
+    Ok(Cow::Owned(decoded))
+}
+
+fn resolve_reference(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let digits = name.strip_prefix('#')?;
+            let code_point = match digits.strip_prefix('x') {
+                Some(hex) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                    u32::from_str_radix(hex, 16).ok()?
+                }
+                None if digits.bytes().all(|b| b.is_ascii_digit()) => digits.parse().ok()?,
+                _ => return None,
+            };
+            char::from_u32(code_point).filter(|&ch| ch != '\0')
+        }
+    }
+}
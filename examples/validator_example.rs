@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use xml_parser::models::Attribute::Attributes;
 use xml_parser::models::Constraint::Constraint;
 use xml_parser::models::ConstraintType::ConstraintType;
 use xml_parser::models::XmlElement::XmlElement;
@@ -172,7 +172,7 @@ fn field_validation_example() {
 
 fn create_mock_constraints_element() -> XmlElement {
     // Create constraint elements similar to the "2-5 Enlightened" group
-    let mut min_attributes = HashMap::new();
+    let mut min_attributes = Attributes::new();
     min_attributes.insert("type".to_string(), "min".to_string());
     min_attributes.insert("value".to_string(), "2".to_string());
     min_attributes.insert("field".to_string(), "selections".to_string());
@@ -181,12 +181,11 @@ fn create_mock_constraints_element() -> XmlElement {
     min_attributes.insert("id".to_string(), "c6ca-55be-a638-5f08".to_string());
 
     let min_constraint = XmlElement {
-        name: "constraint".to_string(),
         attributes: min_attributes,
-        children: Vec::new(),
+        ..XmlElement::new("constraint")
     };
 
-    let mut max_attributes = HashMap::new();
+    let mut max_attributes = Attributes::new();
     max_attributes.insert("type".to_string(), "max".to_string());
     max_attributes.insert("value".to_string(), "5".to_string());
     max_attributes.insert("field".to_string(), "selections".to_string());
@@ -195,17 +194,15 @@ fn create_mock_constraints_element() -> XmlElement {
     max_attributes.insert("id".to_string(), "ad81-4838-8c1d-8c1c".to_string());
 
     let max_constraint = XmlElement {
-        name: "constraint".to_string(),
         attributes: max_attributes,
-        children: Vec::new(),
+        ..XmlElement::new("constraint")
     };
 
     XmlElement {
-        name: "constraints".to_string(),
-        attributes: HashMap::new(),
         children: vec![
             XmlNode::Element(min_constraint),
             XmlNode::Element(max_constraint),
         ],
+        ..XmlElement::new("constraints")
     }
 }
@@ -0,0 +1,155 @@
+use crate::models::XmlArena::{NodeId, XmlArena};
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::lexical_analysis::tokenize;
+use crate::Tools::parse_tokens::{parse_tokens, parse_tokens_arena};
+
+const ROSTER: &str = r#"<roster name="Waaagh!"><!-- forces -->
+<forces><force name="Army"/><force name="Allies"/></forces>
+<costs><cost name="pts" value="500"/></costs>
+</roster>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Parse a string straight into an arena
+fn arena_of(xml: &str) -> XmlArena {
+    parse_tokens_arena(tokenize(xml).expect("Failed to tokenize")).expect("Failed to parse")
+}
+
+/// Names of the element nodes among `ids`
+fn element_names(arena: &XmlArena, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+    ids.filter_map(|id| arena.element(id).map(|element| element.name.clone()))
+        .collect()
+}
+
+// ============================================================================
+// NAVIGATION TESTS
+// ============================================================================
+
+#[test]
+fn test_arena_parent_and_siblings() {
+    let arena = arena_of(ROSTER);
+    let root = arena.root().unwrap();
+    assert_eq!(arena.element(root).unwrap().name, "roster");
+    assert_eq!(arena.parent(root), None);
+
+    let forces = arena
+        .children(root)
+        .find(|&id| arena.element(id).is_some_and(|e| e.name == "forces"))
+        .unwrap();
+    let army = arena.first_child(forces).unwrap();
+    let allies = arena.next_sibling(army).unwrap();
+
+    assert_eq!(
+        arena
+            .element(allies)
+            .unwrap()
+            .get_attribute("name")
+            .unwrap(),
+        "Allies"
+    );
+    assert_eq!(arena.prev_sibling(allies), Some(army));
+    assert_eq!(arena.prev_sibling(army), None);
+    assert_eq!(arena.next_sibling(allies), None);
+    assert_eq!(arena.last_child(forces), Some(allies));
+    assert_eq!(arena.parent(allies), Some(forces));
+    assert_eq!(
+        element_names(&arena, arena.ancestors(allies)),
+        ["forces", "roster"]
+    );
+
+    // Comments and text are nodes too
+    assert!(matches!(
+        arena.node(arena.first_child(root).unwrap()),
+        XmlNode::Comment(_)
+    ));
+}
+
+#[test]
+fn test_arena_descendants_in_document_order() {
+    let arena = arena_of(ROSTER);
+    let root = arena.root().unwrap();
+
+    assert_eq!(
+        element_names(&arena, arena.descendants(root)),
+        ["forces", "force", "force", "costs", "cost"]
+    );
+
+    // Descendants stay within the subtree they start from
+    let force = arena
+        .descendants(root)
+        .find(|&id| arena.element(id).is_some_and(|e| e.name == "force"))
+        .unwrap();
+    let forces = arena.find_ancestor_by_name(force, "forces").unwrap();
+    assert_eq!(
+        element_names(&arena, arena.descendants(forces)),
+        ["force", "force"]
+    );
+}
+
+#[test]
+fn test_arena_constraint_finds_its_selection_entry() {
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+    let arena = arena_of(&xml);
+
+    let constraint = arena
+        .descendants(arena.root().unwrap())
+        .find(|&id| arena.element(id).is_some_and(|e| e.name == "constraint"))
+        .expect("Orks.cat has constraints");
+    let entry = arena
+        .find_ancestor_by_name(constraint, "selectionEntry")
+        .expect("Constraint should sit inside a selectionEntry");
+
+    assert_eq!(
+        arena.element(entry).unwrap().get_attribute("name").unwrap(),
+        "Warlord"
+    );
+}
+
+// ============================================================================
+// CONVERSION TESTS
+// ============================================================================
+
+#[test]
+fn test_arena_converts_back_to_owned_tree() {
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+    let document = parse_tokens(tokenize(&xml).unwrap()).unwrap();
+    let expected = 1 + document.get_root_element().unwrap().descendants().count();
+
+    let arena = XmlArena::from(document);
+    let elements = arena
+        .descendants(arena.root().unwrap())
+        .filter(|&id| arena.element(id).is_some())
+        .count();
+    assert_eq!(elements + 1, expected);
+
+    let document = arena.to_document();
+    let root = document.get_root_element().unwrap();
+    assert_eq!(1 + root.descendants().count(), expected);
+    assert_eq!(root.get_attribute("name").unwrap(), "Xenos - Orks");
+    assert!(document.declaration.is_some());
+    assert_eq!(
+        document
+            .find_element_by_path("sharedSelectionEntries/selectionEntry/constraints/constraint")
+            .and_then(|constraint| constraint.get_attribute("scope"))
+            .unwrap(),
+        "parent"
+    );
+}
+
+#[test]
+fn test_arena_keeps_mixed_content_in_order() {
+    let arena = arena_of("<a>x<![CDATA[y]]><b/><!--z--></a>");
+    let root = arena.root().unwrap();
+    let children: Vec<&XmlNode> = arena.children(root).map(|id| arena.node(id)).collect();
+
+    assert!(matches!(children[0], XmlNode::Text(text) if text == "x"));
+    assert!(matches!(children[1], XmlNode::CData(text) if text == "y"));
+    assert!(matches!(children[2], XmlNode::Element(element) if element.name == "b"));
+    assert!(matches!(children[3], XmlNode::Comment(text) if text == "z"));
+    assert_eq!(arena.len(), 5);
+
+    let error = parse_tokens_arena(tokenize("<a/><b><c/></b>").unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "1:5: unexpected second root element <b>");
+}
@@ -51,18 +51,22 @@ fn test_find_xml_files() {
         DecompressedFile {
             filename: "document.xml".to_string(),
             content: "<root>test</root>".to_string(),
+            raw: b"<root>test</root>".to_vec(),
         },
         DecompressedFile {
             filename: "data.json".to_string(),
             content: "{}".to_string(),
+            raw: b"{}".to_vec(),
         },
         DecompressedFile {
             filename: "catalog.cat".to_string(),
             content: "<catalog>test</catalog>".to_string(),
+            raw: b"<catalog>test</catalog>".to_vec(),
         },
         DecompressedFile {
             filename: "roster.ros".to_string(),
             content: "<roster>test</roster>".to_string(),
+            raw: b"<roster>test</roster>".to_vec(),
         },
     ];
 
@@ -0,0 +1,352 @@
+use crate::models::EditError::EditError;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::writer::{document_to_string, WriterOptions};
+use crate::{parse_file_lossless, parse_str, parse_str_lossless};
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Build an element with the given attributes
+fn element(name: &str, attributes: &[(&str, &str)]) -> XmlElement {
+    let mut element = XmlElement::new(name);
+    for (key, value) in attributes {
+        element
+            .set_attribute(*key, *value)
+            .expect("Invalid attribute name");
+    }
+    element
+}
+
+/// Write a single element without a declaration
+fn to_xml(element: &XmlElement) -> String {
+    let document = XmlDocument {
+        root: Some(element.clone()),
+        ..Default::default()
+    };
+    let options = WriterOptions {
+        declaration: false,
+        space_before_slash: false,
+        ..WriterOptions::compact()
+    };
+    document_to_string(&document, &options)
+}
+
+// ============================================================================
+// CHILD EDITING TESTS
+// ============================================================================
+
+#[test]
+fn test_append_insert_remove_and_replace_children() {
+    let mut selections = XmlElement::new("selections");
+    selections
+        .append_child(element("selection", &[("name", "Boyz")]))
+        .unwrap();
+    selections
+        .append_child(element("selection", &[("name", "Grot")]))
+        .unwrap();
+    selections
+        .insert_child_at(1, element("selection", &[("name", "Nob")]))
+        .unwrap();
+    selections
+        .insert_child_at(0, XmlNode::Comment(" units ".to_string()))
+        .unwrap();
+    assert_eq!(
+        to_xml(&selections),
+        "<selections><!-- units --><selection name=\"Boyz\"/><selection name=\"Nob\"/><selection name=\"Grot\"/></selections>"
+    );
+
+    let removed = selections.remove_child(0).unwrap();
+    assert!(matches!(removed, XmlNode::Comment(_)));
+    let replaced = selections
+        .replace_child(2, element("selection", &[("name", "Gretchin")]))
+        .unwrap();
+    assert!(matches!(replaced, XmlNode::Element(e) if e.get_attribute("name").unwrap() == "Grot"));
+    assert_eq!(
+        to_xml(&selections),
+        "<selections><selection name=\"Boyz\"/><selection name=\"Nob\"/><selection name=\"Gretchin\"/></selections>"
+    );
+}
+
+#[test]
+fn test_out_of_bounds_edits_leave_element_unchanged() {
+    let mut force = XmlElement::new("force");
+    force.append_child(XmlElement::new("selections")).unwrap();
+
+    assert_eq!(
+        force.insert_child_at(2, XmlElement::new("rules")),
+        Err(EditError::IndexOutOfBounds { index: 2, len: 1 })
+    );
+    assert!(force.remove_child(1).is_err());
+    assert_eq!(
+        force
+            .replace_child(5, XmlElement::new("rules"))
+            .unwrap_err()
+            .to_string(),
+        "child index 5 is out of bounds for 1 children"
+    );
+    assert_eq!(force.children.len(), 1);
+}
+
+#[test]
+fn test_set_text_and_attributes() {
+    let mut rule = element("rule", &[("name", "Waaagh!"), ("hidden", "false")]);
+    rule.append_child(XmlElement::new("description")).unwrap();
+
+    rule.find_child_by_name_mut("description")
+        .unwrap()
+        .set_text("Charge & fight")
+        .unwrap();
+    assert_eq!(rule.remove_attribute("hidden"), Some("false".to_string()));
+    assert_eq!(rule.remove_attribute("hidden"), None);
+    assert_eq!(
+        rule.set_attribute("name", "Da Jump"),
+        Ok(Some("Waaagh!".to_string()))
+    );
+
+    assert_eq!(
+        to_xml(&rule),
+        "<rule name=\"Da Jump\"><description>Charge &amp; fight</description></rule>"
+    );
+
+    rule.set_text("").unwrap();
+    assert!(rule.children.is_empty());
+}
+
+// ============================================================================
+// VALIDATION TESTS
+// ============================================================================
+
+#[test]
+fn test_invalid_names_are_rejected() {
+    let mut force = XmlElement::new("force");
+
+    assert_eq!(
+        force.set_attribute("1st", "x"),
+        Err(EditError::InvalidAttributeName("1st".to_string()))
+    );
+    assert_eq!(
+        force.append_child(XmlElement::new("bad name")),
+        Err(EditError::InvalidElementName("bad name".to_string()))
+    );
+
+    // Names below the added node are checked too
+    let mut selection = XmlElement::new("selection");
+    selection
+        .children
+        .push(XmlNode::Element(XmlElement::new("<cost>")));
+    assert_eq!(
+        force.append_child(selection),
+        Err(EditError::InvalidElementName("<cost>".to_string()))
+    );
+
+    let mut cost = XmlElement::new("cost");
+    cost.attributes.insert("pts value", "5");
+    assert_eq!(
+        force.append_child(cost).unwrap_err().to_string(),
+        "\"pts value\" is not a valid attribute name"
+    );
+
+    let declaration = XmlNode::ProcessingInstruction {
+        target: "xml".to_string(),
+        data: "version=\"1.0\"".to_string(),
+    };
+    assert!(matches!(
+        force.append_child(declaration),
+        Err(EditError::InvalidProcessingInstructionTarget(_))
+    ));
+
+    assert!(force.children.is_empty());
+    assert!(force.attributes.is_empty());
+}
+
+#[test]
+fn test_ill_formed_content_is_rejected() {
+    let mut force = XmlElement::new("force");
+
+    assert_eq!(
+        force.append_child(XmlNode::Comment("a -- b".to_string())),
+        Err(EditError::InvalidComment("a -- b".to_string()))
+    );
+    assert_eq!(
+        force.append_child(XmlNode::Comment("trailing-".to_string())),
+        Err(EditError::InvalidComment("trailing-".to_string()))
+    );
+    assert_eq!(
+        force.append_child(XmlNode::CData("x]]>y".to_string())),
+        Err(EditError::InvalidCData("x]]>y".to_string()))
+    );
+    assert_eq!(
+        force.append_child(XmlNode::ProcessingInstruction {
+            target: "app".to_string(),
+            data: "a ?> b".to_string(),
+        }),
+        Err(EditError::InvalidProcessingInstructionData(
+            "a ?> b".to_string()
+        ))
+    );
+    assert_eq!(
+        force.append_child(XmlNode::Text("bell\u{7}".to_string())),
+        Err(EditError::InvalidCharacter('\u{7}'))
+    );
+
+    // Attribute values and text below the added node are checked too
+    let mut cost = XmlElement::new("cost");
+    cost.attributes.insert("name", "pts\u{0}");
+    assert_eq!(
+        force.append_child(cost).unwrap_err().to_string(),
+        "U+0000 is not allowed in XML"
+    );
+
+    assert_eq!(
+        force.set_attribute("name", "\u{1B}[1m"),
+        Err(EditError::InvalidCharacter('\u{1B}'))
+    );
+    assert_eq!(
+        force.set_text("\u{FFFF}"),
+        Err(EditError::InvalidCharacter('\u{FFFF}'))
+    );
+
+    assert!(force.children.is_empty());
+    assert!(force.attributes.is_empty());
+}
+
+#[test]
+fn test_setting_xmlns_resolves_namespaces_again() {
+    let document = parse_str(
+        r#"<catalogue xmlns="http://a"><entry/><bs:entry xmlns:bs="http://b"/></catalogue>"#,
+    )
+    .unwrap();
+    let mut catalogue = document.root.unwrap();
+
+    catalogue.set_attribute("xmlns", "http://c").unwrap();
+    catalogue.set_attribute("xmlns:bs", "http://d").unwrap();
+
+    assert_eq!(catalogue.namespace.as_deref(), Some("http://c"));
+    let children: Vec<&XmlElement> = catalogue.child_elements().collect();
+    assert_eq!(children[0].namespace.as_deref(), Some("http://c"));
+    // The child's own declaration still wins over the one it inherits
+    assert_eq!(children[1].namespace.as_deref(), Some("http://b"));
+    assert_eq!(catalogue.namespaces.lookup("bs"), Some("http://d"));
+}
+
+#[test]
+fn test_removing_xmlns_resolves_namespaces_again() {
+    let document = parse_str(r#"<r xmlns="urn:a"><c/></r>"#).unwrap();
+    let mut root = document.root.unwrap();
+
+    assert_eq!(root.remove_attribute("xmlns").as_deref(), Some("urn:a"));
+
+    assert_eq!(root.namespace, None);
+    assert_eq!(root.child_elements().next().unwrap().namespace, None);
+
+    // A removed redeclaration falls back to the binding inherited from the parent
+    let document =
+        parse_str(r#"<r xmlns:p="urn:a"><p:c xmlns:p="urn:b"><p:d/></p:c></r>"#).unwrap();
+    let mut root = document.root.unwrap();
+    let c = root.child_elements_mut().next().unwrap();
+
+    c.remove_attribute("xmlns:p");
+
+    assert_eq!(c.namespace.as_deref(), Some("urn:a"));
+    assert_eq!(
+        c.child_elements().next().unwrap().namespace.as_deref(),
+        Some("urn:a")
+    );
+}
+
+// ============================================================================
+// MUTABLE LOOKUP TESTS
+// ============================================================================
+
+#[test]
+fn test_find_mut_and_children_mut() {
+    let mut document = parse_str(
+        "<roster><forces><force name='Army'><selections><selection name='Boyz' number='10'/></selections></force><force name='Allies'/></forces></roster>",
+    )
+    .unwrap();
+    let roster = document.root.as_mut().unwrap();
+
+    roster
+        .find_by_attribute_mut("name", "Boyz")
+        .unwrap()
+        .set_attribute("number", "20")
+        .unwrap();
+    roster
+        .find_mut("forces")
+        .unwrap()
+        .children_named_mut("force")
+        .for_each(|force| {
+            force.set_attribute("hidden", "false").unwrap();
+        });
+    roster.for_each_descendant_mut(&mut |element| {
+        element.set_attribute("checked", "true").unwrap();
+    });
+    assert!(roster.find_mut("cost").is_none());
+
+    let roster = document.get_root_element().unwrap();
+    let boyz = roster.find_all("selection").next().unwrap();
+    assert_eq!(boyz.get_attribute("number").unwrap(), "20");
+    assert_eq!(roster.find_by_attribute("hidden", "false").count(), 2);
+    assert_eq!(roster.find_by_attribute("checked", "true").count(), 5);
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_add_selection_to_roster_and_write_back() {
+    let original = std::fs::read_to_string("example-data/Tts game teams.ros").unwrap();
+    let mut document = parse_file_lossless("example-data/Tts game teams.ros").unwrap();
+
+    let mut selection = element(
+        "selection",
+        &[
+            ("id", "new-selection"),
+            ("name", "Gretchin"),
+            ("number", "1"),
+            ("type", "unit"),
+        ],
+    );
+    selection.append_child(XmlElement::new("costs")).unwrap();
+    let force = document.root.as_mut().unwrap().find_mut("force").unwrap();
+    let selections = force.find_child_by_name_mut("selections").unwrap();
+    let count = selections.child_elements().count();
+    selections.append_child(selection).unwrap();
+
+    let written = document_to_string(&document, &WriterOptions::lossless());
+    assert_ne!(written, original);
+    // Everything outside the edit is written back as it was
+    let added = "<selection id=\"new-selection\" name=\"Gretchin\" number=\"1\" type=\"unit\"><costs/></selection>";
+    assert_eq!(written.replacen(added, "", 1), original);
+
+    let reparsed = parse_str_lossless(&written).unwrap();
+    let selections = reparsed
+        .get_root_element()
+        .unwrap()
+        .find_all("force")
+        .next()
+        .unwrap()
+        .find_child_by_name("selections")
+        .unwrap();
+    assert_eq!(selections.child_elements().count(), count + 1);
+}
+
+#[test]
+fn test_removing_children_keeps_lossless_spelling() {
+    let xml = "<rules>\n  <rule>&#39;Ard</rule>\n  <rule>Da &#x4A;ump</rule>\n</rules>";
+    let mut document = parse_str_lossless(xml).unwrap();
+    let rules = document.root.as_mut().unwrap();
+
+    // Drop the first rule and the whitespace after it
+    rules.remove_child(1).unwrap();
+    rules.remove_child(1).unwrap();
+
+    assert_eq!(
+        document_to_string(&document, &WriterOptions::lossless()),
+        "<rules>\n  <rule>Da &#x4A;ump</rule>\n</rules>"
+    );
+}
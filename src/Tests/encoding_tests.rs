@@ -0,0 +1,168 @@
+use crate::Tools::decompression::decompress_rosz_file;
+use crate::Tools::encoding::{decode_bytes, detect_encoding, Encoding, EncodingError};
+use crate::Tools::parser::{parse_bytes, XmlError};
+
+const ROSTER: &str = r#"<?xml version="1.0" encoding="UTF-16"?>
+<roster name="Légion of Waaagh" gameSystemName="Warhammer 40,000 10th Edition">
+  <forces>
+    <force name="Army Roster"/>
+  </forces>
+</roster>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Encode text as UTF-16, optionally with a byte order mark
+fn utf16_bytes(text: &str, little_endian: bool, bom: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let units = bom.then_some(0xFEFF).into_iter().chain(text.encode_utf16());
+    for unit in units {
+        if little_endian {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+    }
+    bytes
+}
+
+/// Parse and return the root's name attribute
+fn roster_name(bytes: &[u8]) -> String {
+    let document = parse_bytes(bytes).expect("Failed to parse bytes");
+    let root = document.get_root_element().expect("Missing root element");
+    root.get_attribute("name").cloned().unwrap_or_default()
+}
+
+// ============================================================================
+// DETECTION TESTS
+// ============================================================================
+
+#[test]
+fn test_detect_encoding_from_bom_and_declaration() {
+    assert_eq!(detect_encoding(b"<roster/>").unwrap(), (Encoding::Utf8, 0));
+    assert_eq!(
+        detect_encoding(b"\xEF\xBB\xBF<roster/>").unwrap(),
+        (Encoding::Utf8, 3)
+    );
+    assert_eq!(
+        detect_encoding(b"\xFF\xFE<\0r\0").unwrap(),
+        (Encoding::Utf16Le, 2)
+    );
+    assert_eq!(
+        detect_encoding(b"\xFE\xFF\0<\0r").unwrap(),
+        (Encoding::Utf16Be, 2)
+    );
+    assert_eq!(
+        detect_encoding(b"<?xml version='1.0' encoding = 'iso-8859-1'?><roster/>").unwrap(),
+        (Encoding::Latin1, 0)
+    );
+}
+
+#[test]
+fn test_declared_encoding_is_read_from_the_pseudo_attribute() {
+    // "encoding" elsewhere in the declaration is not the pseudo-attribute
+    assert_eq!(
+        detect_encoding(b"<?xml version='1.0' xencoding='latin1'?><roster/>").unwrap(),
+        (Encoding::Utf8, 0)
+    );
+    assert_eq!(
+        detect_encoding(b"<?xml version='encoding' encoding='latin1'?><roster/>").unwrap(),
+        (Encoding::Latin1, 0)
+    );
+    // Nor does anything outside an XML declaration count
+    assert_eq!(
+        detect_encoding(b"<?xml-stylesheet encoding='latin1'?><roster/>").unwrap(),
+        (Encoding::Utf8, 0)
+    );
+    assert_eq!(
+        detect_encoding(b"<?xml version='1.0'?><!-- encoding='latin1' --><roster/>").unwrap(),
+        (Encoding::Utf8, 0)
+    );
+}
+
+// ============================================================================
+// PARSE_BYTES TESTS
+// ============================================================================
+
+#[test]
+fn test_parse_bytes_utf8() {
+    let bytes = std::fs::read("example-data/Orks.cat").expect("Failed to read test file");
+
+    let document = parse_bytes(&bytes).expect("Failed to parse Orks.cat");
+
+    assert_eq!(document.get_root_element().unwrap().name, "catalogue");
+}
+
+#[test]
+fn test_parse_bytes_skips_utf8_bom() {
+    let mut bytes = b"\xEF\xBB\xBF".to_vec();
+    bytes.extend_from_slice(ROSTER.as_bytes());
+
+    assert_eq!(roster_name(&bytes), "Légion of Waaagh");
+}
+
+#[test]
+fn test_parse_bytes_transcodes_utf16() {
+    for little_endian in [true, false] {
+        for bom in [true, false] {
+            let bytes = utf16_bytes(ROSTER, little_endian, bom);
+            assert_eq!(roster_name(&bytes), "Légion of Waaagh");
+        }
+    }
+}
+
+#[test]
+fn test_parse_bytes_transcodes_latin1() {
+    let mut bytes = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n".to_vec();
+    bytes.extend_from_slice(b"<roster name=\"L\xE9gion of Waaagh\"/>");
+
+    assert_eq!(roster_name(&bytes), "Légion of Waaagh");
+}
+
+#[test]
+fn test_parse_bytes_reports_unsupported_encoding() {
+    let bytes = b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?><roster/>";
+
+    let error = parse_bytes(bytes).expect_err("Shift_JIS should be rejected");
+
+    assert!(matches!(
+        error,
+        XmlError::Encoding(EncodingError::UnsupportedEncoding { ref name }) if name == "Shift_JIS"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "unsupported encoding \"Shift_JIS\" (supported: UTF-8, UTF-16, ISO-8859-1)"
+    );
+}
+
+#[test]
+fn test_parse_bytes_reports_invalid_data() {
+    let error = parse_bytes(b"<roster name=\"L\xE9gion\"/>").expect_err("Invalid UTF-8");
+    assert_eq!(error.to_string(), "byte 15: invalid UTF-8 data");
+
+    // Unpaired surrogate after the BOM and "<"
+    let error = decode_bytes(b"\xFF\xFE<\0\x00\xD8r\0").expect_err("Invalid UTF-16");
+    assert!(matches!(
+        error,
+        EncodingError::InvalidData {
+            encoding: Encoding::Utf16Le,
+            offset: 4
+        }
+    ));
+}
+
+// ============================================================================
+// DECOMPRESSION TESTS
+// ============================================================================
+
+#[test]
+fn test_decompressed_files_keep_raw_bytes() {
+    let files = decompress_rosz_file("example-data/Test-sample-army.rosz")
+        .expect("Failed to decompress .rosz file");
+
+    for file in &files {
+        assert!(!file.raw.is_empty());
+        assert_eq!(decode_bytes(&file.raw).unwrap(), file.content);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::models::DocumentKind::DocumentKind;
+use crate::models::EditError::EditError;
+use crate::models::XmlDocument::XmlDocument;
+use crate::Tools::writer::{document_to_string, WriterOptions};
+use crate::{
+    document_from_json, document_from_json_str, document_to_json, document_to_json_string,
+    parse_file, parse_str_lossless, JsonError, JsonMode,
+};
+use serde_json::json;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Write a document compactly, so documents can be compared by their XML
+fn to_xml(document: &XmlDocument) -> String {
+    document_to_string(document, &WriterOptions::compact())
+}
+
+/// Convert to JSON and back
+fn round_trip(document: &XmlDocument, mode: JsonMode) -> XmlDocument {
+    let json = document_to_json_string(document, mode);
+    document_from_json_str(&json, mode).expect("Failed to read back JSON")
+}
+
+// ============================================================================
+// LOSSLESS TESTS
+// ============================================================================
+
+#[test]
+fn test_lossless_shape() {
+    let document = parse_str_lossless(
+        "<?xml version=\"1.0\"?><!-- exported --><cost name=\"pts\" value=\"5\">5 <![CDATA[<b>]]><?tag x?></cost>",
+    )
+    .unwrap();
+
+    assert_eq!(
+        document_to_json(&document, JsonMode::Lossless),
+        json!({
+            "declaration": {"version": "1.0"},
+            "prolog": [{"type": "comment", "value": " exported "}],
+            "root": {
+                "type": "element",
+                "name": "cost",
+                "attributes": {"name": "pts", "value": "5"},
+                "children": [
+                    {"type": "text", "value": "5 "},
+                    {"type": "cdata", "value": "<b>"},
+                    {"type": "processingInstruction", "target": "tag", "data": "x"},
+                ],
+            },
+        })
+    );
+}
+
+#[test]
+fn test_lossless_round_trip_keeps_every_node() {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+               <!DOCTYPE roster SYSTEM \"roster.dtd\">\n\
+               <roster z=\"1\" a=\"2\">\n  <!-- force -->\n  <force name=\"Army\"><![CDATA[x < y]]></force>\n</roster>\n\
+               <?done?>";
+    let document = parse_str_lossless(xml).unwrap();
+    let back = round_trip(&document, JsonMode::Lossless);
+
+    assert_eq!(to_xml(&back), to_xml(&document));
+    assert_eq!(back.declaration, document.declaration);
+    assert_eq!(back.doctype, document.doctype);
+    assert_eq!(back.root.as_ref().unwrap().children.len(), 5);
+    // Attribute order survives the JSON object
+    assert_eq!(
+        document_to_json(&back, JsonMode::Lossless),
+        document_to_json(&document, JsonMode::Lossless)
+    );
+}
+
+// ============================================================================
+// BADGERFISH TESTS
+// ============================================================================
+
+#[test]
+fn test_badgerfish_shape() {
+    let document = parse_str_lossless(
+        "<profile name=\"Boyz\"><!-- note --><characteristic name=\"M\">6&quot;</characteristic><characteristic name=\"T\">5</characteristic><keyword>Infantry</keyword><modifiers/></profile>",
+    )
+    .unwrap();
+
+    assert_eq!(
+        document_to_json(&document, JsonMode::BadgerFish),
+        json!({
+            "profile": {
+                "@name": "Boyz",
+                "characteristic": [
+                    {"@name": "M", "$": "6\""},
+                    {"@name": "T", "$": "5"},
+                ],
+                "keyword": "Infantry",
+                "modifiers": {},
+            }
+        })
+    );
+}
+
+#[test]
+fn test_badgerfish_reads_back_scalars_and_arrays() {
+    let value = json!({
+        "selection": {
+            "@name": "Boyz",
+            "@number": 10,
+            "@hidden": false,
+            "cost": [{"@value": 85.5}, {"@value": 0}],
+            "rule": "Waaagh!",
+            "note": null,
+        }
+    });
+    let document = document_from_json(&value, JsonMode::BadgerFish).unwrap();
+
+    let options = WriterOptions {
+        declaration: false,
+        space_before_slash: false,
+        ..WriterOptions::compact()
+    };
+    assert_eq!(
+        document_to_string(&document, &options),
+        "<selection name=\"Boyz\" number=\"10\" hidden=\"false\"><cost value=\"85.5\"/><cost value=\"0\"/><rule>Waaagh!</rule><note/></selection>"
+    );
+}
+
+// ============================================================================
+// ERROR TESTS
+// ============================================================================
+
+#[test]
+fn test_json_errors_point_at_the_value() {
+    let value = json!({
+        "root": {
+            "type": "element",
+            "name": "roster",
+            "children": [{"type": "element", "name": "bad name"}],
+        }
+    });
+    assert_eq!(
+        document_from_json(&value, JsonMode::Lossless).unwrap_err(),
+        JsonError::InvalidName {
+            path: "/root/children/0/name".to_string(),
+            name: "bad name".to_string(),
+        }
+    );
+
+    let value = json!({"root": {"type": "element", "name": "cost", "attributes": {"value": 5}}});
+    assert_eq!(
+        document_from_json(&value, JsonMode::Lossless)
+            .unwrap_err()
+            .to_string(),
+        "/root/attributes/value: expected a string"
+    );
+
+    let value = json!({"a": {}, "b": {}});
+    assert_eq!(
+        document_from_json(&value, JsonMode::BadgerFish)
+            .unwrap_err()
+            .to_string(),
+        "/: expected an object with the root element as its only key"
+    );
+
+    assert!(matches!(
+        document_from_json_str("{\"roster\": ", JsonMode::BadgerFish),
+        Err(JsonError::Syntax(_))
+    ));
+}
+
+#[test]
+fn test_json_content_that_would_not_be_well_formed_is_refused() {
+    let node = |node| json!({"root": {"type": "element", "name": "r", "children": [node]}});
+
+    assert_eq!(
+        document_from_json(
+            &node(json!({"type": "comment", "value": " a -- b "})),
+            JsonMode::Lossless
+        )
+        .unwrap_err(),
+        JsonError::InvalidContent {
+            path: "/root/children/0".to_string(),
+            error: EditError::InvalidComment(" a -- b ".to_string()),
+        }
+    );
+    assert!(matches!(
+        document_from_json(
+            &node(json!({"type": "comment", "value": "end-"})),
+            JsonMode::Lossless
+        ),
+        Err(JsonError::InvalidContent {
+            error: EditError::InvalidComment(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        document_from_json(
+            &node(json!({"type": "cdata", "value": "a]]>b"})),
+            JsonMode::Lossless
+        ),
+        Err(JsonError::InvalidContent {
+            error: EditError::InvalidCData(_),
+            ..
+        })
+    ));
+    assert!(matches!(
+        document_from_json(
+            &node(json!({"type": "processingInstruction", "target": "pi", "data": "?>"})),
+            JsonMode::Lossless
+        ),
+        Err(JsonError::InvalidContent {
+            error: EditError::InvalidProcessingInstructionData(_),
+            ..
+        })
+    ));
+    assert_eq!(
+        document_from_json(
+            &node(json!({"type": "text", "value": "\u{1}"})),
+            JsonMode::Lossless
+        )
+        .unwrap_err()
+        .to_string(),
+        "/root/children/0: U+0001 is not allowed in XML"
+    );
+
+    let value = json!({"cost": {"@name": "\u{0}"}});
+    assert_eq!(
+        document_from_json(&value, JsonMode::BadgerFish).unwrap_err(),
+        JsonError::InvalidContent {
+            path: "/cost/@name".to_string(),
+            error: EditError::InvalidCharacter('\u{0}'),
+        }
+    );
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_catalogue_round_trips_through_both_modes() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+
+    let lossless = round_trip(&document, JsonMode::Lossless);
+    assert_eq!(to_xml(&lossless), to_xml(&document));
+    assert_eq!(lossless.document_kind(), DocumentKind::Catalogue);
+
+    // BadgerFish groups children by name, so compare after one conversion has settled the order
+    let badgerfish = round_trip(&document, JsonMode::BadgerFish);
+    assert_eq!(badgerfish.document_kind(), DocumentKind::Catalogue);
+    let root = badgerfish.get_root_element().unwrap();
+    let original = document.get_root_element().unwrap();
+    assert_eq!(root.descendants().count(), original.descendants().count());
+    assert_eq!(
+        root.find_all("constraint").count(),
+        original.find_all("constraint").count()
+    );
+    assert_eq!(
+        to_xml(&round_trip(&badgerfish, JsonMode::BadgerFish)),
+        to_xml(&badgerfish)
+    );
+}
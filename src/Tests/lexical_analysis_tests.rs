@@ -0,0 +1,578 @@
+use crate::models::Attribute::Quote;
+use crate::models::DocumentType::DocumentType;
+use crate::models::Position::Position;
+use crate::models::XmlDeclaration::XmlDeclaration;
+use crate::Tools::lexical_analysis::{
+    decode_references, tokenize, tokenize_with, Lexer, Token, TokenizeError, TokenizerOptions,
+};
+use std::borrow::Cow;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Tokenize and drop the spans, for tests that only care about the token stream
+fn tokens_of(xml: &str) -> Result<Vec<Token<'_>>, TokenizeError> {
+    Ok(tokenize(xml)?
+        .into_iter()
+        .map(|spanned| spanned.token)
+        .collect())
+}
+
+// ============================================================================
+// ENTITY AND CHARACTER REFERENCE TESTS
+// ============================================================================
+
+#[test]
+fn test_decode_predefined_entities() {
+    let xml = r#"<catalogue authorContact="Dunamis55 &amp; AbonShell" range="10&quot;">&apos;Ard Case &lt;Vehicle&gt;</catalogue>"#;
+
+    let tokens = tokens_of(xml).expect("Failed to tokenize XML with entities");
+
+    assert!(tokens.contains(&Token::Attribute(
+        "authorContact".into(),
+        "Dunamis55 & AbonShell".into(),
+        Quote::Double
+    )));
+    assert!(tokens.contains(&Token::Attribute(
+        "range".into(),
+        "10\"".into(),
+        Quote::Double
+    )));
+    assert!(tokens.contains(&Token::Text("'Ard Case <Vehicle>".into())));
+}
+
+#[test]
+fn test_decode_numeric_character_references() {
+    assert_eq!(decode_references("Ork&#8217;s").unwrap(), "Ork\u{2019}s");
+    assert_eq!(decode_references("Ork&#x2019;s").unwrap(), "Ork\u{2019}s");
+    assert!(decode_references("&#65;&#x42;&#X43;").is_err());
+    assert_eq!(decode_references("&#65;&#x42;").unwrap(), "AB");
+}
+
+#[test]
+fn test_decode_without_references_borrows_input() {
+    let decoded = decode_references("Waaagh!").unwrap();
+    assert!(matches!(decoded, std::borrow::Cow::Borrowed("Waaagh!")));
+}
+
+#[test]
+fn test_unknown_entity_is_rejected() {
+    let result = tokenize(r#"<profile name="&nbsp;Boyz"/>"#);
+    assert!(matches!(
+        result,
+        Err(TokenizeError::InvalidReference { ref reference, position })
+            if reference == "&nbsp;" && position.column == 16
+    ));
+}
+
+#[test]
+fn test_malformed_references_are_rejected() {
+    for raw in [
+        "Dunamis55 & AbonShell",
+        "&amp",
+        "&#;",
+        "&#xZZ;",
+        "&#0;",
+        "&#1114112;",
+    ] {
+        assert!(
+            matches!(
+                decode_references(raw),
+                Err(TokenizeError::InvalidReference { .. })
+            ),
+            "Expected {:?} to be rejected",
+            raw
+        );
+    }
+}
+
+#[test]
+fn test_orks_catalogue_attributes_are_decoded() {
+    let xml_content =
+        std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+
+    let tokens = tokens_of(&xml_content).expect("Failed to tokenize Orks.cat");
+
+    assert!(tokens.contains(&Token::Attribute(
+        "authorContact".into(),
+        "Dunamis55 & AbonShell".into(),
+        Quote::Double
+    )));
+    assert!(!tokens.iter().any(|token| match token {
+        Token::Attribute(_, value, _) | Token::Text(value) => value.contains("&quot;"),
+        _ => false,
+    }));
+}
+
+// ============================================================================
+// SOURCE POSITION TESTS
+// ============================================================================
+
+#[test]
+fn test_tokens_carry_spans() {
+    let xml = "<roster>\n  <force name=\"Orks\">Boyz</force>\n</roster>";
+    let tokens = tokenize(xml).expect("Failed to tokenize XML");
+
+    let force = tokens
+        .iter()
+        .find(|spanned| spanned.token == Token::OpenTag("force".into()))
+        .expect("Should find force open tag");
+    assert_eq!(force.span.start.line, 2);
+    assert_eq!(force.span.start.column, 3);
+    assert_eq!(&xml[force.span.byte_range()], "<force name=\"Orks\">");
+
+    let attribute = tokens
+        .iter()
+        .find(|spanned| matches!(spanned.token, Token::Attribute(..)))
+        .expect("Should find attribute");
+    assert_eq!(&xml[attribute.span.byte_range()], "name=\"Orks\"");
+
+    let text = tokens
+        .iter()
+        .find(|spanned| spanned.token == Token::Text("Boyz".into()))
+        .expect("Should find text");
+    assert_eq!(&xml[text.span.byte_range()], "Boyz");
+
+    let close = tokens
+        .iter()
+        .find(|spanned| spanned.token == Token::CloseTag("roster".into()))
+        .expect("Should find roster close tag");
+    assert_eq!(close.span.start.line, 3);
+    assert_eq!(close.span.start.column, 1);
+}
+
+#[test]
+fn test_columns_count_characters_not_bytes() {
+    let xml = "<a>Waaagh\u{2019}</a><b/>";
+    let tokens = tokenize(xml).expect("Failed to tokenize XML");
+
+    let b = tokens
+        .iter()
+        .find(|spanned| spanned.token == Token::SelfClosingTag("b".into()))
+        .expect("Should find b");
+    assert_eq!(b.span.start.column, 15);
+    assert_eq!(b.span.start.offset, 16);
+}
+
+#[test]
+fn test_tokenize_errors_carry_positions() {
+    let result = tokenize("<roster>\n  <force name=Orks/>\n</roster>");
+    match result {
+        Err(TokenizeError::MalformedAttribute { position }) => {
+            assert_eq!(
+                position,
+                Position {
+                    offset: 23,
+                    line: 2,
+                    column: 15
+                }
+            );
+        }
+        other => panic!("Expected MalformedAttribute, got {:?}", other),
+    }
+
+    let error = tokenize("<roster>\n<!- comment -->").unwrap_err();
+    assert_eq!(error.to_string(), "2:2: malformed tag");
+}
+
+// ============================================================================
+// CDATA TESTS
+// ============================================================================
+
+#[test]
+fn test_cdata_section_is_tokenized_verbatim() {
+    let xml = "<description><![CDATA[Roll 2D6 & add <Toughness> ]] >]]></description>";
+    let tokens = tokens_of(xml).expect("Failed to tokenize CDATA");
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::OpenTag("description".into()),
+            Token::CData("Roll 2D6 & add <Toughness> ]] >".into()),
+            Token::CloseTag("description".into()),
+            Token::EndOfFile,
+        ]
+    );
+}
+
+#[test]
+fn test_unterminated_cdata_section_fails() {
+    let result = tokenize("<description><![CDATA[Waaagh!</description>");
+    assert!(matches!(
+        result,
+        Err(TokenizeError::UnexpectedEndOfInput { .. })
+    ));
+
+    let result = tokenize("<description><![CDAT[Waaagh!]]></description>");
+    assert!(matches!(result, Err(TokenizeError::MalformedTag { .. })));
+}
+
+// ============================================================================
+// PROLOG TESTS
+// ============================================================================
+
+#[test]
+fn test_xml_declaration_pseudo_attributes() {
+    let tokens = tokens_of(r#"<?xml version="1.0" encoding='UTF-8' standalone="yes"?><roster/>"#)
+        .expect("Failed to tokenize declaration");
+
+    assert_eq!(
+        tokens[0],
+        Token::XmlDeclaration(XmlDeclaration {
+            version: "1.0".into(),
+            encoding: Some("UTF-8".into()),
+            standalone: Some(true),
+        })
+    );
+}
+
+#[test]
+fn test_malformed_xml_declarations_are_rejected() {
+    for xml in [
+        r#"<?xml encoding="UTF-8"?><roster/>"#,
+        r#"<?xml version="1.0" standalone="maybe"?><roster/>"#,
+        r#"<?xml version="1.0" colour="green"?><roster/>"#,
+        r#"<?xml version=1.0?><roster/>"#,
+    ] {
+        assert!(
+            matches!(
+                tokenize(xml),
+                Err(TokenizeError::MalformedDeclaration { .. })
+            ),
+            "Expected {:?} to be rejected",
+            xml
+        );
+    }
+}
+
+#[test]
+fn test_processing_instructions_are_kept() {
+    let tokens =
+        tokens_of(r#"<?xml-stylesheet type="text/xsl" href="roster.xsl"?><?page-break?><roster/>"#)
+            .expect("Failed to tokenize processing instructions");
+
+    assert_eq!(
+        tokens[0],
+        Token::ProcessingInstruction {
+            target: "xml-stylesheet".into(),
+            data: r#"type="text/xsl" href="roster.xsl""#.into(),
+        }
+    );
+    assert_eq!(
+        tokens[1],
+        Token::ProcessingInstruction {
+            target: "page-break".into(),
+            data: "".into(),
+        }
+    );
+}
+
+#[test]
+fn test_doctype_with_internal_subset() {
+    let xml = r#"<!DOCTYPE roster SYSTEM "roster.dtd" [
+  <!ENTITY waaagh "Waaagh!">
+  <!ATTLIST roster note CDATA "]>">
+]>
+<roster/>"#;
+
+    let tokens = tokens_of(xml).expect("Failed to tokenize DOCTYPE");
+
+    assert_eq!(
+        tokens[0],
+        Token::Doctype(DocumentType {
+            name: "roster".into(),
+            external_id: Some(r#"SYSTEM "roster.dtd""#.into()),
+            internal_subset: Some(
+                "\n  <!ENTITY waaagh \"Waaagh!\">\n  <!ATTLIST roster note CDATA \"]>\">\n"
+                    .to_string()
+            ),
+        })
+    );
+    assert_eq!(tokens[1], Token::SelfClosingTag("roster".into()));
+}
+
+#[test]
+fn test_simple_doctype() {
+    let tokens = tokens_of("<!DOCTYPE catalogue><catalogue/>").expect("Failed to tokenize");
+
+    assert_eq!(
+        tokens[0],
+        Token::Doctype(DocumentType {
+            name: "catalogue".into(),
+            external_id: None,
+            internal_subset: None,
+        })
+    );
+}
+
+// ============================================================================
+// WHITESPACE MODE TESTS
+// ============================================================================
+
+const WAAAGH_DESCRIPTION: &str = "<rule name=\"Waaagh!\">\n  <description>\n  Once per battle:\n    - Advance and charge\n    - 5+ invulnerable save\n  </description>\n  <!--  keep me  -->\n</rule>";
+
+#[test]
+fn test_default_mode_trims_text() {
+    let tokens = tokens_of(WAAAGH_DESCRIPTION).expect("Failed to tokenize");
+
+    assert!(tokens.contains(&Token::Text(
+        "Once per battle:\n    - Advance and charge\n    - 5+ invulnerable save".into()
+    )));
+    assert!(tokens.contains(&Token::Comment("keep me".into())));
+    assert!(!tokens
+        .iter()
+        .any(|token| matches!(token, Token::Text(text) if text.trim().is_empty())));
+}
+
+#[test]
+fn test_preserve_mode_keeps_whitespace_exactly() {
+    let tokens = tokenize_with(WAAAGH_DESCRIPTION, &TokenizerOptions::preserve_whitespace())
+        .expect("Failed to tokenize");
+
+    let texts: Vec<&str> = tokens
+        .iter()
+        .filter_map(|spanned| match &spanned.token {
+            Token::Text(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        texts,
+        vec![
+            "\n  ",
+            "\n  Once per battle:\n    - Advance and charge\n    - 5+ invulnerable save\n  ",
+            "\n  ",
+            "\n",
+        ]
+    );
+    assert!(tokens
+        .iter()
+        .any(|spanned| spanned.token == Token::Comment("  keep me  ".into())));
+
+    // Every text span maps back onto the exact source text
+    for spanned in &tokens {
+        if let Token::Text(text) = &spanned.token {
+            assert_eq!(&WAAAGH_DESCRIPTION[spanned.span.byte_range()], text);
+        }
+    }
+}
+
+#[test]
+fn test_collapse_mode_normalises_whitespace() {
+    let tokens = tokenize_with(WAAAGH_DESCRIPTION, &TokenizerOptions::collapse_whitespace())
+        .expect("Failed to tokenize");
+
+    assert!(tokens.iter().any(|spanned| spanned.token
+        == Token::Text("Once per battle: - Advance and charge - 5+ invulnerable save".into())));
+}
+
+#[test]
+fn test_xml_space_preserve_is_honoured() {
+    let xml = "<rules>\n  <rule xml:space=\"preserve\">\n    <description>  indented\n  text </description>\n    <name xml:space=\"default\">  Waaagh!  </name>\n  </rule>\n  <rule>  trimmed  </rule>\n</rules>";
+
+    let tokens = tokens_of(xml).expect("Failed to tokenize");
+    let texts: Vec<&str> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        texts,
+        vec![
+            "\n    ",
+            "  indented\n  text ",
+            "\n    ",
+            "Waaagh!",
+            "\n  ",
+            "trimmed",
+        ]
+    );
+}
+
+#[test]
+fn test_character_references_survive_trimming() {
+    let tokens = tokens_of("<name>&#32;Boyz&#x20;</name>").expect("Failed to tokenize");
+    assert!(tokens.contains(&Token::Text(" Boyz ".into())));
+}
+
+// ============================================================================
+// BYTE LEXER TESTS
+// ============================================================================
+
+#[test]
+fn test_streaming_lexer_matches_tokenize() {
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+
+    let collected = tokenize(&xml).expect("Failed to tokenize Orks.cat");
+    let streamed: Vec<_> = Lexer::new(&xml, &TokenizerOptions::default())
+        .map(|token| token.expect("Failed to lex Orks.cat"))
+        .collect();
+
+    assert_eq!(collected, streamed);
+    assert_eq!(
+        streamed.last().map(|spanned| &spanned.token),
+        Some(&Token::EndOfFile)
+    );
+    assert_eq!(streamed.last().unwrap().span.end.offset, xml.len());
+}
+
+#[test]
+fn test_tokens_borrow_from_input() {
+    let xml = r#"<rule name="Waaagh!" note="Orks &amp; Grots">Plain text</rule>"#;
+
+    let tokens = tokens_of(xml).expect("Failed to tokenize");
+
+    assert!(matches!(
+        &tokens[0],
+        Token::Attribute(Cow::Borrowed("name"), Cow::Borrowed("Waaagh!"), _)
+    ));
+    // Only values that needed decoding are copied
+    assert!(
+        matches!(&tokens[1], Token::Attribute(Cow::Borrowed("note"), Cow::Owned(value), _) if value == "Orks & Grots")
+    );
+    assert!(matches!(&tokens[2], Token::OpenTag(Cow::Borrowed("rule"))));
+    assert!(matches!(
+        &tokens[3],
+        Token::Text(Cow::Borrowed("Plain text"))
+    ));
+    assert!(matches!(&tokens[4], Token::CloseTag(Cow::Borrowed("rule"))));
+}
+
+#[test]
+fn test_lexer_stops_after_error() {
+    let mut lexer = Lexer::new("<a><!x><b/>", &TokenizerOptions::default());
+
+    assert!(matches!(lexer.next(), Some(Ok(_))));
+    assert!(matches!(
+        lexer.next(),
+        Some(Err(TokenizeError::MalformedTag { .. }))
+    ));
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+fn test_positions_count_characters_not_bytes() {
+    let xml = "<name>Ork\u{2019}s Choppa</name>\n<rule/>";
+
+    let tokens = tokenize(xml).expect("Failed to tokenize");
+    let close = &tokens[2];
+    let rule = &tokens[3];
+
+    assert_eq!(close.token, Token::CloseTag("name".into()));
+    assert_eq!(close.span.start.column, 19);
+    assert_eq!(close.span.start.offset, 20);
+    assert_eq!(rule.span.start.line, 2);
+    assert_eq!(rule.span.start.column, 1);
+}
+
+// ============================================================================
+// STRICT MODE TESTS
+// ============================================================================
+
+/// Tokenize in strict mode and return the error, checking lenient mode accepts the input
+fn strict_error(xml: &str) -> TokenizeError {
+    tokenize(xml).expect("Lenient mode should accept the input");
+    tokenize_with(xml, &TokenizerOptions::strict())
+        .expect_err("Strict mode should reject the input")
+}
+
+#[test]
+fn test_strict_mode_accepts_example_data() {
+    for path in [
+        "example-data/Orks.cat",
+        "example-data/Test-Chaos-Thousand Sons.cat",
+        "example-data/Tts game teams.ros",
+    ] {
+        let xml = std::fs::read_to_string(path).expect("Failed to read test file");
+        let strict = tokenize_with(&xml, &TokenizerOptions::strict())
+            .unwrap_or_else(|error| panic!("{}:{}", path, error));
+        assert_eq!(strict, tokenize(&xml).unwrap());
+    }
+}
+
+#[test]
+fn test_strict_mode_rejects_invalid_names() {
+    let error = strict_error(r#"<1st-company/>"#);
+    assert!(matches!(error, TokenizeError::InvalidName { ref name, .. } if name == "1st-company"));
+    assert_eq!(error.to_string(), "1:2: invalid name \"1st-company\"");
+
+    let error = strict_error(r#"<unit pts$="10"/>"#);
+    assert!(matches!(error, TokenizeError::InvalidName { ref name, .. } if name == "pts$"));
+
+    let error = strict_error("<unit></unit x>");
+    assert!(matches!(error, TokenizeError::InvalidName { ref name, .. } if name == "unit x"));
+
+    // Letters outside ASCII, digits after the first character, '.', '-' and ':' are fine
+    let tokens = tokenize_with(
+        r#"<émpire:unit-2.0 xml:lang="en" _id="1"/>"#,
+        &TokenizerOptions::strict(),
+    );
+    assert!(tokens.is_ok());
+}
+
+#[test]
+fn test_strict_mode_rejects_duplicate_attributes() {
+    let error = strict_error(r#"<cost name="pts" value="10" name="PL"/>"#);
+
+    assert!(matches!(error, TokenizeError::DuplicateAttribute { ref name, .. } if name == "name"));
+    assert_eq!(error.position().column, 29);
+}
+
+#[test]
+fn test_strict_mode_requires_whitespace_between_attributes() {
+    let error = strict_error(r#"<cost name="pts"value="10"/>"#);
+
+    assert!(matches!(
+        error,
+        TokenizeError::MissingAttributeWhitespace { .. }
+    ));
+    assert_eq!(error.position().column, 17);
+}
+
+#[test]
+fn test_strict_mode_rejects_less_than_in_attribute_values() {
+    let error = strict_error(r#"<characteristic name="<Toughness>"/>"#);
+
+    assert!(matches!(
+        error,
+        TokenizeError::LessThanInAttributeValue { .. }
+    ));
+    assert_eq!(error.to_string(), "1:23: '<' in attribute value");
+}
+
+#[test]
+fn test_strict_mode_rejects_double_hyphen_in_comments() {
+    let error = strict_error("<roster><!-- points -- pending --></roster>");
+    assert!(matches!(error, TokenizeError::DoubleHyphenInComment { .. }));
+    assert_eq!(error.position().column, 21);
+
+    let error = strict_error("<roster><!-- ends with a hyphen ---></roster>");
+    assert!(matches!(error, TokenizeError::DoubleHyphenInComment { .. }));
+}
+
+#[test]
+fn test_strict_mode_rejects_illegal_characters() {
+    let error = strict_error("<name>Ork\u{1}Boyz</name>");
+    assert!(matches!(
+        error,
+        TokenizeError::IllegalCharacter {
+            character: '\u{1}',
+            ..
+        }
+    ));
+    assert_eq!(error.to_string(), "1:10: illegal character U+0001");
+
+    let error = strict_error("<name note=\"\u{FFFF}\"/>");
+    assert!(matches!(
+        error,
+        TokenizeError::IllegalCharacter {
+            character: '\u{FFFF}',
+            ..
+        }
+    ));
+}
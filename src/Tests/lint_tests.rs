@@ -0,0 +1,178 @@
+use crate::models::DocumentKind::DocumentKind;
+use crate::{lint_document, lint_element, parse_file, parse_rosz, parse_str, LintKind, LintReport};
+
+const CATALOGUE: &str = r#"<catalogue xmlns="http://www.battlescribe.net/schema/catalogueSchema" id="c1" name="Orks" revision="1" gameSystemId="g1" type="catalogue">
+  <sharedSelectionEntries>
+    <selectionEntry id="e1" name="Boyz" type="unit" hidden="false">
+      <constraints>
+        <constraint type="max" value="1" field="selections" scope="parent" shared="true" id="k1"/>
+      </constraints>
+      <modifiers>
+        <modifier type="increment" field="k1" value="1"/>
+      </modifiers>
+    </selectionEntry>
+  </sharedSelectionEntries>
+</catalogue>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Lint an XML string
+fn lint(xml: &str) -> LintReport {
+    lint_document(&parse_str(xml).expect("Failed to parse XML"))
+}
+
+// ============================================================================
+// RULE TESTS
+// ============================================================================
+
+#[test]
+fn test_clean_catalogue() {
+    let report = lint(CATALOGUE);
+    assert_eq!(report.document_kind, DocumentKind::Catalogue);
+    assert!(report.is_clean(), "{}", report);
+}
+
+#[test]
+fn test_missing_attributes() {
+    let report = lint(
+        &CATALOGUE
+            .replace(" field=\"selections\"", "")
+            .replace(" id=\"k1\"", ""),
+    );
+
+    let issues: Vec<_> = report.issues_of(LintKind::MissingAttribute).collect();
+    assert_eq!(issues.len(), 2);
+    assert_eq!(
+        issues[0].path,
+        "catalogue/sharedSelectionEntries/selectionEntry/constraints/constraint"
+    );
+    assert_eq!(
+        issues[0].message,
+        "<constraint> is missing required attribute \"id\""
+    );
+    assert_eq!(issues[0].suggestion.as_deref(), Some("add id=\"...\""));
+    assert_eq!(
+        issues[1].message,
+        "<constraint> is missing required attribute \"field\""
+    );
+    assert_eq!(issues[0].position.unwrap().line, 5);
+}
+
+#[test]
+fn test_misspelt_attribute_is_suggested() {
+    let report = lint(
+        "<roster xmlns='http://www.battlescribe.net/schema/rosterSchema' id='r' name='R' gameSystemId='g'>\
+           <forces><force id='f' name='F' entryId='e' catalogueId='c'>\
+             <selections><selection id='s' name='Boyz' entryID='e2' number='10' type='unit'/></selections>\
+           </force></forces>\
+         </roster>",
+    );
+    assert_eq!(report.document_kind, DocumentKind::Roster);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(
+        report.issues[0].to_string(),
+        "1:169: roster/forces/force/selections/selection: <selection> is missing required attribute \"entryId\" (rename \"entryID\" to \"entryId\")"
+    );
+}
+
+#[test]
+fn test_invalid_values() {
+    let report = lint(
+        &CATALOGUE
+            .replace("\"increment\"", "\"incremnt\"")
+            .replace("hidden=\"false\"", "hidden=\"False\"")
+            .replace("type=\"max\"", "type=\"most\""),
+    );
+
+    let suggestions: Vec<_> = report
+        .issues_of(LintKind::InvalidValue)
+        .map(|issue| issue.suggestion.as_deref())
+        .collect();
+    assert_eq!(
+        suggestions,
+        vec![
+            Some("change it to \"false\""),
+            None,
+            Some("change it to \"increment\""),
+        ]
+    );
+    assert_eq!(
+        report.issues[2].message,
+        "attribute \"type\" is \"incremnt\", expected one of set, increment, decrement, append, add, remove, set-primary, unset-primary"
+    );
+}
+
+#[test]
+fn test_unknown_elements() {
+    let report = lint(&CATALOGUE.replace("constraints>", "constrants>"));
+    assert_eq!(report.issues.len(), 1);
+    let issue = &report.issues[0];
+    assert_eq!(issue.kind, LintKind::UnknownElement);
+    assert_eq!(issue.message, "<constrants> does not belong in a catalogue");
+    assert_eq!(
+        issue.suggestion.as_deref(),
+        Some("rename it to <constraints>")
+    );
+
+    // Roster elements are unknown in a catalogue, and extension elements are left alone
+    let report = lint(&CATALOGUE.replace(
+        "<modifiers>",
+        "<selections xmlns:x='urn:x'><x:note/></selections><modifiers>",
+    ));
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(
+        report.issues[0].path,
+        "catalogue/sharedSelectionEntries/selectionEntry/selections"
+    );
+
+    let report = lint("<catalog id='c'/>");
+    assert_eq!(report.document_kind, DocumentKind::Unknown);
+    assert_eq!(
+        report.issues[0].suggestion.as_deref(),
+        Some("rename it to <catalogue>")
+    );
+}
+
+#[test]
+fn test_kind_from_root_name_without_namespace() {
+    let document = parse_str("<gameSystem id='g' name='40k' revision='1'><costTypes><costType id='pts' name='pts'/></costTypes></gameSystem>").unwrap();
+    let report = lint_document(&document);
+    assert_eq!(report.document_kind, DocumentKind::GameSystem);
+    assert!(report.is_clean(), "{}", report);
+
+    // The same element linted as a roster knows none of the game system elements
+    let issues = lint_element(document.get_root_element().unwrap(), DocumentKind::Roster);
+    assert_eq!(issues.len(), 3);
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_example_data() {
+    let orks = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    assert!(lint_document(&orks).is_clean());
+
+    for path in [
+        "example-data/Tts game teams.ros",
+        "example-data/Tts game teams .ros",
+    ] {
+        let roster = parse_file(path).expect("Failed to parse roster");
+        assert!(lint_document(&roster).is_clean(), "{}", path);
+    }
+    for file in parse_rosz("example-data/Tts Ork game teams.rosz").unwrap() {
+        assert!(lint_document(&file.document).is_clean());
+    }
+
+    // The one issue in the example data: a "floor" modifier, outside the BattleScribe modifier types
+    let sons = parse_file("example-data/Test-Chaos-Thousand Sons.cat")
+        .expect("Failed to parse Thousand Sons catalogue");
+    let report = lint_document(&sons);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].kind, LintKind::InvalidValue);
+    assert!(report.issues[0].message.contains("\"floor\""));
+    assert_eq!(report.issues[0].position.unwrap().line, 3771);
+}
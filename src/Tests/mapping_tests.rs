@@ -0,0 +1,230 @@
+use crate::models::Constraint::Constraint;
+use crate::models::ConstraintType::ConstraintType;
+use crate::{parse_file, parse_str, FromXmlElement, FromXmlError};
+
+const PROFILE: &str = r#"<profile id="p1" name="Battlewagon" typeName="Unit" hidden="false">
+  <characteristics>
+    <characteristic name="M" typeId="m">10&quot;</characteristic>
+    <characteristic name="T" typeId="t">10</characteristic>
+  </characteristics>
+  <keywords>
+    <keyword>Vehicle</keyword>
+    <keyword>Transport</keyword>
+  </keywords>
+  <description>Da biggest.</description>
+</profile>"#;
+
+#[derive(Debug, PartialEq, FromXmlElement)]
+struct Profile {
+    id: String,
+    name: String,
+    #[xml(attr = "typeName")]
+    kind: String,
+    hidden: bool,
+    publication_id: Option<String>,
+    #[xml(child = "characteristics/characteristic", many)]
+    characteristics: Vec<Characteristic>,
+    #[xml(child = "keywords/keyword", many)]
+    keywords: Vec<String>,
+    #[xml(child = "description")]
+    description: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromXmlElement)]
+struct Characteristic {
+    name: String,
+    #[xml(attr = "typeId")]
+    type_id: String,
+    #[xml(text)]
+    value: String,
+}
+
+#[derive(Debug, PartialEq, FromXmlElement)]
+struct Cost {
+    name: String,
+    #[xml(default)]
+    value: f64,
+    #[xml(attr = "typeId", default = "default_cost_type")]
+    type_id: String,
+}
+
+fn default_cost_type() -> String {
+    "points".to_string()
+}
+
+/// Convert the root element of an XML string
+fn convert<T: FromXmlElement>(xml: &str) -> Result<T, FromXmlError> {
+    let document = parse_str(xml).expect("Failed to parse XML");
+    T::from_xml_element(document.get_root_element().unwrap())
+}
+
+// ============================================================================
+// CONVERSION TESTS
+// ============================================================================
+
+#[test]
+fn test_derive_reads_attributes_children_and_text() {
+    let profile: Profile = convert(PROFILE).unwrap();
+
+    assert_eq!(
+        profile,
+        Profile {
+            id: "p1".to_string(),
+            name: "Battlewagon".to_string(),
+            kind: "Unit".to_string(),
+            hidden: false,
+            publication_id: None,
+            characteristics: vec![
+                Characteristic {
+                    name: "M".to_string(),
+                    type_id: "m".to_string(),
+                    value: "10\"".to_string(),
+                },
+                Characteristic {
+                    name: "T".to_string(),
+                    type_id: "t".to_string(),
+                    value: "10".to_string(),
+                },
+            ],
+            keywords: vec!["Vehicle".to_string(), "Transport".to_string()],
+            description: Some("Da biggest.".to_string()),
+        }
+    );
+}
+
+#[test]
+fn test_defaults_and_empty_child_lists() {
+    let cost: Cost = convert("<cost name='pts'/>").unwrap();
+    assert_eq!(
+        cost,
+        Cost {
+            name: "pts".to_string(),
+            value: 0.0,
+            type_id: "points".to_string(),
+        }
+    );
+
+    let profile: Profile = convert("<profile id='p2' name='Grot' typeName='Unit' hidden='1'/>")
+        .expect("Child lists and optional children may be missing");
+    assert!(profile.hidden);
+    assert!(profile.characteristics.is_empty());
+    assert_eq!(profile.description, None);
+}
+
+#[test]
+fn test_with_replaces_from_xml_value() {
+    fn shouted(value: &str) -> Result<String, &'static str> {
+        match value.is_empty() {
+            true => Err("a non-empty string"),
+            false => Ok(value.to_uppercase()),
+        }
+    }
+
+    #[derive(Debug, FromXmlElement)]
+    struct Rule {
+        #[xml(with = "shouted")]
+        name: String,
+        #[xml(text, with = "shouted")]
+        description: Option<String>,
+    }
+
+    let rule: Rule = convert("<rule name='waaagh'>charge</rule>").unwrap();
+    assert_eq!(rule.name, "WAAAGH");
+    assert_eq!(rule.description.as_deref(), Some("CHARGE"));
+
+    let error = convert::<Rule>("<rule name=''/>").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "rule: invalid value \"\" for attribute \"name\", expected a non-empty string"
+    );
+}
+
+// ============================================================================
+// ERROR TESTS
+// ============================================================================
+
+#[test]
+fn test_errors_name_attribute_and_element_path() {
+    let error = convert::<Profile>(&PROFILE.replace(" typeId=\"t\"", "")).unwrap_err();
+    assert_eq!(
+        error,
+        FromXmlError::MissingAttribute {
+            path: "profile/characteristics/characteristic[2]".to_string(),
+            attribute: "typeId".to_string(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "profile/characteristics/characteristic[2]: missing attribute \"typeId\""
+    );
+
+    let error =
+        convert::<Profile>(&PROFILE.replace("hidden=\"false\"", "hidden=\"no\"")).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "profile: invalid value \"no\" for attribute \"hidden\", expected a boolean"
+    );
+
+    let error = convert::<Cost>("<cost name='pts' value='lots'/>").unwrap_err();
+    assert_eq!(error.path(), "cost");
+}
+
+#[test]
+fn test_missing_children_and_text() {
+    #[derive(Debug, FromXmlElement)]
+    struct Entry {
+        #[xml(child = "profiles/profile")]
+        #[allow(dead_code)]
+        profile: Profile,
+    }
+
+    let error = convert::<Entry>("<selectionEntry><profiles/></selectionEntry>").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "selectionEntry: missing child element <profiles/profile>"
+    );
+
+    let xml = format!(
+        "<selectionEntry><profiles>{}</profiles></selectionEntry>",
+        PROFILE
+    )
+    .replace("<keyword>Transport</keyword>", "<keyword/>");
+    let error = convert::<Entry>(&xml).unwrap_err();
+    assert_eq!(
+        error,
+        FromXmlError::MissingText {
+            path: "selectionEntry/profiles/profile/keywords/keyword[2]".to_string()
+        }
+    );
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_constraints_from_catalogue() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let root = document.get_root_element().unwrap();
+
+    let constraints: Vec<Constraint> = root
+        .find_all("constraint")
+        .map(Constraint::from_xml_element)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(constraints.len() > 1000);
+    assert_eq!(constraints[0].constraint_type, ConstraintType::Max);
+    assert!(constraints.iter().all(|c| !c.id.is_empty()));
+
+    let profiles: Vec<Profile> = root
+        .find_all("profile")
+        .filter(|profile| profile.get_attribute("typeName").unwrap() == "Unit")
+        .map(Profile::from_xml_element)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(!profiles.is_empty());
+    assert!(profiles.iter().all(|profile| profile
+        .characteristics
+        .iter()
+        .all(|c| !c.type_id.is_empty())));
+}
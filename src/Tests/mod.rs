@@ -1,4 +1,38 @@
+#[cfg(test)]
+pub mod arena_tests;
+#[cfg(test)]
 pub mod decompression_tests;
+#[cfg(test)]
+pub mod edit_tests;
+#[cfg(test)]
+pub mod encoding_tests;
+#[cfg(all(test, feature = "json"))]
+pub mod json_tests;
+#[cfg(test)]
+pub mod lexical_analysis_tests;
+#[cfg(test)]
+pub mod lint_tests;
+#[cfg(test)]
+pub mod mapping_tests;
+#[cfg(test)]
 pub mod parse_tokens_tests;
+#[cfg(test)]
+pub mod parser_tests;
+#[cfg(test)]
+pub mod reader_tests;
+#[cfg(test)]
 pub mod roster_validation_tests;
+#[cfg(test)]
+pub mod schema_tests;
+#[cfg(test)]
+pub mod selector_tests;
+#[cfg(all(test, feature = "serde"))]
+pub mod serde_tests;
+#[cfg(test)]
+pub mod traversal_tests;
+#[cfg(test)]
 pub mod validator_tests;
+#[cfg(test)]
+pub mod writer_tests;
+#[cfg(test)]
+pub mod xpath_tests;
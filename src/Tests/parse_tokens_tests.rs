@@ -1,8 +1,14 @@
+use crate::models::Attribute::Quote;
+use crate::models::Diagnostic::DiagnosticKind;
+use crate::models::DocumentKind::{DocumentKind, CATALOGUE_NAMESPACE};
+use crate::models::NamespaceScope::XML_NAMESPACE;
 use crate::models::XmlDocument::XmlDocument;
 use crate::models::XmlElement::XmlElement;
 use crate::models::XmlNode::XmlNode;
 use crate::Tools::lexical_analysis::tokenize;
-use crate::Tools::parse_tokens::parse_tokens;
+use crate::Tools::parse_tokens::{parse_tokens, parse_tokens_recovering, ParseError};
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema";
 
 #[test]
 fn test_parse_chaos_thousand_sons_cat() {
@@ -275,3 +281,387 @@ fn test_parse_comments() {
 
     assert!(comment_count > 0, "Should have at least one comment");
 }
+
+#[test]
+fn test_elements_remember_their_span() {
+    let xml =
+        "<profiles>\n  <profile name=\"Boyz\">\n    <characteristics/>\n  </profile>\n</profiles>";
+
+    let tokens = tokenize(xml).expect("Failed to tokenize XML");
+    let document = parse_tokens(tokens).expect("Failed to parse XML");
+    let root = document.get_root_element().expect("Should have root");
+
+    let root_span = root.span.expect("Root should have a span");
+    assert_eq!(root_span.byte_range(), 0..xml.len());
+
+    let profile = root.find_child_by_name("profile").unwrap();
+    let profile_span = profile.span.expect("Profile should have a span");
+    assert_eq!(profile_span.start.line, 2);
+    assert_eq!(profile_span.start.column, 3);
+    assert_eq!(profile_span.end.line, 4);
+    assert!(xml[profile_span.byte_range()].starts_with("<profile name"));
+    assert!(xml[profile_span.byte_range()].ends_with("</profile>"));
+
+    let characteristics = profile.find_child_by_name("characteristics").unwrap();
+    assert_eq!(
+        &xml[characteristics.span.unwrap().byte_range()],
+        "<characteristics/>"
+    );
+}
+
+#[test]
+fn test_mismatched_tags_report_position_and_names() {
+    let xml = "<profiles>\n  <profile>\n  </profiles>\n</profile>";
+
+    let tokens = tokenize(xml).expect("Failed to tokenize XML");
+    let error = parse_tokens(tokens).expect_err("Mismatched tags should fail");
+
+    match &error {
+        ParseError::MismatchedTags {
+            expected,
+            found,
+            position,
+        } => {
+            assert_eq!(expected, "profile");
+            assert_eq!(found, "profiles");
+            assert_eq!((position.line, position.column), (3, 3));
+        }
+        other => panic!("Expected MismatchedTags, got {:?}", other),
+    }
+
+    assert_eq!(
+        format!("Orks.cat:{}", error),
+        "Orks.cat:3:3: expected </profile>, found </profiles>"
+    );
+}
+
+#[test]
+fn test_incomplete_document_reports_unclosed_element() {
+    let xml = "<roster>\n  <forces>\n";
+
+    let tokens = tokenize(xml).expect("Failed to tokenize XML");
+    let error = parse_tokens(tokens).expect_err("Unclosed elements should fail");
+
+    assert!(matches!(
+        &error,
+        ParseError::IncompleteDocument { expected, position }
+            if expected == "forces" && position.line == 3
+    ));
+    assert_eq!(
+        error.to_string(),
+        "3:1: expected </forces>, found end of document"
+    );
+}
+
+#[test]
+fn test_parse_cdata_sections() {
+    let xml = r#"
+        <rule name="Waaagh!">
+            <description><![CDATA[Units get +1 <Strength> & <Attacks>]]>.</description>
+        </rule>
+    "#;
+
+    let tokens = tokenize(xml).expect("Failed to tokenize XML with CDATA");
+    let document = parse_tokens(tokens).expect("Failed to parse XML with CDATA");
+
+    let description = document
+        .find_element_by_path("description")
+        .expect("Should find description element");
+
+    assert!(matches!(
+        description.children.first(),
+        Some(XmlNode::CData(content)) if content == "Units get +1 <Strength> & <Attacks>"
+    ));
+    assert_eq!(
+        description.get_text_content(),
+        "Units get +1 <Strength> & <Attacks>."
+    );
+}
+
+#[test]
+fn test_parse_keeps_the_prolog() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<!-- Exported roster -->
+<!DOCTYPE roster>
+<?xml-stylesheet href="roster.xsl"?>
+<roster name="Tts game teams">
+    <?battlescribe keep?>
+</roster>
+<!-- trailing -->"#;
+
+    let tokens = tokenize(xml).expect("Failed to tokenize XML with prolog");
+    let document = parse_tokens(tokens).expect("Failed to parse XML with prolog");
+
+    let declaration = document
+        .declaration
+        .as_ref()
+        .expect("Should keep declaration");
+    assert_eq!(declaration.version, "1.0");
+    assert_eq!(declaration.encoding.as_deref(), Some("UTF-8"));
+    assert_eq!(declaration.standalone, Some(true));
+
+    assert_eq!(document.doctype.as_ref().unwrap().name, "roster");
+
+    assert_eq!(document.prolog.len(), 2);
+    assert!(matches!(&document.prolog[0], XmlNode::Comment(c) if c == "Exported roster"));
+    assert!(matches!(
+        &document.prolog[1],
+        XmlNode::ProcessingInstruction { target, data }
+            if target == "xml-stylesheet" && data == r#"href="roster.xsl""#
+    ));
+    assert!(matches!(&document.epilog[..], [XmlNode::Comment(c)] if c == "trailing"));
+
+    let root = document.get_root_element().unwrap();
+    assert!(matches!(
+        &root.children[..],
+        [XmlNode::ProcessingInstruction { target, .. }] if target == "battlescribe"
+    ));
+}
+
+#[test]
+fn test_parse_catalogue_declaration() {
+    let xml_content = std::fs::read_to_string("example-data/Test-Chaos-Thousand Sons.cat")
+        .expect("Failed to read test file");
+
+    let tokens = tokenize(&xml_content).expect("Failed to tokenize XML");
+    let document = parse_tokens(tokens).expect("Failed to parse tokens");
+
+    let declaration = document.declaration.expect("Catalogue has a declaration");
+    assert_eq!(declaration.version, "1.0");
+    assert_eq!(declaration.encoding.as_deref(), Some("UTF-8"));
+    assert_eq!(declaration.standalone, Some(true));
+    assert!(document.doctype.is_none());
+}
+
+#[test]
+fn test_namespaces_identify_battlescribe_documents() {
+    let catalogue = std::fs::read_to_string("example-data/Test-Chaos-Thousand Sons.cat")
+        .expect("Failed to read catalogue");
+    let document = parse_tokens(tokenize(&catalogue).unwrap()).unwrap();
+
+    assert_eq!(document.document_kind(), DocumentKind::Catalogue);
+    let root = document.get_root_element().unwrap();
+    assert_eq!(root.namespace.as_deref(), Some(CATALOGUE_NAMESPACE));
+
+    // Children inherit the default namespace
+    let publications = root
+        .find_child_by_ns(Some(CATALOGUE_NAMESPACE), "publications")
+        .expect("Should find publications by namespace");
+    let publication = publications
+        .find_child_by_ns(Some(CATALOGUE_NAMESPACE), "publication")
+        .expect("Should find publication by namespace");
+    assert_eq!(
+        publication.namespaces.default_namespace(),
+        Some(CATALOGUE_NAMESPACE)
+    );
+    assert!(root.find_child_by_ns(None, "publications").is_none());
+
+    let roster =
+        std::fs::read_to_string("example-data/Tts game teams.ros").expect("Failed to read roster");
+    let document = parse_tokens(tokenize(&roster).unwrap()).unwrap();
+    assert_eq!(document.document_kind(), DocumentKind::Roster);
+}
+
+#[test]
+fn test_prefixed_names_resolve_through_inherited_scopes() {
+    let xml = r#"
+        <xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" xmlns="urn:outer">
+            <xs:element name="roster" xml:lang="en">
+                <inner xmlns="urn:inner">
+                    <plain xmlns=""/>
+                </inner>
+                <outer/>
+            </xs:element>
+        </xs:schema>
+    "#;
+
+    let document = parse_tokens(tokenize(xml).unwrap()).unwrap();
+    let schema = document.get_root_element().unwrap();
+    assert_eq!(schema.prefix(), Some("xs"));
+    assert_eq!(schema.local_name(), "schema");
+    assert_eq!(schema.namespace.as_deref(), Some(XSD));
+    assert_eq!(document.document_kind(), DocumentKind::Unknown);
+
+    let element = schema
+        .find_child_by_ns(Some(XSD), "element")
+        .expect("Should find xs:element by namespace");
+    assert_eq!(element.name, "xs:element");
+    assert_eq!(element.namespaces.lookup("xs"), Some(XSD));
+    assert_eq!(
+        element
+            .get_attribute_ns(Some(XML_NAMESPACE), "lang")
+            .map(String::as_str),
+        Some("en")
+    );
+    assert_eq!(
+        element.get_attribute_ns(None, "name").map(String::as_str),
+        Some("roster")
+    );
+
+    let inner = element
+        .find_child_by_ns(Some("urn:inner"), "inner")
+        .unwrap();
+    let plain = inner
+        .find_child_by_ns(None, "plain")
+        .expect("xmlns=\"\" undeclares");
+    assert_eq!(plain.namespaces.lookup("xs"), Some(XSD));
+    assert!(element
+        .find_child_by_ns(Some("urn:outer"), "outer")
+        .is_some());
+}
+
+// ============================================================================
+// ATTRIBUTE ORDER TESTS
+// ============================================================================
+
+#[test]
+fn test_attributes_keep_document_order_and_quotes() {
+    let xml = r#"<selectionEntry type="model" name='Boyz' import="true" id="a1"/>"#;
+
+    let document = parse_tokens(tokenize(xml).unwrap()).unwrap();
+    let entry = document.get_root_element().unwrap();
+
+    let names: Vec<_> = entry
+        .attributes_in_order()
+        .iter()
+        .map(|attribute| attribute.name.as_str())
+        .collect();
+    assert_eq!(names, ["type", "name", "import", "id"]);
+    assert_eq!(
+        entry.attributes.attribute("name").unwrap().quote,
+        Quote::Single
+    );
+    assert_eq!(
+        entry.attributes.attribute("id").unwrap().quote,
+        Quote::Double
+    );
+    assert_eq!(entry.get_attribute("name").unwrap(), "Boyz");
+
+    // The catalogue root keeps the order it has on disk, run after run
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+    let document = parse_tokens(tokenize(&xml).unwrap()).unwrap();
+    let names: Vec<_> = document
+        .get_root_element()
+        .unwrap()
+        .attributes
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .take(4)
+        .collect();
+    assert_eq!(names, ["xmlns", "library", "id", "name"]);
+}
+
+#[test]
+fn test_set_attribute_replaces_in_place() {
+    let xml = r#"<cost name='pts' typeId="points" value="65"/>"#;
+    let mut document = parse_tokens(tokenize(xml).unwrap()).unwrap();
+    let cost = document.root.as_mut().unwrap();
+
+    assert_eq!(
+        cost.set_attribute("name", "points"),
+        Ok(Some("pts".to_string()))
+    );
+    assert_eq!(cost.set_attribute("hidden", "false"), Ok(None));
+
+    let attributes = cost.attributes_in_order();
+    assert_eq!(attributes[0].name, "name");
+    assert_eq!(attributes[0].value, "points");
+    assert_eq!(attributes[0].quote, Quote::Single);
+    assert_eq!(attributes[3].name, "hidden");
+    assert_eq!(attributes[3].quote, Quote::Double);
+}
+
+// ============================================================================
+// RECOVERY TESTS
+// ============================================================================
+
+#[test]
+fn test_recovering_closes_unclosed_elements() {
+    let xml = "<roster>\n  <forces>\n    <force name=\"Army\">\n</roster>";
+
+    let (document, diagnostics) = parse_tokens_recovering(tokenize(xml).unwrap());
+
+    let root = document.get_root_element().unwrap();
+    let force = root
+        .find_child_by_name("forces")
+        .and_then(|forces| forces.find_child_by_name("force"))
+        .expect("Unclosed elements should be kept");
+    assert_eq!(force.get_attribute("name").unwrap(), "Army");
+
+    let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            DiagnosticKind::UnclosedElement,
+            DiagnosticKind::UnclosedElement
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "4:1: closed <force> at </roster>"
+    );
+    assert_eq!(force.span.unwrap().end, diagnostics[0].position);
+}
+
+#[test]
+fn test_recovering_skips_stray_close_tags_and_extra_roots() {
+    let xml = "<roster><forces></profile></forces></roster></roster><extra/>";
+
+    let (document, diagnostics) = parse_tokens_recovering(tokenize(xml).unwrap());
+
+    let root = document.get_root_element().unwrap();
+    assert_eq!(root.name, "roster");
+    assert!(root.find_child_by_name("forces").is_some());
+
+    let kinds: Vec<_> = diagnostics.iter().map(|d| d.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            DiagnosticKind::StrayCloseTag,
+            DiagnosticKind::StrayCloseTag,
+            DiagnosticKind::ExtraRootElement
+        ]
+    );
+    assert_eq!(diagnostics[0].message, "skipped stray </profile>");
+    assert_eq!(
+        diagnostics[2].message,
+        "dropped second root element <extra>"
+    );
+}
+
+#[test]
+fn test_recovering_closes_truncated_documents() {
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+    let truncated = &xml[..xml.len() / 2];
+    // Cut back to the end of the last complete tag so the lexer still succeeds
+    let truncated = &truncated[..truncated.rfind('>').unwrap() + 1];
+
+    let (document, diagnostics) = parse_tokens_recovering(tokenize(truncated).unwrap());
+
+    assert_eq!(document.get_root_element().unwrap().name, "catalogue");
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.kind == DiagnosticKind::UnclosedElement));
+    assert!(diagnostics
+        .last()
+        .unwrap()
+        .message
+        .ends_with("<catalogue> at end of document"));
+}
+
+#[test]
+fn test_recovering_matches_strict_parse_on_valid_input() {
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read test file");
+
+    let strict = parse_tokens(tokenize(&xml).unwrap()).unwrap();
+    let (recovered, diagnostics) = parse_tokens_recovering(tokenize(&xml).unwrap());
+
+    assert!(diagnostics.is_empty());
+    let (recovered, strict) = (
+        recovered.get_root_element().unwrap(),
+        strict.get_root_element().unwrap(),
+    );
+    assert_eq!(recovered.span, strict.span);
+    assert_eq!(recovered.children.len(), strict.children.len());
+}
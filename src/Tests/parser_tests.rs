@@ -0,0 +1,125 @@
+use crate::models::DocumentKind::DocumentKind;
+use crate::Tools::lexical_analysis::TokenizeError;
+use crate::Tools::parse_tokens::ParseError;
+use crate::{parse_file, parse_rosz, parse_str, parse_str_recovering, XmlError};
+use std::error::Error;
+
+// ============================================================================
+// PARSE_STR TESTS
+// ============================================================================
+
+#[test]
+fn test_parse_str() {
+    let document =
+        parse_str(r#"<roster name="Waaagh!"><forces/></roster>"#).expect("Failed to parse roster");
+
+    let root = document.get_root_element().unwrap();
+    assert_eq!(root.get_attribute("name").unwrap(), "Waaagh!");
+    assert!(root.find_child_by_name("forces").is_some());
+}
+
+#[test]
+fn test_parse_str_errors_keep_their_kind_and_position() {
+    let error = parse_str("<roster>\n  <forces>\n</roster>").expect_err("Mismatched tags");
+    assert!(matches!(
+        error,
+        XmlError::Parse(ParseError::MismatchedTags { .. })
+    ));
+    assert_eq!(error.position().map(|p| (p.line, p.column)), Some((3, 1)));
+
+    let error = parse_str("<roster name=Waaagh/>").expect_err("Unquoted attribute");
+    assert!(matches!(
+        error,
+        XmlError::Tokenize(TokenizeError::MalformedAttribute { .. })
+    ));
+    assert!(error.source().is_some());
+}
+
+#[test]
+fn test_parse_str_recovering() {
+    let (document, diagnostics) =
+        parse_str_recovering("<roster>\n  <forces>\n</roster>").expect("Failed to recover");
+    assert!(document
+        .get_root_element()
+        .unwrap()
+        .find_child_by_name("forces")
+        .is_some());
+    assert_eq!(diagnostics.len(), 1);
+
+    // Tokenize errors cannot be recovered from
+    let error = parse_str_recovering("<roster name=Waaagh/>").expect_err("Unquoted attribute");
+    assert!(matches!(error, XmlError::Tokenize(_)));
+}
+
+// ============================================================================
+// PARSE_FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_parse_file() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+
+    assert_eq!(document.document_kind(), DocumentKind::Catalogue);
+}
+
+#[test]
+fn test_parse_file_errors_name_the_file() {
+    let error = parse_file("example-data/nonexistent.cat").expect_err("Missing file");
+    assert_eq!(error.file(), Some("example-data/nonexistent.cat"));
+    assert!(error
+        .to_string()
+        .starts_with("example-data/nonexistent.cat: "));
+    let io_error = XmlError::Io(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "No such file or directory",
+    ))
+    .in_file("army.cat");
+    assert_eq!(io_error.to_string(), "army.cat: No such file or directory");
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|source| source.downcast_ref::<XmlError>()),
+        Some(XmlError::Io(_))
+    ));
+
+    let path = "example-data/temp_parser_mismatched.cat";
+    std::fs::write(
+        path,
+        "<catalogue>\n  <profiles>\n  </profile>\n</catalogue>",
+    )
+    .expect("Failed to write temporary catalogue");
+    let error = parse_file(path).expect_err("Mismatched tags");
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(
+        error.to_string(),
+        "example-data/temp_parser_mismatched.cat: 3:3: expected </profiles>, found </profile>"
+    );
+    assert_eq!(error.position().map(|p| p.line), Some(3));
+}
+
+// ============================================================================
+// PARSE_ROSZ TESTS
+// ============================================================================
+
+#[test]
+fn test_parse_rosz() {
+    let files = parse_rosz("example-data/Tts Ork game teams.rosz").expect("Failed to parse .rosz");
+
+    assert_eq!(files.len(), 1);
+    assert!(files[0].filename.ends_with(".ros"));
+    assert_eq!(files[0].document.document_kind(), DocumentKind::Roster);
+}
+
+#[test]
+fn test_parse_rosz_errors_name_the_archive() {
+    let error = parse_rosz("example-data/nonexistent.rosz").expect_err("Missing archive");
+    assert_eq!(error.file(), Some("example-data/nonexistent.rosz"));
+
+    // A catalogue is not a zip archive
+    let error = parse_rosz("example-data/Orks.cat").expect_err("Not an archive");
+    assert!(matches!(
+        error,
+        XmlError::InFile { ref error, .. } if matches!(**error, XmlError::Zip(_))
+    ));
+}
@@ -0,0 +1,247 @@
+use crate::models::Attribute::{Attribute, Attributes, Quote};
+use crate::Tools::decompression::{find_xml_entry, open_zip_archive};
+use crate::Tools::lexical_analysis::{tokenize, TokenizeError, TokenizerOptions};
+use crate::Tools::parse_tokens::{parse_tokens, ParseError};
+use crate::Tools::reader::{ReaderError, XmlEvent, XmlReader};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Read every event up to and including EndOfFile
+fn events_of<R: BufRead>(reader: XmlReader<R>) -> Vec<XmlEvent> {
+    reader
+        .map(|event| event.expect("Failed to read event"))
+        .collect()
+}
+
+// ============================================================================
+// EVENT TESTS
+// ============================================================================
+
+#[test]
+fn test_reader_yields_events_in_document_order() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<roster name="Waaagh!">
+  <!-- Army list -->
+  <force catalogueName="Orks"/>
+  <comment>Boyz &amp; Grots</comment>
+</roster>"#;
+
+    let events = events_of(XmlReader::new(xml.as_bytes()));
+
+    assert!(matches!(events[0], XmlEvent::Declaration(_)));
+    assert_eq!(
+        events[1..],
+        [
+            XmlEvent::StartElement {
+                name: "roster".to_string(),
+                attributes: Attributes::from_iter([("name", "Waaagh!")]),
+            },
+            XmlEvent::Comment("Army list".to_string()),
+            XmlEvent::StartElement {
+                name: "force".to_string(),
+                attributes: Attributes::from_iter([("catalogueName", "Orks")]),
+            },
+            XmlEvent::EndElement {
+                name: "force".to_string()
+            },
+            XmlEvent::StartElement {
+                name: "comment".to_string(),
+                attributes: Attributes::new(),
+            },
+            XmlEvent::Text("Boyz & Grots".to_string()),
+            XmlEvent::EndElement {
+                name: "comment".to_string()
+            },
+            XmlEvent::EndElement {
+                name: "roster".to_string()
+            },
+            XmlEvent::EndOfFile,
+        ]
+    );
+}
+
+#[test]
+fn test_reader_handles_markup_split_across_reads() {
+    let xml = r#"<rule name="Dakka > Choppa" note='a "quoted" value'><!-- a -> b --><![CDATA[<raw> ]] text]]>Text</rule>"#;
+
+    // A tiny buffer forces every tag, comment and CDATA section across several reads
+    let small = events_of(XmlReader::new(BufReader::with_capacity(3, xml.as_bytes())));
+    let large = events_of(XmlReader::new(xml.as_bytes()));
+
+    assert_eq!(small, large);
+    assert_eq!(
+        small[0],
+        XmlEvent::StartElement {
+            name: "rule".to_string(),
+            attributes: [
+                Attribute::new("name", "Dakka > Choppa"),
+                Attribute {
+                    quote: Quote::Single,
+                    ..Attribute::new("note", "a \"quoted\" value")
+                },
+            ]
+            .into_iter()
+            .fold(Attributes::new(), |mut attributes, attribute| {
+                attributes.push(attribute);
+                attributes
+            }),
+        }
+    );
+    assert_eq!(small[1], XmlEvent::Comment("a -> b".to_string()));
+    assert_eq!(small[2], XmlEvent::CData("<raw> ]] text".to_string()));
+    assert_eq!(small[3], XmlEvent::Text("Text".to_string()));
+}
+
+#[test]
+fn test_reader_keeps_attribute_order_and_quotes() {
+    let xml = r#"<selection number="1" type='model' name="Boy" entryId='e1'/>"#;
+    let mut reader = XmlReader::new(xml.as_bytes());
+
+    let XmlEvent::StartElement { attributes, .. } = reader.next_event().unwrap() else {
+        panic!("Expected a start element");
+    };
+    let order: Vec<(&str, Quote)> = attributes
+        .as_slice()
+        .iter()
+        .map(|attribute| (attribute.name.as_str(), attribute.quote))
+        .collect();
+    assert_eq!(
+        order,
+        [
+            ("number", Quote::Double),
+            ("type", Quote::Single),
+            ("name", Quote::Double),
+            ("entryId", Quote::Single),
+        ]
+    );
+
+    // Strict reading still refuses a repeated attribute
+    let xml = r#"<selection name="Boy" name="Grot"/>"#;
+    let mut reader = XmlReader::with_options(xml.as_bytes(), TokenizerOptions::strict());
+    assert!(matches!(
+        reader.next_event(),
+        Err(ReaderError::Tokenize(
+            TokenizeError::DuplicateAttribute { .. }
+        ))
+    ));
+}
+
+#[test]
+fn test_reader_reports_event_positions() {
+    let xml = "<roster>\n  <forces>\n    <force/>\n  </forces>\n</roster>";
+    let mut reader = XmlReader::new(xml.as_bytes());
+
+    reader.next_event().expect("roster");
+    reader.next_event().expect("forces");
+    let force = reader.next_event().expect("force");
+
+    assert!(matches!(force, XmlEvent::StartElement { ref name, .. } if name == "force"));
+    assert_eq!(reader.position().line, 3);
+    assert_eq!(reader.position().column, 5);
+    assert_eq!(reader.depth(), 2);
+}
+
+// ============================================================================
+// ERROR TESTS
+// ============================================================================
+
+#[test]
+fn test_reader_reports_mismatched_tags() {
+    let xml = "<profiles>\n  <profile>\n  </profiles>";
+    let mut reader = XmlReader::new(xml.as_bytes());
+
+    let error = loop {
+        match reader.next_event() {
+            Ok(XmlEvent::EndOfFile) => panic!("Expected a mismatched tag error"),
+            Ok(_) => {}
+            Err(error) => break error,
+        }
+    };
+
+    assert!(matches!(
+        error,
+        ReaderError::Parse(ParseError::MismatchedTags { .. })
+    ));
+    assert_eq!(
+        error.to_string(),
+        "3:3: expected </profile>, found </profiles>"
+    );
+    // Nothing more is read after an error
+    assert_eq!(reader.next_event().unwrap(), XmlEvent::EndOfFile);
+}
+
+#[test]
+fn test_reader_reports_unclosed_elements() {
+    let result: Result<Vec<XmlEvent>, ReaderError> =
+        XmlReader::new("<roster><forces>".as_bytes()).collect();
+
+    assert!(matches!(
+        result,
+        Err(ReaderError::Parse(ParseError::IncompleteDocument { ref expected, .. })) if expected == "forces"
+    ));
+}
+
+#[test]
+fn test_reader_reports_invalid_utf8() {
+    let bytes: &[u8] = b"<name>Ork\xFFs</name>";
+
+    let result: Result<Vec<XmlEvent>, ReaderError> = XmlReader::new(bytes).collect();
+
+    match result {
+        Err(ReaderError::InvalidUtf8 { position }) => assert_eq!(position.column, 10),
+        other => panic!("Expected invalid UTF-8 error, got {:?}", other),
+    }
+}
+
+// ============================================================================
+// FILE AND ARCHIVE TESTS
+// ============================================================================
+
+#[test]
+fn test_reader_streams_catalogue_file() {
+    let file = File::open("example-data/Orks.cat").expect("Failed to open Orks.cat");
+    let events = events_of(XmlReader::new(BufReader::new(file)));
+
+    let xml = std::fs::read_to_string("example-data/Orks.cat").expect("Failed to read Orks.cat");
+    let document = parse_tokens(tokenize(&xml).unwrap()).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    let starts = events
+        .iter()
+        .filter(|event| matches!(event, XmlEvent::StartElement { .. }))
+        .count();
+    let ends = events
+        .iter()
+        .filter(|event| matches!(event, XmlEvent::EndElement { .. }))
+        .count();
+
+    assert_eq!(starts, 1 + root.descendants().count());
+    assert_eq!(starts, ends);
+}
+
+#[test]
+fn test_reader_streams_roster_from_rosz_archive() {
+    let mut archive =
+        open_zip_archive("example-data/Test-sample-army.rosz").expect("Failed to open archive");
+    let index = find_xml_entry(&mut archive).expect("Archive should contain a roster");
+    let entry = archive
+        .by_index(index)
+        .expect("Failed to open roster entry");
+
+    let mut reader = XmlReader::new(BufReader::new(entry));
+    let root = loop {
+        match reader.next_event().expect("Failed to read roster") {
+            XmlEvent::StartElement { name, attributes } => break (name, attributes),
+            XmlEvent::EndOfFile => panic!("Roster has no root element"),
+            _ => {}
+        }
+    };
+
+    assert_eq!(root.0, "roster");
+    assert!(root.1.contains_key("gameSystemName"));
+    while reader.next_event().expect("Failed to read roster") != XmlEvent::EndOfFile {}
+}
@@ -5,8 +5,8 @@ use crate::models::XmlDocument::XmlDocument;
 use crate::models::XmlElement::XmlElement;
 use crate::models::XmlNode::XmlNode;
 use crate::Tools::decompression::decompress_rosz_file;
-use crate::Tools::lexical_analysis::tokenize;
-use crate::Tools::parse_tokens::parse_tokens;
+use crate::Tools::mapping::FromXmlElement;
+use crate::Tools::parser::parse_file;
 use crate::Tools::validator::ConstraintValidator;
 use std::collections::HashMap;
 
@@ -16,15 +16,9 @@ use std::collections::HashMap;
 
 /// Parse a catalog file and extract all constraints
 fn parse_catalog_constraints(catalog_path: &str) -> Result<Vec<Constraint>, String> {
-    // Read the catalog file
-    let catalog_content = std::fs::read_to_string(catalog_path)
-        .map_err(|e| format!("Failed to read catalog file: {}", e))?;
-
-    // Tokenize and parse the catalog
-    let tokens =
-        tokenize(&catalog_content).map_err(|e| format!("Failed to tokenize catalog: {:?}", e))?;
-
-    let document = parse_tokens(tokens).map_err(|e| format!("Failed to parse catalog: {:?}", e))?;
+    // Read, tokenize and parse the catalog
+    let document =
+        parse_file(catalog_path).map_err(|e| format!("Failed to read catalog file: {}", e))?;
 
     let root = document
         .get_root_element()
@@ -66,79 +60,14 @@ fn extract_constraints_from_element(
 
 /// Parse a single constraint element
 fn parse_constraint_from_element(element: &XmlElement) -> Result<Constraint, String> {
-    let constraint_type = match element.get_attribute("type") {
-        Some(type_str) => match type_str.as_str() {
-            "min" => ConstraintType::Min,
-            "max" => ConstraintType::Max,
-            "equal" => ConstraintType::Equal,
-            "notEqual" => ConstraintType::NotEqual,
-            "atLeast" => ConstraintType::AtLeast,
-            "atMost" => ConstraintType::AtMost,
-            _ => return Err(format!("Unknown constraint type: {}", type_str)),
-        },
-        None => return Err("Constraint type is required".to_string()),
-    };
-
-    let value = element
-        .get_attribute("value")
-        .ok_or("Constraint value is required")?
-        .parse::<i32>()
-        .map_err(|_| "Constraint value must be a valid integer")?;
-
-    let field = element
-        .get_attribute("field")
-        .ok_or("Constraint field is required")?
-        .clone();
-
-    let scope = element
-        .get_attribute("scope")
-        .unwrap_or(&"parent".to_string())
-        .clone();
-
-    let shared = element
-        .get_attribute("shared")
-        .map(|s| s == "true")
-        .unwrap_or(false);
-
-    let id = element
-        .get_attribute("id")
-        .ok_or("Constraint id is required")?
-        .clone();
-
-    let include_child_selections = element
-        .get_attribute("includeChildSelections")
-        .map(|s| s == "true");
-
-    let include_child_forces = element
-        .get_attribute("includeChildForces")
-        .map(|s| s == "true");
-
-    let percent_value = element.get_attribute("percentValue").map(|s| s == "true");
-
-    Ok(Constraint {
-        constraint_type,
-        value,
-        field,
-        scope,
-        shared,
-        id,
-        include_child_selections,
-        include_child_forces,
-        percent_value,
-    })
+    Constraint::from_xml_element(element).map_err(|e| e.to_string())
 }
 
 /// Parse a roster file and extract selection counts
 fn parse_roster_selections(roster_path: &str) -> Result<HashMap<String, i32>, String> {
-    // Read the roster file
-    let roster_content = std::fs::read_to_string(roster_path)
-        .map_err(|e| format!("Failed to read roster file: {}", e))?;
-
-    // Tokenize and parse the roster
-    let tokens =
-        tokenize(&roster_content).map_err(|e| format!("Failed to tokenize roster: {:?}", e))?;
-
-    let document = parse_tokens(tokens).map_err(|e| format!("Failed to parse roster: {:?}", e))?;
+    // Read, tokenize and parse the roster
+    let document =
+        parse_file(roster_path).map_err(|e| format!("Failed to read roster file: {}", e))?;
 
     let root = document
         .get_root_element()
@@ -0,0 +1,381 @@
+use crate::models::XmlDocument::XmlDocument;
+use crate::{parse_file, parse_str, Schema, SchemaError, SchemaViolation, ViolationKind};
+
+const ROSTER_XSD: &str = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:annotation><xs:documentation>A cut-down roster</xs:documentation></xs:annotation>
+  <xs:element name="roster" type="rosterType"/>
+  <xs:complexType name="rosterType">
+    <xs:sequence>
+      <xs:element name="costs" minOccurs="0">
+        <xs:complexType>
+          <xs:sequence>
+            <xs:element name="cost" type="costType" maxOccurs="unbounded"/>
+          </xs:sequence>
+        </xs:complexType>
+      </xs:element>
+      <xs:element ref="force" maxOccurs="2"/>
+      <xs:choice minOccurs="0">
+        <xs:element name="note" type="xs:string"/>
+        <xs:element name="description" type="xs:string"/>
+      </xs:choice>
+    </xs:sequence>
+    <xs:attribute name="name" type="xs:string" use="required"/>
+    <xs:attribute name="points" type="points"/>
+  </xs:complexType>
+  <xs:element name="force">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:element name="selection" minOccurs="0" maxOccurs="unbounded">
+          <xs:complexType>
+            <xs:simpleContent>
+              <xs:extension base="xs:string">
+                <xs:attribute name="number" type="xs:positiveInteger"/>
+              </xs:extension>
+            </xs:simpleContent>
+          </xs:complexType>
+        </xs:element>
+      </xs:sequence>
+      <xs:attribute name="catalogueName" use="required"/>
+    </xs:complexType>
+  </xs:element>
+  <xs:complexType name="costType">
+    <xs:attribute name="name" use="required">
+      <xs:simpleType>
+        <xs:restriction base="xs:string">
+          <xs:enumeration value="pts"/>
+          <xs:enumeration value="PL"/>
+        </xs:restriction>
+      </xs:simpleType>
+    </xs:attribute>
+    <xs:attribute name="value" type="xs:decimal" use="required"/>
+  </xs:complexType>
+  <xs:simpleType name="points">
+    <xs:restriction base="xs:integer">
+      <xs:minInclusive value="0"/>
+      <xs:maxInclusive value="2000"/>
+    </xs:restriction>
+  </xs:simpleType>
+</xs:schema>"#;
+
+// Catalogue header attributes and every constraint; everything else is checked laxly
+const CATALOGUE_XSD: &str = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+    targetNamespace="http://www.battlescribe.net/schema/catalogueSchema">
+  <xs:element name="catalogue">
+    <xs:complexType>
+      <xs:sequence>
+        <xs:any processContents="lax" minOccurs="0" maxOccurs="unbounded"/>
+      </xs:sequence>
+      <xs:attribute name="id" use="required"/>
+      <xs:attribute name="name" use="required"/>
+      <xs:attribute name="revision" type="xs:int" use="required"/>
+      <xs:attribute name="battleScribeVersion" type="xs:decimal" use="required"/>
+      <xs:attribute name="library" type="xs:boolean"/>
+      <xs:attribute name="gameSystemId" use="required"/>
+      <xs:attribute name="gameSystemRevision" type="xs:int"/>
+      <xs:attribute name="type" type="xs:string"/>
+      <xs:attribute name="authorName"/>
+      <xs:attribute name="authorContact"/>
+      <xs:attribute name="authorUrl"/>
+    </xs:complexType>
+  </xs:element>
+  <xs:element name="constraint">
+    <xs:complexType>
+      <xs:attribute name="id" use="required"/>
+      <xs:attribute name="type" type="constraintType" use="required"/>
+      <xs:attribute name="value" type="xs:decimal" use="required"/>
+      <xs:attribute name="field" type="xs:string" use="required"/>
+      <xs:attribute name="scope" type="xs:string" use="required"/>
+      <xs:attribute name="shared" type="xs:boolean" use="required"/>
+      <xs:attribute name="percentValue" type="xs:boolean"/>
+      <xs:attribute name="includeChildSelections" type="xs:boolean"/>
+      <xs:attribute name="includeChildForces" type="xs:boolean"/>
+    </xs:complexType>
+  </xs:element>
+  <xs:simpleType name="constraintType">
+    <xs:restriction base="xs:string">
+      <xs:enumeration value="min"/>
+      <xs:enumeration value="max"/>
+    </xs:restriction>
+  </xs:simpleType>
+</xs:schema>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Validate an XML string against the roster schema
+fn validate(xml: &str) -> Vec<SchemaViolation> {
+    let schema = Schema::parse_str(ROSTER_XSD).expect("Failed to load schema");
+    let document: XmlDocument = parse_str(xml).expect("Failed to parse XML");
+    schema.validate(&document)
+}
+
+/// The kind and path of each violation, for compact assertions
+fn summary(violations: &[SchemaViolation]) -> Vec<(ViolationKind, &str)> {
+    violations
+        .iter()
+        .map(|violation| (violation.kind, violation.path.as_str()))
+        .collect()
+}
+
+// ============================================================================
+// VALIDATION TESTS
+// ============================================================================
+
+#[test]
+fn test_valid_document_has_no_violations() {
+    let violations = validate(
+        "<roster name='Waaagh' points='500'>\
+           <costs><cost name='pts' value='500.0'/><cost name='PL' value='25'/></costs>\
+           <force catalogueName='Orks'><selection number='10'>Boyz</selection></force>\
+           <force catalogueName='Orks'/>\
+           <note>Green tide</note>\
+         </roster>",
+    );
+    assert_eq!(violations, vec![]);
+}
+
+#[test]
+fn test_attribute_violations() {
+    let violations = validate(
+        "<roster points='2500' colour='green' xmlns:bs='urn:x' bs:extra='1'>\
+           <costs><cost name='gold' value='lots'/></costs>\
+           <force catalogueName='Orks'/>\
+         </roster>",
+    );
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (ViolationKind::MissingAttribute, "roster"),
+            (ViolationKind::InvalidValue, "roster"),
+            (ViolationKind::UndeclaredAttribute, "roster"),
+            (ViolationKind::InvalidValue, "roster/costs/cost"),
+            (ViolationKind::InvalidValue, "roster/costs/cost"),
+        ]
+    );
+    assert_eq!(
+        violations[1].message,
+        "attribute \"points\": 2500 is greater than the maximum 2000"
+    );
+    assert_eq!(
+        violations[3].message,
+        "attribute \"name\": \"gold\" is not one of pts, PL"
+    );
+    assert_eq!(
+        violations[4].message,
+        "attribute \"value\": \"lots\" is not a number"
+    );
+}
+
+#[test]
+fn test_content_model_violations() {
+    let violations =
+        validate("<roster name='a'><costs/><note>x</note><force catalogueName='Orks'/></roster>");
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (ViolationKind::MissingElement, "roster/costs"),
+            (ViolationKind::MissingElement, "roster"),
+            (ViolationKind::UnexpectedElement, "roster/force"),
+        ]
+    );
+    assert_eq!(violations[0].message, "expected <cost>");
+    assert_eq!(violations[1].message, "expected <force>");
+    assert_eq!(violations[2].message, "<force> is not allowed here");
+}
+
+#[test]
+fn test_max_occurs_and_sibling_paths() {
+    let violations = validate(
+        "<roster name='a'>\n\
+           <force catalogueName='A'/>\n\
+           <force catalogueName='B'/>\n\
+           <note/>\n\
+           <force catalogueName='C'/>\n\
+           <description/>\n\
+         </roster>",
+    );
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (ViolationKind::UnexpectedElement, "roster/force[3]"),
+            (ViolationKind::UnexpectedElement, "roster/description"),
+        ]
+    );
+    assert_eq!(violations[0].position.unwrap().line, 5);
+}
+
+#[test]
+fn test_text_and_simple_content() {
+    let violations = validate(
+        "<roster name='a'>stray<force catalogueName='Orks'>\
+           <selection number='0'>Boyz<upgrade/></selection>\
+         </force></roster>",
+    );
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (ViolationKind::UnexpectedText, "roster"),
+            (ViolationKind::InvalidValue, "roster/force/selection"),
+            (
+                ViolationKind::UnexpectedElement,
+                "roster/force/selection/upgrade"
+            ),
+        ]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "1:1: roster: <roster> may only contain elements"
+    );
+    assert_eq!(
+        violations[1].message,
+        "attribute \"number\": 0 is out of range"
+    );
+}
+
+#[test]
+fn test_doubles_use_xsd_spellings() {
+    let schema = Schema::parse_str(
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="cost">
+    <xs:complexType><xs:attribute name="value" type="xs:double"/></xs:complexType>
+  </xs:element>
+</xs:schema>"#,
+    )
+    .unwrap();
+    let check = |value: &str| {
+        let document = parse_str(&format!("<cost value='{}'/>", value)).unwrap();
+        schema.validate(&document).is_empty()
+    };
+
+    for valid in [
+        "1", "-1.5", ".5", "2.", "1.5E-3", "1e10", "INF", "-INF", "NaN",
+    ] {
+        assert!(check(valid), "{} should be a double", valid);
+    }
+    for invalid in [
+        "inf", "infinity", "nan", "-nan", "1e", "e5", "1.5E+", "0x10", "",
+    ] {
+        assert!(!check(invalid), "{} should not be a double", invalid);
+    }
+}
+
+#[test]
+fn test_root_element_and_namespace() {
+    assert_eq!(
+        summary(&validate("<army/>")),
+        vec![(ViolationKind::UnknownElement, "army")]
+    );
+
+    let schema = Schema::parse_str(CATALOGUE_XSD).unwrap();
+    let document = parse_str(
+        "<catalogue id='c' name='Orks' revision='1' battleScribeVersion='2.03' gameSystemId='g'/>",
+    )
+    .unwrap();
+    let violations = schema.validate(&document);
+    assert_eq!(
+        summary(&violations),
+        vec![(ViolationKind::WrongNamespace, "catalogue")]
+    );
+    assert_eq!(
+        violations[0].message,
+        "expected namespace \"http://www.battlescribe.net/schema/catalogueSchema\", found none"
+    );
+}
+
+// ============================================================================
+// SCHEMA LOADING TESTS
+// ============================================================================
+
+#[test]
+fn test_schema_loading_errors() {
+    assert!(matches!(
+        Schema::parse_str("<roster/>"),
+        Err(SchemaError::NotASchema)
+    ));
+    assert!(matches!(
+        Schema::parse_str("<xs:schema"),
+        Err(SchemaError::Xml(_))
+    ));
+
+    let schema = |body: &str| {
+        Schema::parse_str(&format!(
+            "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\n{}\n</xs:schema>",
+            body
+        ))
+    };
+
+    let error = schema("<xs:group name='g'/>").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "2:1: unsupported schema construct <xs:group>"
+    );
+
+    assert!(matches!(
+        schema("<xs:element name='a' type='missing'/>"),
+        Err(SchemaError::UnknownType { name }) if name == "missing"
+    ));
+    assert!(matches!(
+        schema("<xs:element name='a'><xs:complexType><xs:sequence><xs:element ref='b'/></xs:sequence></xs:complexType></xs:element>"),
+        Err(SchemaError::UnknownElement { name }) if name == "b"
+    ));
+    assert!(matches!(
+        schema("<xs:element type='xs:string'/>"),
+        Err(SchemaError::MissingAttribute { attribute, .. }) if attribute == "name"
+    ));
+    assert!(matches!(
+        schema("<xs:element name='a'><xs:complexType><xs:sequence><xs:element name='b' maxOccurs='many'/></xs:sequence></xs:complexType></xs:element>"),
+        Err(SchemaError::InvalidValue { attribute, .. }) if attribute == "maxOccurs"
+    ));
+
+    let error = schema(
+        "<xs:element name='r' type='A'/><xs:simpleType name='A'><xs:restriction base='A'/></xs:simpleType>",
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "simple type \"A\" is derived from itself"
+    );
+    assert!(matches!(
+        schema("<xs:simpleType name='A'><xs:restriction base='B'/></xs:simpleType><xs:simpleType name='B'><xs:restriction><xs:simpleType><xs:restriction base='A'/></xs:simpleType></xs:restriction></xs:simpleType>"),
+        Err(SchemaError::CircularType { .. })
+    ));
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_catalogue_against_schema() {
+    let schema = Schema::parse_str(CATALOGUE_XSD).unwrap();
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    assert_eq!(schema.validate(&document), vec![]);
+    assert!(schema.is_valid(&document));
+
+    // Break the first constraint and drop the id of another
+    let xml = std::fs::read_to_string("example-data/Orks.cat").unwrap();
+    let xml = xml
+        .replacen("type=\"max\" value=\"1\"", "type=\"most\" value=\"1\"", 1)
+        .replace(" id=\"db6b-b087-cadb-48e1\"", "");
+    let violations = schema.validate(&parse_str(&xml).unwrap());
+
+    assert_eq!(
+        summary(&violations),
+        vec![
+            (
+                ViolationKind::InvalidValue,
+                "catalogue/sharedSelectionEntries/selectionEntry[1]/constraints/constraint"
+            ),
+            (
+                ViolationKind::MissingAttribute,
+                "catalogue/sharedSelectionEntries/selectionEntry[2]/constraints/constraint[3]"
+            ),
+        ]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "9:9: catalogue/sharedSelectionEntries/selectionEntry[1]/constraints/constraint: \
+         attribute \"type\": \"most\" is not one of min, max"
+    );
+}
@@ -0,0 +1,246 @@
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::selector::{Selector, SelectorError};
+use crate::{parse_file, parse_str};
+
+const CATALOGUE: &str = r#"<catalogue name="Orks">
+  <selectionEntries>
+    <selectionEntry type="unit" name="Boyz" id="boyz-1">
+      <profiles>
+        <profile typeName="Unit" name="Boy"/>
+        <profile typeName="Weapon" name="Slugga"/>
+        <profile typeName="Unit" name="Boss Nob"/>
+      </profiles>
+      <selectionEntries>
+        <selectionEntry type="model" name="Boy" id="boy-1"><profiles><profile typeName="Unit" name="Nested"/></profiles></selectionEntry>
+      </selectionEntries>
+    </selectionEntry>
+    <selectionEntry type="model" name="Warboss" id="warboss-1">
+      <profiles><profile typeName="Unit" name="Warboss"/></profiles>
+    </selectionEntry>
+    <selectionEntry type="unit" name="Gretchin" id="grot-1"/>
+  </selectionEntries>
+</catalogue>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Select below the CATALOGUE root and return the name of every match
+fn names(selector: &str) -> Vec<String> {
+    let document = parse_str(CATALOGUE).expect("Failed to parse");
+    let root = document.get_root_element().unwrap();
+    root.select(selector)
+        .expect("Failed to parse selector")
+        .map(|element| element.get_attribute("name").cloned().unwrap_or_default())
+        .collect()
+}
+
+/// Collect constraints the way the roster validation tests do, by walking the tree by hand
+fn collect_constraints<'a>(element: &'a XmlElement, found: &mut Vec<&'a XmlElement>) {
+    for child in &element.children {
+        if let XmlNode::Element(child) = child {
+            if element.name == "constraints" && child.name == "constraint" {
+                found.push(child);
+            }
+            collect_constraints(child, found);
+        }
+    }
+}
+
+// ============================================================================
+// MATCHING TESTS
+// ============================================================================
+
+#[test]
+fn test_type_and_attribute_selectors() {
+    assert_eq!(
+        names("selectionEntry"),
+        vec!["Boyz", "Boy", "Warboss", "Gretchin"]
+    );
+    assert_eq!(names("selectionEntry[type=unit]"), vec!["Boyz", "Gretchin"]);
+    assert_eq!(
+        names("selectionEntry[type=\"model\"]"),
+        vec!["Boy", "Warboss"]
+    );
+    assert_eq!(names("[id^='boy']"), vec!["Boyz", "Boy"]);
+    assert_eq!(names("[id$=boss-1]"), vec!["Warboss"]);
+    assert_eq!(
+        names("profile[name*='o']"),
+        vec!["Boy", "Boss Nob", "Warboss"]
+    );
+    assert_eq!(names("*[typeName]").len(), 5);
+    assert_eq!(
+        names("profile[typeName=Unit][name^=B]"),
+        vec!["Boy", "Boss Nob"]
+    );
+}
+
+#[test]
+fn test_prefixed_names() {
+    let document = parse_str(
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema" xmlns:bs="urn:bs">
+  <xs:element name="catalogue"/>
+  <bs:element name="roster" bs:hidden="true"/>
+  <element name="force"/>
+  <xs:complexType name="costType"/>
+</xs:schema>"#,
+    )
+    .unwrap();
+    let root = document.get_root_element().unwrap();
+    let names = |selector: &str| -> Vec<String> {
+        root.select(selector)
+            .expect("Failed to parse selector")
+            .map(|element| element.get_attribute("name").cloned().unwrap_or_default())
+            .collect()
+    };
+
+    assert_eq!(names("xs|element"), vec!["catalogue"]);
+    assert_eq!(names("*|element"), vec!["catalogue", "roster", "force"]);
+    assert_eq!(names("|element"), vec!["force"]);
+    assert_eq!(names("xs|*"), vec!["catalogue", "costType"]);
+    assert_eq!(names("|*"), vec!["force"]);
+    assert_eq!(names("*|*").len(), 4);
+    assert_eq!(names("[bs|hidden=true]"), vec!["roster"]);
+    assert_eq!(names("xs|schema > xs|complexType"), vec!["costType"]);
+    assert!(Selector::parse("xs|").is_err());
+}
+
+#[test]
+fn test_combinators() {
+    assert_eq!(
+        names("selectionEntry[type=unit] > profiles > profile[typeName=Unit]"),
+        vec!["Boy", "Boss Nob"]
+    );
+    // The descendant combinator also reaches the nested entry's profile
+    assert_eq!(
+        names("selectionEntry[type=unit] profile[typeName=Unit]"),
+        vec!["Boy", "Boss Nob", "Nested"]
+    );
+    assert_eq!(
+        names("selectionEntries > selectionEntry > profiles profile"),
+        vec!["Boy", "Slugga", "Boss Nob", "Nested", "Warboss"]
+    );
+    assert_eq!(
+        names("catalogue > selectionEntries > selectionEntry[type=model]"),
+        vec!["Warboss"]
+    );
+    assert_eq!(
+        names("selectionEntry[name=Warboss],selectionEntry[name=Boy]"),
+        vec!["Boy", "Warboss"]
+    );
+}
+
+#[test]
+fn test_nth_child() {
+    assert_eq!(names("profiles > profile:nth-child(2)"), vec!["Slugga"]);
+    assert_eq!(
+        names("profiles > :first-child"),
+        vec!["Boy", "Nested", "Warboss"]
+    );
+    assert_eq!(
+        names("profile:nth-child(odd)"),
+        vec!["Boy", "Boss Nob", "Nested", "Warboss"]
+    );
+    assert_eq!(names("profile:nth-child(even)"), vec!["Slugga"]);
+    assert_eq!(names("profile:nth-child(n+2)"), vec!["Slugga", "Boss Nob"]);
+    assert_eq!(
+        names("profile:nth-child(-n+2)"),
+        vec!["Boy", "Slugga", "Nested", "Warboss"]
+    );
+    // Positions count elements only, so the whitespace between entries doesn't shift them
+    assert_eq!(
+        names("selectionEntries > selectionEntry:nth-child(3)"),
+        vec!["Gretchin"]
+    );
+}
+
+#[test]
+fn test_select_excludes_the_starting_element() {
+    let document = parse_str(CATALOGUE).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    assert_eq!(root.select("catalogue").unwrap().count(), 0);
+    assert_eq!(document.select("catalogue").unwrap().count(), 1);
+    assert_eq!(
+        document
+            .select("catalogue > selectionEntries")
+            .unwrap()
+            .count(),
+        1
+    );
+
+    let boyz = root.select_first("selectionEntry").unwrap().unwrap();
+    assert_eq!(boyz.get_attribute("name").unwrap(), "Boyz");
+    // The starting element can still satisfy a combinator
+    let names: Vec<_> = boyz
+        .select("selectionEntry > profiles > profile")
+        .unwrap()
+        .map(|profile| profile.get_attribute("name").unwrap().as_str())
+        .collect();
+    assert_eq!(names, vec!["Boy", "Slugga", "Boss Nob", "Nested"]);
+}
+
+#[test]
+fn test_compiled_selector_is_reusable() {
+    let selector: Selector = "profile[typeName=Unit]".parse().unwrap();
+    let document = parse_str(CATALOGUE).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    assert_eq!(selector.select(root).count(), 4);
+    assert_eq!(selector.select_document(&document).count(), 4);
+}
+
+// ============================================================================
+// ERROR TESTS
+// ============================================================================
+
+#[test]
+fn test_invalid_selectors() {
+    assert_eq!(
+        Selector::parse("profile[typeName=Unit").unwrap_err(),
+        SelectorError::UnexpectedEnd { position: 21 }
+    );
+    assert_eq!(
+        Selector::parse("profile:last-child").unwrap_err(),
+        SelectorError::UnknownPseudoClass {
+            name: "last-child".to_string(),
+            position: 8
+        }
+    );
+    assert_eq!(
+        Selector::parse("profiles >").unwrap_err().to_string(),
+        "offset 10: unexpected end of selector"
+    );
+    assert!(Selector::parse("").is_err());
+    assert!(Selector::parse("a,").is_err());
+    assert!(Selector::parse("a[b~=c]").is_err());
+    assert!(Selector::parse("a:nth-child(x)").is_err());
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_select_matches_hand_rolled_traversal() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let root = document.get_root_element().unwrap();
+
+    let mut expected = Vec::new();
+    collect_constraints(root, &mut expected);
+    let selected: Vec<_> = root.select("constraints > constraint").unwrap().collect();
+
+    assert!(expected.len() > 1000);
+    assert_eq!(selected.len(), expected.len());
+    assert!(selected
+        .iter()
+        .zip(&expected)
+        .all(|(found, expected)| std::ptr::eq(*found, *expected)));
+
+    let units = root
+        .select("selectionEntry[type=unit] > profiles > profile[typeName=Unit]")
+        .unwrap()
+        .count();
+    assert!(units > 0);
+}
@@ -0,0 +1,363 @@
+use crate::models::Constraint::Constraint;
+use crate::models::ConstraintType::ConstraintType;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::Tools::validator::ConstraintValidator;
+use crate::Tools::writer::{document_to_string, WriterOptions};
+use crate::{from_element, parse_file, parse_str, to_element, DeserializeError, SerializeError};
+use serde::{Deserialize, Serialize};
+
+const PROFILE: &str = r#"<profile name="Battlewagon" typeName="Unit" hidden="false">
+  <characteristics>
+    <characteristic name="M" typeId="m">10&quot;</characteristic>
+    <characteristic name="T" typeId="t">10</characteristic>
+  </characteristics>
+  <keyword>Vehicle</keyword>
+  <keyword>Transport</keyword>
+  <description>Da biggest.</description>
+</profile>"#;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    name: String,
+    type_name: String,
+    hidden: bool,
+    id: Option<String>,
+    characteristics: Vec<Characteristic>,
+    keyword: Vec<String>,
+    description: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Characteristic {
+    #[serde(rename = "@name")]
+    name: String,
+    type_id: String,
+    #[serde(rename = "$value")]
+    value: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Cost {
+    name: String,
+    value: f64,
+}
+
+// ============================================================================
+// DESERIALIZATION TESTS
+// ============================================================================
+
+#[test]
+fn test_deserialize_attributes_children_and_text() {
+    let document = parse_str(PROFILE).unwrap();
+    let profile: Profile = from_element(document.get_root_element().unwrap()).unwrap();
+
+    assert_eq!(
+        profile,
+        Profile {
+            name: "Battlewagon".to_string(),
+            type_name: "Unit".to_string(),
+            hidden: false,
+            id: None,
+            characteristics: vec![
+                Characteristic {
+                    name: "M".to_string(),
+                    type_id: "m".to_string(),
+                    value: "10\"".to_string(),
+                },
+                Characteristic {
+                    name: "T".to_string(),
+                    type_id: "t".to_string(),
+                    value: "10".to_string(),
+                },
+            ],
+            keyword: vec!["Vehicle".to_string(), "Transport".to_string()],
+            description: "Da biggest.".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_deserialize_borrowed_strings_and_maps() {
+    #[derive(Deserialize)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        #[serde(rename = "$value")]
+        text: &'a str,
+    }
+
+    let document = parse_str("<rule name='Waaagh!'>Charge</rule>").unwrap();
+    let rule: Borrowed = from_element(document.get_root_element().unwrap()).unwrap();
+    assert_eq!((rule.name, rule.text), ("Waaagh!", "Charge"));
+
+    let document = parse_str("<cost name='pts' value='85' typeId='points'/>").unwrap();
+    let map: std::collections::BTreeMap<String, String> =
+        from_element(document.get_root_element().unwrap()).unwrap();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["typeId"], "points");
+}
+
+#[test]
+fn test_deserialize_errors() {
+    let document = parse_str("<cost name='pts' value='lots'/>").unwrap();
+    let error = from_element::<Cost>(document.get_root_element().unwrap()).unwrap_err();
+    assert_eq!(
+        error,
+        DeserializeError::InvalidValue {
+            value: "lots".to_string(),
+            expected: "a number"
+        }
+    );
+
+    let document = parse_str("<cost name='pts'/>").unwrap();
+    let error = from_element::<Cost>(document.get_root_element().unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "missing field `value`");
+
+    #[derive(Debug, Deserialize)]
+    struct Single {
+        #[allow(dead_code)]
+        keyword: String,
+    }
+    let document = parse_str("<p><keyword>a</keyword><keyword>b</keyword></p>").unwrap();
+    let error = from_element::<Single>(document.get_root_element().unwrap()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "found 2 <keyword> elements where one was expected"
+    );
+}
+
+// ============================================================================
+// SERIALIZATION TESTS
+// ============================================================================
+
+#[test]
+fn test_serialize_round_trip() {
+    let document = parse_str(PROFILE).unwrap();
+    let profile: Profile = from_element(document.get_root_element().unwrap()).unwrap();
+
+    // Scalar fields become attributes, so the description moves out of its child element
+    let element = to_element(&profile, "profile").unwrap();
+    let written = document_to_string(
+        &XmlDocument {
+            root: Some(element),
+            ..Default::default()
+        },
+        &WriterOptions {
+            declaration: false,
+            ..WriterOptions::indented()
+        },
+    );
+    assert_eq!(
+        written,
+        "<profile name=\"Battlewagon\" typeName=\"Unit\" hidden=\"false\" description=\"Da biggest.\">\n\
+         \x20 <characteristics name=\"M\" typeId=\"m\">10&quot;</characteristics>\n\
+         \x20 <characteristics name=\"T\" typeId=\"t\">10</characteristics>\n\
+         \x20 <keyword>Vehicle</keyword>\n\
+         \x20 <keyword>Transport</keyword>\n\
+         </profile>\n"
+    );
+
+    let reparsed = parse_str(&written).unwrap();
+    let again: Profile = from_element(reparsed.get_root_element().unwrap()).unwrap();
+    assert_eq!(again, profile);
+}
+
+#[test]
+fn test_serialize_errors() {
+    let cost = Cost {
+        name: "pts".to_string(),
+        value: 85.0,
+    };
+    assert!(matches!(
+        to_element(&cost, "bad name"),
+        Err(SerializeError::InvalidName(_))
+    ));
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("point value", 5);
+    assert_eq!(
+        to_element(&map, "cost").unwrap_err().to_string(),
+        "\"point value\" is not a valid attribute name"
+    );
+    assert!(to_element(&vec![1, 2], "costs").is_err());
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_constraints_match_validator_parsing() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let root = document.get_root_element().unwrap();
+    let validator = ConstraintValidator::new();
+
+    let mut checked = 0;
+    for element in root.find_all("constraint") {
+        let expected = validator.parse_constraint_element(element).unwrap();
+        let constraint: Constraint = from_element(element).unwrap();
+
+        assert_eq!(constraint.to_string(), expected.to_string());
+        assert_eq!(constraint.shared, expected.shared);
+        assert_eq!(
+            constraint.include_child_selections,
+            expected.include_child_selections
+        );
+        assert_eq!(constraint.percent_value, expected.percent_value);
+        checked += 1;
+    }
+    assert!(checked > 1000);
+
+    let first: Constraint = from_element(root.find_all("constraint").next().unwrap()).unwrap();
+    assert_eq!(first.constraint_type, ConstraintType::Max);
+}
+
+#[test]
+fn test_unknown_children_are_ignored() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Named {
+        name: String,
+    }
+
+    let document =
+        parse_str(r#"<profile name="x"><keyword>a</keyword><keyword>b</keyword></profile>"#)
+            .unwrap();
+    let named: Named = from_element(document.get_root_element().unwrap()).unwrap();
+    assert_eq!(named.name, "x");
+
+    let document = parse_str(r#"<profile name="x"><costs/></profile>"#).unwrap();
+    let named: Named = from_element(document.get_root_element().unwrap()).unwrap();
+    assert_eq!(named.name, "x");
+}
+
+#[test]
+fn test_empty_child_is_not_a_wrapper() {
+    #[derive(Debug, Deserialize)]
+    struct Rule {
+        name: Option<String>,
+    }
+    #[derive(Debug, Deserialize)]
+    struct Entry {
+        rule: Vec<Rule>,
+    }
+
+    let document = parse_str("<entry><rule/></entry>").unwrap();
+    let entry: Entry = from_element(document.get_root_element().unwrap()).unwrap();
+    assert_eq!(entry.rule.len(), 1);
+    assert_eq!(entry.rule[0].name, None);
+}
+
+#[test]
+fn test_wrapped_lists_deserialize_as_vec() {
+    #[derive(Deserialize)]
+    struct Entry {
+        name: String,
+        #[serde(default)]
+        constraints: Vec<Constraint>,
+    }
+
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let battlewagon = document
+        .get_root_element()
+        .unwrap()
+        .find_by_attribute("name", "Battlewagon")
+        .find(|element| element.name == "selectionEntry")
+        .unwrap();
+
+    let entry: Entry = from_element(battlewagon).unwrap();
+    assert_eq!(entry.name, "Battlewagon");
+    assert_eq!(entry.constraints.len(), 4);
+    assert_eq!(entry.constraints[0].id, "ab5f-18dd-8d6f-b374");
+}
+
+#[test]
+fn test_wrapper_fields_round_trip_battlescribe_layout() {
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Entry {
+        id: String,
+        name: String,
+        #[serde(rename = "constraints/constraint", default)]
+        constraints: Vec<Constraint>,
+        #[serde(rename = "categoryLinks/categoryLink", default)]
+        category_links: Vec<CategoryLink>,
+    }
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CategoryLink {
+        id: String,
+        target_id: String,
+        primary: bool,
+    }
+
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let battlewagon = document
+        .get_root_element()
+        .unwrap()
+        .find_by_attribute("name", "Battlewagon")
+        .find(|element| element.name == "selectionEntry")
+        .unwrap();
+    let entry: Entry = from_element(battlewagon).unwrap();
+    assert_eq!(entry.constraints.len(), 4);
+    assert!(!entry.category_links.is_empty());
+
+    let element = to_element(&entry, "selectionEntry").unwrap();
+    let names = |parent: &XmlElement| -> Vec<String> {
+        parent
+            .child_elements()
+            .map(|child| child.name.clone())
+            .collect()
+    };
+    assert_eq!(names(&element), vec!["constraints", "categoryLinks"]);
+    let constraints = element.find_child_by_name("constraints").unwrap();
+    assert_eq!(names(constraints), vec!["constraint"; 4]);
+    assert_eq!(
+        constraints
+            .child_elements()
+            .next()
+            .unwrap()
+            .get_attribute("id"),
+        battlewagon
+            .find_child_by_name("constraints")
+            .unwrap()
+            .child_elements()
+            .next()
+            .unwrap()
+            .get_attribute("id")
+    );
+
+    let again: Entry = from_element(&element).unwrap();
+    assert_eq!(again.category_links, entry.category_links);
+    assert_eq!(
+        again
+            .constraints
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>(),
+        entry
+            .constraints
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+    );
+
+    // An empty list keeps its wrapper, and an absent wrapper reads as an empty list
+    let empty = Entry {
+        constraints: Vec::new(),
+        ..again
+    };
+    let element = to_element(&empty, "selectionEntry").unwrap();
+    assert_eq!(
+        element
+            .find_child_by_name("constraints")
+            .unwrap()
+            .children
+            .len(),
+        0
+    );
+    let document = parse_str("<selectionEntry id='a' name='b'/>").unwrap();
+    let entry: Entry = from_element(document.get_root_element().unwrap()).unwrap();
+    assert!(entry.constraints.is_empty() && entry.category_links.is_empty());
+}
@@ -0,0 +1,215 @@
+use crate::models::Traversal::{VisitControl, Visitor};
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::{parse_file, parse_str};
+
+const ROSTER: &str = r#"<roster name="Waaagh!">
+<forces>
+  <force name="Army"><selections>
+    <selection type="model" name="Boyz"/>
+    <selection type="unit" name="Nobz"><selections><selection type="model" name="Boss Nob"/></selections></selection>
+  </selections></force>
+  <force name="Allies"/>
+</forces>
+<costs><cost name="pts" value="500"/></costs>
+</roster>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// The name attribute of each element, or its tag name when it has none
+fn labels<'a>(elements: impl Iterator<Item = &'a XmlElement>) -> Vec<String> {
+    elements
+        .map(|element| {
+            element
+                .get_attribute("name")
+                .cloned()
+                .unwrap_or_else(|| element.name.clone())
+        })
+        .collect()
+}
+
+/// Records enter/leave calls as "+label@depth" and "-label"
+struct Recorder {
+    events: Vec<String>,
+    skip: &'static str,
+}
+
+impl Visitor for Recorder {
+    fn enter(&mut self, element: &XmlElement, depth: usize) -> VisitControl {
+        let label = labels(std::iter::once(element)).remove(0);
+        self.events.push(format!("+{}@{}", label, depth));
+        if label == self.skip {
+            VisitControl::SkipChildren
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn leave(&mut self, element: &XmlElement, _depth: usize) {
+        self.events
+            .push(format!("-{}", labels(std::iter::once(element)).remove(0)));
+    }
+
+    fn content(&mut self, node: &XmlNode, depth: usize) {
+        if let XmlNode::Text(text) = node {
+            self.events.push(format!("text:{}@{}", text, depth));
+        }
+    }
+}
+
+// ============================================================================
+// ITERATOR TESTS
+// ============================================================================
+
+#[test]
+fn test_child_elements_and_children_named() {
+    let document = parse_str(ROSTER).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    assert_eq!(labels(root.child_elements()), vec!["forces", "costs"]);
+    let forces = root.find_child_by_name("forces").unwrap();
+    assert_eq!(
+        labels(forces.children_named("force")),
+        vec!["Army", "Allies"]
+    );
+    assert_eq!(forces.children_named("selection").count(), 0);
+}
+
+#[test]
+fn test_descendants_depth_and_breadth_first() {
+    let document = parse_str(ROSTER).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    assert_eq!(
+        labels(root.descendants()),
+        vec![
+            "forces",
+            "Army",
+            "selections",
+            "Boyz",
+            "Nobz",
+            "selections",
+            "Boss Nob",
+            "Allies",
+            "costs",
+            "pts"
+        ]
+    );
+    assert_eq!(
+        labels(root.descendants_breadth_first()),
+        vec![
+            "forces",
+            "costs",
+            "Army",
+            "Allies",
+            "pts",
+            "selections",
+            "Boyz",
+            "Nobz",
+            "selections",
+            "Boss Nob"
+        ]
+    );
+    assert_eq!(XmlElement::new("empty").descendants().count(), 0);
+}
+
+#[test]
+fn test_find_all_and_find_by_attribute() {
+    let document = parse_str(ROSTER).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    assert_eq!(
+        labels(root.find_all("selection")),
+        vec!["Boyz", "Nobz", "Boss Nob"]
+    );
+    assert_eq!(
+        labels(root.find_by_attribute("type", "model")),
+        vec!["Boyz", "Boss Nob"]
+    );
+    // The element searched from is not included
+    assert_eq!(root.find_all("roster").count(), 0);
+    assert_eq!(root.find_by_attribute("name", "Waaagh!").count(), 0);
+}
+
+// ============================================================================
+// VISITOR TESTS
+// ============================================================================
+
+#[test]
+fn test_visit_calls_enter_and_leave_and_skips_subtrees() {
+    let document = parse_str("<force name='Army'>Orks<selection name='Nobz'><selection name='Boss Nob'/></selection><selection name='Boyz'/></force>").unwrap();
+    let root = document.get_root_element().unwrap();
+
+    let mut recorder = Recorder {
+        events: Vec::new(),
+        skip: "Nobz",
+    };
+    assert_eq!(root.visit(&mut recorder), VisitControl::Continue);
+    assert_eq!(
+        recorder.events,
+        vec![
+            "+Army@0",
+            "text:Orks@1",
+            "+Nobz@1",
+            "-Nobz",
+            "+Boyz@1",
+            "-Boyz",
+            "-Army"
+        ]
+    );
+}
+
+#[test]
+fn test_visit_with_closure_can_stop() {
+    let document = parse_str(ROSTER).unwrap();
+    let root = document.get_root_element().unwrap();
+
+    let mut entered = Vec::new();
+    let result = root.visit(&mut |element: &XmlElement, depth: usize| {
+        entered.push((element.name.clone(), depth));
+        if element
+            .get_attribute("name")
+            .is_some_and(|name| name == "Nobz")
+        {
+            VisitControl::Stop
+        } else {
+            VisitControl::Continue
+        }
+    });
+
+    assert_eq!(result, VisitControl::Stop);
+    assert_eq!(entered.last().unwrap(), &("selection".to_string(), 4));
+    assert_eq!(entered.len(), 6);
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_traversals_agree_on_catalogue() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+    let root = document.get_root_element().unwrap();
+
+    let depth_first = root.descendants().count();
+    assert_eq!(root.descendants_breadth_first().count(), depth_first);
+
+    let mut visited = 0;
+    root.visit(&mut |_: &XmlElement, _: usize| {
+        visited += 1;
+        VisitControl::Continue
+    });
+    // visit includes the root itself
+    assert_eq!(visited, depth_first + 1);
+
+    let constraints = root.find_all("constraint").count();
+    assert!(constraints > 1000);
+    assert_eq!(
+        root.find_by_attribute("field", "selections").count(),
+        root.descendants()
+            .filter(|e| e.get_attribute("field").is_some_and(|f| f == "selections"))
+            .count()
+    );
+}
@@ -1,10 +1,10 @@
+use crate::models::Attribute::Attributes;
 use crate::models::Constraint::Constraint;
 use crate::models::ConstraintType::ConstraintType;
 use crate::models::ValidationResult::ValidationResult;
 use crate::models::XmlElement::XmlElement;
 use crate::models::XmlNode::XmlNode;
 use crate::Tools::validator::ConstraintValidator;
-use std::collections::HashMap;
 
 // ============================================================================
 // HELPER FUNCTIONS
@@ -18,7 +18,7 @@ fn create_test_constraint_element(
     shared: &str,
     id: &str,
 ) -> XmlElement {
-    let mut attributes = HashMap::new();
+    let mut attributes = Attributes::new();
     attributes.insert("type".to_string(), constraint_type.to_string());
     attributes.insert("value".to_string(), value.to_string());
     attributes.insert("field".to_string(), field.to_string());
@@ -27,9 +27,8 @@ fn create_test_constraint_element(
     attributes.insert("id".to_string(), id.to_string());
 
     XmlElement {
-        name: "constraint".to_string(),
         attributes,
-        children: Vec::new(),
+        ..XmlElement::new("constraint")
     }
 }
 
@@ -37,9 +36,8 @@ fn create_test_constraints_element(constraints: Vec<XmlElement>) -> XmlElement {
     let children: Vec<XmlNode> = constraints.into_iter().map(XmlNode::Element).collect();
 
     XmlElement {
-        name: "constraints".to_string(),
-        attributes: HashMap::new(),
         children,
+        ..XmlElement::new("constraints")
     }
 }
 
@@ -432,6 +430,26 @@ fn test_parse_constraint_element_missing_id() {
     assert!(result.unwrap_err().contains("Constraint id is required"));
 }
 
+#[test]
+fn test_parse_constraint_element_reads_flags_leniently() {
+    let validator = ConstraintValidator::new();
+    let mut constraint_element =
+        create_test_constraint_element("min", "2", "selections", "parent", "yes", "test-id");
+    constraint_element
+        .attributes
+        .insert("includeChildSelections", "1");
+    constraint_element.attributes.insert("percentValue", "true");
+
+    // Only "true" is true, as it always has been for constraint flags
+    let constraint = validator
+        .parse_constraint_element(&constraint_element)
+        .unwrap();
+    assert!(!constraint.shared);
+    assert_eq!(constraint.include_child_selections, Some(false));
+    assert_eq!(constraint.percent_value, Some(true));
+    assert_eq!(constraint.include_child_forces, None);
+}
+
 #[test]
 fn test_parse_constraints_from_element_wrong_element_name() {
     let mut validator = ConstraintValidator::new();
@@ -0,0 +1,265 @@
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::decompression::decompress_rosz_file;
+use crate::Tools::lexical_analysis::{tokenize_with, TokenizerOptions};
+use crate::Tools::parse_tokens::parse_tokens;
+use crate::Tools::writer::{document_to_string, escape, WriterOptions, XmlWriter};
+use crate::{parse_bytes, parse_file, parse_file_lossless, parse_str, parse_str_lossless};
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Write a single element with the given options
+fn element_to_string(element: &XmlElement, options: WriterOptions) -> String {
+    let mut writer = XmlWriter::with_options(Vec::new(), options);
+    writer
+        .write_element(element)
+        .expect("Failed to write element");
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+// ============================================================================
+// ESCAPING TESTS
+// ============================================================================
+
+#[test]
+fn test_escape_predefined_entities() {
+    assert!(matches!(escape("Waaagh!"), std::borrow::Cow::Borrowed(_)));
+    assert_eq!(
+        escape(r#"'Ard Case & 10" <Vehicle>"#),
+        "&apos;Ard Case &amp; 10&quot; &lt;Vehicle&gt;"
+    );
+}
+
+#[test]
+fn test_escaped_output_parses_back() {
+    let mut element = XmlElement::new("characteristic");
+    element.set_attribute("name", "Range & \"Type\"").unwrap();
+    element
+        .children
+        .push(XmlNode::Text("24\" <Assault>".to_string()));
+
+    let xml = element_to_string(&element, WriterOptions::compact());
+    assert_eq!(
+        xml,
+        "<characteristic name=\"Range &amp; &quot;Type&quot;\">24&quot; &lt;Assault&gt;</characteristic>"
+    );
+
+    let document = parse_str(&xml).unwrap();
+    let root = document.get_root_element().unwrap();
+    assert_eq!(root.get_attribute("name").unwrap(), "Range & \"Type\"");
+    assert_eq!(root.get_text_content(), "24\" <Assault>");
+}
+
+// ============================================================================
+// LAYOUT TESTS
+// ============================================================================
+
+#[test]
+fn test_write_compact_and_indented() {
+    let xml = r#"<roster name='Waaagh!'><!--army--><forces><force /></forces><rule><![CDATA[a < b]]></rule></roster>"#;
+    let document = parse_str(xml).unwrap();
+
+    let compact = document_to_string(&document, &WriterOptions::compact());
+    assert_eq!(
+        compact,
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", xml)
+    );
+
+    let indented = document_to_string(&document, &WriterOptions::indented());
+    assert_eq!(
+        indented,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <roster name='Waaagh!'>\n\
+         \x20 <!--army-->\n\
+         \x20 <forces>\n\
+         \x20   <force/>\n\
+         \x20 </forces>\n\
+         \x20 <rule><![CDATA[a < b]]></rule>\n\
+         </roster>\n"
+    );
+}
+
+#[test]
+fn test_write_without_declaration_or_self_closing() {
+    let document =
+        parse_str("<?xml version=\"1.0\"?><forces><force/><force a='1'/></forces>").unwrap();
+    let options = WriterOptions {
+        declaration: false,
+        self_close_empty: false,
+        ..WriterOptions::compact()
+    };
+
+    assert_eq!(
+        document_to_string(&document, &options),
+        "<forces><force></force><force a='1'></force></forces>"
+    );
+
+    let options = WriterOptions {
+        declaration: false,
+        space_before_slash: false,
+        ..WriterOptions::compact()
+    };
+    assert_eq!(
+        document_to_string(&document, &options),
+        "<forces><force/><force a='1'/></forces>"
+    );
+}
+
+#[test]
+fn test_decoded_input_is_written_back_as_utf8() {
+    let latin1 = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><r a=\"caf\xe9\"/>";
+    let document = parse_bytes(latin1).unwrap();
+
+    for options in [WriterOptions::compact(), WriterOptions::lossless()] {
+        let output = document_to_string(&document, &options);
+        assert!(output.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+
+        let reparsed = parse_bytes(output.as_bytes()).unwrap();
+        assert_eq!(
+            reparsed
+                .root
+                .unwrap()
+                .get_attribute("a")
+                .map(String::as_str),
+            Some("café")
+        );
+    }
+
+    // The lossless path rewrites the recorded declaration rather than copying it
+    let document =
+        parse_str_lossless("<?xml version='1.0' encoding='ISO-8859-1'?><r a='café'/>").unwrap();
+    let output = document_to_string(&document, &WriterOptions::lossless());
+    assert_eq!(
+        output,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><r a='café'/>"
+    );
+    let reparsed = parse_bytes(output.as_bytes()).unwrap();
+    assert_eq!(
+        reparsed
+            .root
+            .unwrap()
+            .get_attribute("a")
+            .map(String::as_str),
+        Some("café")
+    );
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_indented_output_matches_bsdata_catalogue() {
+    let original = std::fs::read_to_string("example-data/Orks.cat").unwrap();
+    let document = parse_file("example-data/Orks.cat").unwrap();
+
+    let written = document_to_string(&document, &WriterOptions::indented());
+
+    assert_eq!(written, original);
+}
+
+#[test]
+fn test_compact_output_reparses_to_the_same_roster() {
+    let original = std::fs::read_to_string("example-data/Tts game teams.ros").unwrap();
+    // Description text starts and ends with newlines that the default trimming would drop
+    let options = TokenizerOptions::preserve_whitespace();
+    let document = parse_tokens(tokenize_with(&original, &options).unwrap()).unwrap();
+
+    let mut writer = XmlWriter::new(Vec::new());
+    writer.write_document(&document).unwrap();
+    let written = String::from_utf8(writer.into_inner()).unwrap();
+
+    // Same layout as newrecruit: the declaration, then the whole roster on one line
+    assert!(written
+        .starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<roster "));
+    assert!(written.contains("value=\"1990\" /></costs>"));
+
+    let reparsed = parse_tokens(tokenize_with(&written, &options).unwrap()).unwrap();
+    assert_eq!(
+        document_to_string(&reparsed, &WriterOptions::compact()),
+        written
+    );
+    assert_eq!(
+        1 + reparsed.get_root_element().unwrap().descendants().count(),
+        1 + document.get_root_element().unwrap().descendants().count()
+    );
+}
+
+// ============================================================================
+// LOSSLESS ROUND-TRIP TESTS
+// ============================================================================
+
+/// Parse losslessly and write straight back
+fn round_trip(xml: &str) -> String {
+    let document = parse_str_lossless(xml).expect("Failed to parse losslessly");
+    document_to_string(&document, &WriterOptions::lossless())
+}
+
+#[test]
+fn test_lossless_round_trip_keeps_source_spelling() {
+    let xml = "<?xml version='1.0'  encoding=\"UTF-8\"?>\n\
+               <!DOCTYPE roster SYSTEM \"roster.dtd\">\n\
+               <?editor   mode=\"raw\"?>\n\
+               <roster  name = 'Orks &amp; Grots'\n\
+               \x20       note=\"&#39;Ard Boyz&#x27;\" >\n\
+               \x20 <!-- army -->\n\
+               \x20 <force name=\"Army\"></force>\n\
+               \x20 <force name=\"Allies\" />\n\
+               \x20 <rule>Waaagh!'s &quot;charge&quot; &gt; 6<![CDATA[<b>]]></rule>\n\
+               </roster >\n\
+               <!-- end -->";
+
+    assert_eq!(round_trip(xml), xml);
+}
+
+#[test]
+fn test_lossless_round_trip_over_example_data() {
+    let mut checked = 0;
+    for entry in std::fs::read_dir("example-data").expect("Failed to read example-data") {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if name.starts_with("temp_") {
+            continue;
+        }
+
+        let sources = if name.ends_with(".rosz") {
+            decompress_rosz_file(path.to_str().unwrap())
+                .expect("Failed to decompress .rosz file")
+                .into_iter()
+                .map(|file| (format!("{}/{}", name, file.filename), file.content))
+                .collect()
+        } else {
+            vec![(name, std::fs::read_to_string(&path).unwrap())]
+        };
+
+        for (name, source) in sources {
+            assert!(
+                round_trip(&source) == source,
+                "{} changed on round trip",
+                name
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked >= 6);
+}
+
+#[test]
+fn test_lossless_edit_only_changes_the_edited_attribute() {
+    let original = std::fs::read_to_string("example-data/Orks.cat").unwrap();
+    let mut document = parse_file_lossless("example-data/Orks.cat").unwrap();
+
+    let root = document.root.as_mut().unwrap();
+    root.set_attribute("revision", "102").unwrap();
+    root.children
+        .push(XmlNode::Element(XmlElement::new("publications")));
+
+    let written = document_to_string(&document, &WriterOptions::lossless());
+
+    let expected = original
+        .replacen("revision=\"101\"", "revision=\"102\"", 1)
+        .replacen("</catalogue>", "<publications/></catalogue>", 1);
+    assert_eq!(written, expected);
+}
@@ -0,0 +1,303 @@
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::xpath::{select, XPath, XPathError, XPathNode};
+use crate::{parse_file, parse_str, parse_str_lossless};
+
+const ROSTER: &str = r#"<roster name="Waaagh!">
+<forces>
+  <force name="Army"><selections>
+    <selection type="model" name="Boyz" number="10"/>
+    <selection type="unit" name="Nobz"><selections><selection type="model" name="Boss Nob" number="1"/></selections></selection>
+    <selection type="model" name="Grot" number="3"/>
+  </selections></force>
+  <force name="Allies"><selections><selection type="model" name="Gretchin" number="20"/></selections></force>
+</forces>
+<rules><rule name="Waaagh!"><description>Charge <b>twice</b></description></rule></rules>
+</roster>"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Run a query and return the string value of every match
+fn values(xml: &str, expression: &str) -> Vec<String> {
+    let document = parse_str(xml).expect("Failed to parse");
+    select(&document, expression)
+        .expect("Failed to compile expression")
+        .iter()
+        .map(XPathNode::string_value)
+        .collect()
+}
+
+/// Run a query against ROSTER and return the name attribute of every matched element
+fn names(expression: &str) -> Vec<String> {
+    let document = parse_str(ROSTER).expect("Failed to parse");
+    select(&document, expression)
+        .expect("Failed to compile expression")
+        .iter()
+        .map(|node| {
+            node.as_element()
+                .and_then(|element| element.get_attribute("name"))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Collect every descendant element with the given name, depth first
+fn collect_named<'a>(element: &'a XmlElement, name: &str, found: &mut Vec<&'a XmlElement>) {
+    for child in &element.children {
+        if let XmlNode::Element(child) = child {
+            if child.name == name {
+                found.push(child);
+            }
+            collect_named(child, name, found);
+        }
+    }
+}
+
+// ============================================================================
+// PATH TESTS
+// ============================================================================
+
+#[test]
+fn test_absolute_and_descendant_paths() {
+    assert_eq!(names("/roster"), vec!["Waaagh!"]);
+    assert_eq!(names("/roster/forces/force"), vec!["Army", "Allies"]);
+    assert_eq!(names("/force"), Vec::<String>::new());
+    assert_eq!(
+        names("//selection"),
+        vec!["Boyz", "Nobz", "Boss Nob", "Grot", "Gretchin"]
+    );
+    assert_eq!(
+        names("/roster//force[@name='Army']//selection/selections/*"),
+        vec!["Boss Nob"]
+    );
+}
+
+#[test]
+fn test_attribute_and_text_selection() {
+    assert_eq!(
+        values(ROSTER, "//force/@name"),
+        vec!["Army".to_string(), "Allies".to_string()]
+    );
+    assert_eq!(values(ROSTER, "/roster/@*"), vec!["Waaagh!"]);
+    assert_eq!(values(ROSTER, "//description/text()"), vec!["Charge"]);
+    // An element's string value includes the text of its descendants; keep the whitespace
+    // between them, which the default parse trims
+    let document = parse_str_lossless(ROSTER).unwrap();
+    let description = select(&document, "//description").unwrap();
+    assert_eq!(description[0].string_value(), "Charge twice");
+}
+
+#[test]
+fn test_text_and_attribute_matches_come_in_document_order() {
+    // The root's own text follows the text of the element before it
+    assert_eq!(
+        values("<r><a>1</a>2<b x='3'>4</b></r>", "//text()"),
+        vec!["1", "2", "4"]
+    );
+    assert_eq!(
+        values("<r x='1'><a x='2'><b x='3'/></a><c x='4'/></r>", "//*//@x"),
+        vec!["1", "2", "3", "4"]
+    );
+
+    // Equal text nodes are still distinct nodes
+    let mut document = parse_str("<r/>").unwrap();
+    let root = document.root.as_mut().unwrap();
+    root.append_child(XmlNode::Text(String::new())).unwrap();
+    root.append_child(XmlNode::Text(String::new())).unwrap();
+    assert_eq!(select(&document, "/r/text()").unwrap().len(), 2);
+    assert_eq!(select(&document, "//r//text()").unwrap().len(), 2);
+}
+
+// ============================================================================
+// PREDICATE TESTS
+// ============================================================================
+
+#[test]
+fn test_attribute_predicates() {
+    assert_eq!(
+        names("//selection[@type='model']"),
+        vec!["Boyz", "Boss Nob", "Grot", "Gretchin"]
+    );
+    assert_eq!(names("//selection[@type!='model']"), vec!["Nobz"]);
+    assert_eq!(names("//*[@number]"), names("//selection[@type='model']"));
+    assert_eq!(
+        names("//selection[@type='model' and @number > 5]"),
+        vec!["Boyz", "Gretchin"]
+    );
+    assert_eq!(
+        names("//selection[@name=\"Grot\" or not(@number)]"),
+        vec!["Nobz", "Grot"]
+    );
+    assert_eq!(
+        names("//force[selections/selection/@name='Gretchin']"),
+        vec!["Allies"]
+    );
+    assert_eq!(
+        names("//selection[starts-with(@name, 'G') and contains(@name, 'r')]"),
+        vec!["Grot", "Gretchin"]
+    );
+    assert_eq!(names("//force[count(.//selection) = 4]"), vec!["Army"]);
+}
+
+#[test]
+fn test_absolute_paths_in_predicates_start_at_the_root() {
+    assert_eq!(
+        names("//selection[/roster/@name='Waaagh!'][@number > 5]"),
+        vec!["Boyz", "Gretchin"]
+    );
+    assert!(names("//selection[/roster/@name='Grots']").is_empty());
+    // The rule shares the roster's name; an anchor at the context node would find nothing
+    assert_eq!(names("//rule[@name = /roster/@name]"), vec!["Waaagh!"]);
+
+    let document = parse_str(ROSTER).unwrap();
+    let army = select(&document, "//force[@name='Army']").unwrap()[0]
+        .as_element()
+        .unwrap();
+    let relative = XPath::compile("selections/selection[/force/@name='Army']").unwrap();
+    assert_eq!(relative.select_from(army).len(), 3);
+}
+
+#[test]
+fn test_boolean_comparisons_follow_xpath_boolean() {
+    let xml = "<r><e n='1' a=''/><e n='2' a='0'/><e n='3' a='x'/><e n='4'/></r>";
+    // A node-set is true when it is non-empty, whatever its nodes hold
+    assert_eq!(values(xml, "//e[@a = true()]/@n"), vec!["1", "2", "3"]);
+    assert_eq!(values(xml, "//e[@a = false()]/@n"), vec!["4"]);
+    // A string is true when it is non-empty, whatever number it spells
+    assert_eq!(values(xml, "//e[string(@a) = true()]/@n"), vec!["2", "3"]);
+    assert_eq!(values(xml, "//e[string(@a) != true()]/@n"), vec!["1", "4"]);
+    assert_eq!(values(xml, "//e[boolean(string(@a))]/@n"), vec!["2", "3"]);
+    // A number is true when it is neither zero nor NaN
+    assert_eq!(values(xml, "//e[@n = 2 = true()]/@n"), vec!["2"]);
+}
+
+#[test]
+fn test_positional_predicates_apply_per_parent() {
+    // Like XPath, //selection[1] is the first selection of every selections element
+    assert_eq!(
+        names("//selection[1]"),
+        vec!["Boyz", "Boss Nob", "Gretchin"]
+    );
+    assert_eq!(
+        names("//force[1]//selection[last()]"),
+        vec!["Boss Nob", "Grot"]
+    );
+    assert_eq!(names("/roster/forces/force[2]"), vec!["Allies"]);
+    assert_eq!(
+        names("//force[@name='Army']/selections/selection[position() > 1]"),
+        vec!["Nobz", "Grot"]
+    );
+    assert_eq!(
+        names("//force[@name='Army']/selections/selection[@type='model'][2]"),
+        vec!["Grot"]
+    );
+}
+
+#[test]
+fn test_select_from_element() {
+    let document = parse_str(ROSTER).unwrap();
+    let allies = select(&document, "//force[@name='Allies']").unwrap()[0]
+        .as_element()
+        .unwrap();
+
+    let relative = XPath::compile("selections/selection/@name").unwrap();
+    let matches = relative.select_from(allies);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].as_attribute().unwrap().value, "Gretchin");
+
+    // Absolute paths treat the element as the root
+    let absolute = XPath::compile("/force/@name").unwrap();
+    assert_eq!(absolute.select_from(allies)[0].string_value(), "Allies");
+}
+
+// ============================================================================
+// ERROR TESTS
+// ============================================================================
+
+#[test]
+fn test_invalid_expressions() {
+    assert_eq!(
+        XPath::compile("//selection[@type='model'").unwrap_err(),
+        XPathError::UnexpectedEnd { position: 25 }
+    );
+    assert_eq!(
+        XPath::compile("//selection]").unwrap_err(),
+        XPathError::UnexpectedToken {
+            found: "]".to_string(),
+            position: 11
+        }
+    );
+    assert_eq!(
+        XPath::compile("//selection[lower-case(@name)='boyz']").unwrap_err(),
+        XPathError::UnknownFunction {
+            name: "lower-case".to_string(),
+            position: 12
+        }
+    );
+    assert!(XPath::compile("//selection[@name='Boyz]").is_err());
+    assert!(XPath::compile("").is_err());
+    assert!(XPath::compile("/").is_err());
+    assert_eq!(
+        XPath::compile("a//").unwrap_err().to_string(),
+        "offset 3: unexpected end of expression"
+    );
+}
+
+// ============================================================================
+// FILE TESTS
+// ============================================================================
+
+#[test]
+fn test_query_bsdata_catalogue() {
+    let document = parse_file("example-data/Orks.cat").expect("Failed to parse Orks.cat");
+
+    let matches = select(
+        &document,
+        "//selectionEntry[@name='Battlewagon']//constraint[@field='selections']",
+    )
+    .expect("Failed to compile expression");
+
+    // Same constraints found by walking the tree by hand
+    let mut entries = Vec::new();
+    collect_named(
+        document.get_root_element().unwrap(),
+        "selectionEntry",
+        &mut entries,
+    );
+    let battlewagon = entries
+        .iter()
+        .find(|entry| {
+            entry
+                .get_attribute("name")
+                .is_some_and(|n| n == "Battlewagon")
+        })
+        .expect("Orks.cat has a Battlewagon");
+    let mut constraints = Vec::new();
+    collect_named(battlewagon, "constraint", &mut constraints);
+    constraints.retain(|c| c.get_attribute("field").is_some_and(|f| f == "selections"));
+
+    assert!(constraints.len() > 1);
+    assert_eq!(matches.len(), constraints.len());
+    for (found, expected) in matches.iter().zip(&constraints) {
+        assert!(std::ptr::eq(found.as_element().unwrap(), *expected));
+    }
+    assert_eq!(
+        matches[0]
+            .as_element()
+            .unwrap()
+            .get_attribute("id")
+            .unwrap(),
+        "ab5f-18dd-8d6f-b374"
+    );
+
+    let models = XPath::compile("//selectionEntry[@type='model']/@name").unwrap();
+    let names: Vec<String> = models
+        .select(&document)
+        .iter()
+        .map(XPathNode::string_value)
+        .collect();
+    assert!(names.contains(&"Battlewagon".to_string()));
+}
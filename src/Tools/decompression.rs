@@ -1,21 +1,39 @@
-use anyhow::{Context, Result};
+use crate::Tools::encoding::decode_bytes;
+use anyhow::{anyhow, Context, Result};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek};
 use std::path::Path;
 use zip::ZipArchive;
 
 #[derive(Debug)]
 pub struct DecompressedFile {
     pub filename: String,
+    // Text decoded from `raw` according to its BOM or XML declaration
     pub content: String,
+    pub raw: Vec<u8>,
 }
 
-pub fn decompress_zip_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<DecompressedFile>> {
+// Opens the archive without reading any entries, so an entry can be streamed with
+// `archive.by_index(i)` or `archive.by_name(name)` instead of being read into memory
+pub fn open_zip_archive<P: AsRef<Path>>(file_path: P) -> Result<ZipArchive<BufReader<File>>> {
     let file = File::open(&file_path)
         .with_context(|| format!("Failed to open file: {:?}", file_path.as_ref()))?;
 
-    let mut archive =
-        ZipArchive::new(BufReader::new(file)).with_context(|| "Failed to read ZIP archive")?;
+    ZipArchive::new(BufReader::new(file)).with_context(|| "Failed to read ZIP archive")
+}
+
+// Index of the first .xml/.cat/.ros entry in the archive
+pub fn find_xml_entry<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<usize> {
+    (0..archive.len()).find(|&i| {
+        archive
+            .by_index_raw(i)
+            .map(|file| is_xml_file_name(file.name()))
+            .unwrap_or(false)
+    })
+}
+
+pub fn decompress_zip_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<DecompressedFile>> {
+    let mut archive = open_zip_archive(file_path)?;
 
     let mut decompressed_files = Vec::new();
 
@@ -32,11 +50,18 @@ pub fn decompress_zip_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<Decompres
         }
 
         // Read file content
-        let mut content = String::new();
-        file.read_to_string(&mut content)
+        let mut raw = Vec::new();
+        file.read_to_end(&mut raw)
             .with_context(|| format!("Failed to read file content: {}", filename))?;
+        let content = decode_bytes(&raw)
+            .map_err(|error| anyhow!("Failed to decode {}: {}", filename, error))?
+            .into_owned();
 
-        decompressed_files.push(DecompressedFile { filename, content });
+        decompressed_files.push(DecompressedFile {
+            filename,
+            content,
+            raw,
+        });
     }
 
     Ok(decompressed_files)
@@ -50,10 +75,10 @@ pub fn decompress_rosz_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<Decompre
 pub fn find_xml_files(decompressed_files: &[DecompressedFile]) -> Vec<&DecompressedFile> {
     decompressed_files
         .iter()
-        .filter(|file| {
-            file.filename.ends_with(".xml")
-                || file.filename.ends_with(".cat")
-                || file.filename.ends_with(".ros")
-        })
+        .filter(|file| is_xml_file_name(&file.filename))
         .collect()
 }
+
+fn is_xml_file_name(name: &str) -> bool {
+    name.ends_with(".xml") || name.ends_with(".cat") || name.ends_with(".ros")
+}
@@ -0,0 +1,641 @@
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use serde::de::value::CowStrDeserializer;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
+
+// Reads an element through serde's data model:
+// - attributes and child elements are map entries, i.e. struct fields, by name; a field named
+//   "@name" only ever matches the attribute
+// - children sharing a name form a sequence; so does a lone wrapper child with no attributes or
+//   text, such as <constraints>, whose own children all share one other name and become the items
+// - a field named "wrapper/item" reads the <item> children of <wrapper>, and is written back the
+//   same way by the serializer
+// - the element's text is the "$value" field, and an element holding only text reads directly
+//   as a string, number or bool
+// - attribute values and text read as bools ("true"/"false"/"1"/"0"), numbers, strings, unit
+//   enum variants, or whitespace-separated lists
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeserializeError {
+    Custom(String),
+    InvalidValue {
+        value: String,
+        expected: &'static str,
+    },
+    // Several children with this name where the target holds a single value
+    RepeatedElement {
+        name: String,
+        count: usize,
+    },
+}
+
+impl std::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeserializeError::Custom(message) => write!(f, "{}", message),
+            DeserializeError::InvalidValue { value, expected } => {
+                write!(f, "invalid value \"{}\", expected {}", value, expected)
+            }
+            DeserializeError::RepeatedElement { name, count } => {
+                write!(
+                    f,
+                    "found {} <{}> elements where one was expected",
+                    count, name
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        DeserializeError::Custom(message.to_string())
+    }
+}
+
+pub fn from_element<'de, T: Deserialize<'de>>(
+    element: &'de XmlElement,
+) -> Result<T, DeserializeError> {
+    T::deserialize(ElementDeserializer { element })
+}
+
+// ============================================================================
+// TEXT
+// ============================================================================
+
+// An attribute value or an element's text
+struct TextDeserializer<'de> {
+    text: Cow<'de, str>,
+}
+
+impl<'de> TextDeserializer<'de> {
+    fn parse<T: std::str::FromStr>(&self, expected: &'static str) -> Result<T, DeserializeError> {
+        self.text
+            .trim()
+            .parse()
+            .map_err(|_| DeserializeError::InvalidValue {
+                value: self.text.to_string(),
+                expected,
+            })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident, $expected:literal;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse($expected)?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text {
+            Cow::Borrowed(text) => visitor.visit_borrowed_str(text),
+            Cow::Owned(text) => visitor.visit_string(text),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.text.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(DeserializeError::InvalidValue {
+                value: self.text.to_string(),
+                expected: "a boolean",
+            }),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8, "an integer";
+        deserialize_i16 => visit_i16, "an integer";
+        deserialize_i32 => visit_i32, "an integer";
+        deserialize_i64 => visit_i64, "an integer";
+        deserialize_i128 => visit_i128, "an integer";
+        deserialize_u8 => visit_u8, "an unsigned integer";
+        deserialize_u16 => visit_u16, "an unsigned integer";
+        deserialize_u32 => visit_u32, "an unsigned integer";
+        deserialize_u64 => visit_u64, "an unsigned integer";
+        deserialize_u128 => visit_u128, "an unsigned integer";
+        deserialize_f32 => visit_f32, "a number";
+        deserialize_f64 => visit_f64, "a number";
+        deserialize_char => visit_char, "a single character";
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // xs:list style: whitespace-separated items
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let items: Vec<Cow<'de, str>> = match self.text {
+            Cow::Borrowed(text) => text.split_whitespace().map(Cow::Borrowed).collect(),
+            Cow::Owned(text) => text
+                .split_whitespace()
+                .map(|item| Cow::Owned(item.to_string()))
+                .collect(),
+        };
+        visitor.visit_seq(TextSeq {
+            items: items.into_iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::invalid_type(
+            Unexpected::Str(&self.text),
+            &visitor,
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(CowStrDeserializer::new(self.text))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf identifier
+    }
+}
+
+struct TextSeq<'de> {
+    items: std::vec::IntoIter<Cow<'de, str>>,
+}
+
+impl<'de> SeqAccess<'de> for TextSeq<'de> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.items
+            .next()
+            .map(|text| seed.deserialize(TextDeserializer { text }))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+// ============================================================================
+// ELEMENTS
+// ============================================================================
+
+struct ElementDeserializer<'de> {
+    element: &'de XmlElement,
+}
+
+impl<'de> ElementDeserializer<'de> {
+    fn text(&self) -> TextDeserializer<'de> {
+        TextDeserializer {
+            text: element_text(self.element),
+        }
+    }
+
+    fn holds_only_text(&self) -> bool {
+        self.element.attributes.is_empty() && self.element.child_elements().next().is_none()
+    }
+}
+
+fn element_text(element: &XmlElement) -> Cow<'_, str> {
+    match element.children.as_slice() {
+        [XmlNode::Text(text) | XmlNode::CData(text)] => Cow::Borrowed(text),
+        _ => Cow::Owned(element.get_text_content()),
+    }
+}
+
+macro_rules! deserialize_text {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                de::Deserializer::$method(self.text(), visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.holds_only_text() {
+            de::Deserializer::deserialize_any(self.text(), visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_text! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_seq(ElementSeq {
+            elements: self
+                .element
+                .child_elements()
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(ElementMap::new(self.element, &[]))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(ElementMap::new(self.element, fields))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_enum(self.text(), name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct ElementSeq<'de> {
+    elements: std::vec::IntoIter<&'de XmlElement>,
+}
+
+impl<'de> SeqAccess<'de> for ElementSeq<'de> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.elements
+            .next()
+            .map(|element| seed.deserialize(ElementDeserializer { element }))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+// ============================================================================
+// FIELDS
+// ============================================================================
+
+enum FieldValue<'de> {
+    Text(Cow<'de, str>),
+    // Every child element with the field's name
+    Elements(&'de str, Vec<&'de XmlElement>),
+    // The items of a "wrapper/item" field, already taken out of their wrapper
+    Items(&'de str, Vec<&'de XmlElement>),
+}
+
+// Attributes, then child elements grouped by name in order of first appearance, then text
+struct ElementMap<'de> {
+    entries: std::vec::IntoIter<(Cow<'de, str>, FieldValue<'de>)>,
+    value: Option<FieldValue<'de>>,
+}
+
+impl<'de> ElementMap<'de> {
+    fn new(element: &'de XmlElement, fields: &'static [&'static str]) -> Self {
+        let mut entries: Vec<(Cow<'de, str>, FieldValue<'de>)> = Vec::new();
+
+        for attribute in element.attributes_in_order() {
+            let prefixed = format!("@{}", attribute.name);
+            let key = if fields.contains(&prefixed.as_str()) {
+                Cow::Owned(prefixed)
+            } else {
+                Cow::Borrowed(attribute.name.as_str())
+            };
+            entries.push((key, FieldValue::Text(Cow::Borrowed(&attribute.value))));
+        }
+
+        // (key, wrapper, item) for every "wrapper/item" field
+        let wrapped: Vec<(&str, &str, &str)> = fields
+            .iter()
+            .filter_map(|&field| {
+                let (wrapper, item) = field.split_once('/')?;
+                Some((field, wrapper, item))
+            })
+            .collect();
+
+        let mut groups: Vec<(&'de str, FieldValue<'de>)> = Vec::new();
+        for child in element.child_elements() {
+            let wrapper = wrapped
+                .iter()
+                .find(|(_, wrapper, _)| *wrapper == child.name);
+            if let Some(&(field, _, item)) = wrapper {
+                let items = child
+                    .child_elements()
+                    .filter(|element| element.name == item);
+                match groups.iter_mut().find(|(key, _)| *key == field) {
+                    Some((_, FieldValue::Items(_, elements))) => elements.extend(items),
+                    _ => groups.push((field, FieldValue::Items(item, items.collect()))),
+                }
+                continue;
+            }
+            match groups.iter_mut().find(|(key, _)| *key == child.name) {
+                Some((_, FieldValue::Elements(_, elements))) => elements.push(child),
+                _ => groups.push((&child.name, FieldValue::Elements(&child.name, vec![child]))),
+            }
+        }
+        entries.extend(
+            groups
+                .into_iter()
+                .map(|(key, value)| (Cow::Borrowed(key), value)),
+        );
+
+        let text = element_text(element);
+        if !text.trim().is_empty() {
+            entries.push((Cow::Borrowed("$value"), FieldValue::Text(text)));
+        }
+
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for ElementMap<'de> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(CowStrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value.take() {
+            Some(FieldValue::Text(text)) => seed.deserialize(TextDeserializer { text }),
+            Some(FieldValue::Elements(name, elements)) => seed.deserialize(ElementsDeserializer {
+                name,
+                elements,
+                unwrapped: false,
+            }),
+            Some(FieldValue::Items(name, elements)) => seed.deserialize(ElementsDeserializer {
+                name,
+                elements,
+                unwrapped: true,
+            }),
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+// The children named after a field: a sequence, or a single element for anything else
+struct ElementsDeserializer<'de> {
+    name: &'de str,
+    elements: Vec<&'de XmlElement>,
+    // Set for a "wrapper/item" field, whose items are never wrappers themselves
+    unwrapped: bool,
+}
+
+impl<'de> ElementsDeserializer<'de> {
+    fn single(self) -> Result<ElementDeserializer<'de>, DeserializeError> {
+        match self.elements.as_slice() {
+            [element] => Ok(ElementDeserializer { element }),
+            _ => Err(DeserializeError::RepeatedElement {
+                name: self.name.to_string(),
+                count: self.elements.len(),
+            }),
+        }
+    }
+}
+
+fn is_wrapper(element: &XmlElement) -> bool {
+    if !element.attributes.is_empty() || !element_text(element).trim().is_empty() {
+        return false;
+    }
+    let mut children = element.child_elements();
+    match children.next() {
+        Some(first) => first.name != element.name && children.all(|child| child.name == first.name),
+        None => false,
+    }
+}
+
+macro_rules! deserialize_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                de::Deserializer::$method(self.single()?, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ElementsDeserializer<'de> {
+    type Error = DeserializeError;
+
+    deserialize_single! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    // Unknown fields are skipped however many children share the name
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let elements = match self.elements.as_slice() {
+            [wrapper] if !self.unwrapped && is_wrapper(wrapper) => {
+                wrapper.child_elements().collect()
+            }
+            _ => self.elements,
+        };
+        visitor.visit_seq(ElementSeq {
+            elements: elements.into_iter(),
+        })
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_unit_struct(self.single()?, name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_struct(self.single()?, name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_enum(self.single()?, name, variants, visitor)
+    }
+}
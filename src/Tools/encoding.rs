@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    // ISO-8859-1, also used for US-ASCII since it is a subset
+    Latin1,
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub enum EncodingError {
+    UnsupportedEncoding { name: String },
+    // `offset` is the byte offset of the first undecodable sequence in the raw input
+    InvalidData { encoding: Encoding, offset: usize },
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::UnsupportedEncoding { name } => write!(
+                f,
+                "unsupported encoding \"{}\" (supported: UTF-8, UTF-16, ISO-8859-1)",
+                name
+            ),
+            EncodingError::InvalidData { encoding, offset } => {
+                write!(f, "byte {}: invalid {} data", offset, encoding)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+// Works out the encoding from a byte order mark, the byte pattern of "<?" in UTF-16, or the
+// encoding pseudo-attribute of the XML declaration, in that order. Also returns the length
+// of the BOM to skip.
+pub fn detect_encoding(bytes: &[u8]) -> Result<(Encoding, usize), EncodingError> {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Ok((Encoding::Utf8, 3));
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Ok((Encoding::Utf16Le, 2));
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Ok((Encoding::Utf16Be, 2));
+    }
+    if bytes.starts_with(&[b'<', 0, b'?', 0]) {
+        return Ok((Encoding::Utf16Le, 0));
+    }
+    if bytes.starts_with(&[0, b'<', 0, b'?']) {
+        return Ok((Encoding::Utf16Be, 0));
+    }
+
+    match declared_encoding(bytes) {
+        Some(name) => encoding_for_label(&name).map(|encoding| (encoding, 0)),
+        None => Ok((Encoding::Utf8, 0)),
+    }
+}
+
+// Decodes `bytes` to UTF-8 text, borrowing when the input already is UTF-8 without a BOM
+pub fn decode_bytes(bytes: &[u8]) -> Result<Cow<'_, str>, EncodingError> {
+    let (encoding, bom_length) = detect_encoding(bytes)?;
+    decode_with(&bytes[bom_length..], encoding).map_err(|error| match error {
+        EncodingError::InvalidData { encoding, offset } => EncodingError::InvalidData {
+            encoding,
+            offset: offset + bom_length,
+        },
+        other => other,
+    })
+}
+
+pub fn decode_with(bytes: &[u8], encoding: Encoding) -> Result<Cow<'_, str>, EncodingError> {
+    match encoding {
+        Encoding::Utf8 => std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|error| EncodingError::InvalidData {
+                encoding,
+                offset: error.valid_up_to(),
+            }),
+        Encoding::Latin1 => Ok(Cow::Owned(bytes.iter().map(|&b| b as char).collect())),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let invalid = |offset| EncodingError::InvalidData { encoding, offset };
+            let pairs = bytes.chunks_exact(2);
+            if !pairs.remainder().is_empty() {
+                return Err(invalid(bytes.len() - 1));
+            }
+
+            let units = pairs.map(|pair| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                _ => u16::from_be_bytes([pair[0], pair[1]]),
+            });
+
+            let mut decoded = String::with_capacity(bytes.len() / 2);
+            let mut offset = 0;
+            for ch in char::decode_utf16(units) {
+                let ch = ch.map_err(|_| invalid(offset))?;
+                offset += ch.len_utf16() * 2;
+                decoded.push(ch);
+            }
+            Ok(Cow::Owned(decoded))
+        }
+    }
+}
+
+// Reads the encoding pseudo-attribute of an XML declaration in an ASCII-compatible encoding,
+// going through the declaration's name="value" pairs in order
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    const WHITESPACE: [char; 4] = [' ', '\t', '\r', '\n'];
+
+    // "<?xml" followed by whitespace, so <?xml-stylesheet ...?> is not a declaration
+    let rest = bytes.strip_prefix(b"<?xml")?;
+    if !rest.first().is_some_and(u8::is_ascii_whitespace) {
+        return None;
+    }
+    let end = memchr::memmem::find(rest, b"?>")?;
+    let mut rest = std::str::from_utf8(&rest[..end]).ok()?;
+
+    loop {
+        rest = rest.trim_start_matches(WHITESPACE);
+        let (name, after_name) = rest.split_once('=')?;
+        let value = after_name.trim_start_matches(WHITESPACE);
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let (value, after_value) = value[1..].split_once(quote)?;
+        if name.trim_end_matches(WHITESPACE) == "encoding" {
+            return Some(value.to_string());
+        }
+        rest = after_value;
+    }
+}
+
+fn encoding_for_label(label: &str) -> Result<Encoding, EncodingError> {
+    match label.to_ascii_lowercase().as_str() {
+        // A UTF-16 declaration on ASCII-compatible bytes was saved without transcoding
+        "utf-8" | "utf8" | "utf-16" | "utf-16le" | "utf-16be" => Ok(Encoding::Utf8),
+        "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "latin-1" | "l1" | "us-ascii"
+        | "ascii" => Ok(Encoding::Latin1),
+        _ => Err(EncodingError::UnsupportedEncoding {
+            name: label.to_string(),
+        }),
+    }
+}
@@ -0,0 +1,492 @@
+use crate::models::DocumentType::DocumentType;
+use crate::models::EditError::EditError;
+use crate::models::NamespaceScope::NamespaceScope;
+use crate::models::XmlDeclaration::XmlDeclaration;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::{check_chars, validate_node, XmlElement};
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::lexical_analysis::is_valid_name;
+use crate::Tools::parse_tokens::resolve_namespace;
+use serde_json::{Map, Value};
+
+// Two JSON shapes for a document:
+//
+// Lossless keeps every node in order, so JSON -> XML -> JSON gives back the same document:
+//   {"declaration": {"version": "1.0", "encoding": "UTF-8"},
+//    "root": {"type": "element", "name": "cost", "attributes": {"name": "pts", "value": "5"},
+//             "children": [{"type": "text", "value": "..."}, {"type": "comment", "value": "..."}]}}
+// plus "doctype", "prolog" and "epilog" when present. Other node types are "cdata" (value) and
+// "processingInstruction" (target, data). Source formatting and spans are not kept.
+//
+// BadgerFish is compact and lossy: {"cost": {"@name": "pts", "@value": "5"}}. Attributes become
+// "@key", children become properties named after them (an array when the name repeats), and text
+// becomes "$". An element with only text becomes a plain string. Comments, processing
+// instructions, whitespace-only text and the order between differently named children are lost.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonMode {
+    #[default]
+    Lossless,
+    BadgerFish,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonError {
+    // The value at `path` (a JSON pointer) has the wrong shape
+    UnexpectedValue {
+        path: String,
+        expected: &'static str,
+    },
+    InvalidName {
+        path: String,
+        name: String,
+    },
+    // Text, an attribute value, a comment, CDATA or a processing instruction that could not be
+    // written as well-formed XML, e.g. a comment containing "--"
+    InvalidContent {
+        path: String,
+        error: EditError,
+    },
+    // The text given to document_from_json_str is not JSON at all
+    Syntax(String),
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::UnexpectedValue { path, expected } => {
+                write!(f, "{}: expected {}", pointer(path), expected)
+            }
+            JsonError::InvalidName { path, name } => {
+                write!(f, "{}: \"{}\" is not a valid XML name", pointer(path), name)
+            }
+            JsonError::InvalidContent { path, error } => {
+                write!(f, "{}: {}", pointer(path), error)
+            }
+            JsonError::Syntax(message) => write!(f, "invalid JSON: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+fn pointer(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+pub fn document_to_json(document: &XmlDocument, mode: JsonMode) -> Value {
+    match mode {
+        JsonMode::Lossless => lossless_document(document),
+        JsonMode::BadgerFish => badgerfish_document(document),
+    }
+}
+
+pub fn document_from_json(value: &Value, mode: JsonMode) -> Result<XmlDocument, JsonError> {
+    let mut document = match mode {
+        JsonMode::Lossless => read_lossless_document(value)?,
+        JsonMode::BadgerFish => read_badgerfish_document(value)?,
+    };
+    if let Some(root) = document.root.as_mut() {
+        resolve_namespaces(root, None);
+    }
+    Ok(document)
+}
+
+pub fn document_to_json_string(document: &XmlDocument, mode: JsonMode) -> String {
+    document_to_json(document, mode).to_string()
+}
+
+pub fn document_from_json_str(json: &str, mode: JsonMode) -> Result<XmlDocument, JsonError> {
+    let value = serde_json::from_str(json).map_err(|error| JsonError::Syntax(error.to_string()))?;
+    document_from_json(&value, mode)
+}
+
+// Same resolution the parser does, top down
+fn resolve_namespaces(element: &mut XmlElement, parent: Option<&NamespaceScope>) {
+    resolve_namespace(element, parent);
+    let scope = element.namespaces.clone();
+    for child in &mut element.children {
+        if let XmlNode::Element(child) = child {
+            resolve_namespaces(child, Some(&scope));
+        }
+    }
+}
+
+fn unexpected(path: &str, expected: &'static str) -> JsonError {
+    JsonError::UnexpectedValue {
+        path: path.to_string(),
+        expected,
+    }
+}
+
+fn checked_name(name: &str, path: &str) -> Result<String, JsonError> {
+    if is_valid_name(name) {
+        Ok(name.to_string())
+    } else {
+        Err(JsonError::InvalidName {
+            path: path.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+// The same checks the editing API makes, so the writer can only produce well-formed XML
+fn checked_node(node: XmlNode, path: &str) -> Result<XmlNode, JsonError> {
+    validate_node(&node).map_err(|error| invalid_content(path, error))?;
+    Ok(node)
+}
+
+fn checked_text(text: String, path: &str) -> Result<String, JsonError> {
+    check_chars(&text).map_err(|error| invalid_content(path, error))?;
+    Ok(text)
+}
+
+fn invalid_content(path: &str, error: EditError) -> JsonError {
+    JsonError::InvalidContent {
+        path: path.to_string(),
+        error,
+    }
+}
+
+// JSON pointer escaping for object keys
+fn key_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+// ============================================================================
+// LOSSLESS
+// ============================================================================
+
+fn lossless_document(document: &XmlDocument) -> Value {
+    let mut object = Map::new();
+    if let Some(declaration) = &document.declaration {
+        let mut value = Map::new();
+        value.insert("version".into(), declaration.version.clone().into());
+        if let Some(encoding) = &declaration.encoding {
+            value.insert("encoding".into(), encoding.clone().into());
+        }
+        if let Some(standalone) = declaration.standalone {
+            value.insert("standalone".into(), standalone.into());
+        }
+        object.insert("declaration".into(), value.into());
+    }
+    if let Some(doctype) = &document.doctype {
+        let mut value = Map::new();
+        value.insert("name".into(), doctype.name.clone().into());
+        if let Some(external_id) = &doctype.external_id {
+            value.insert("externalId".into(), external_id.clone().into());
+        }
+        if let Some(internal_subset) = &doctype.internal_subset {
+            value.insert("internalSubset".into(), internal_subset.clone().into());
+        }
+        object.insert("doctype".into(), value.into());
+    }
+    if !document.prolog.is_empty() {
+        object.insert("prolog".into(), lossless_nodes(&document.prolog));
+    }
+    if let Some(root) = &document.root {
+        object.insert("root".into(), lossless_element(root));
+    }
+    if !document.epilog.is_empty() {
+        object.insert("epilog".into(), lossless_nodes(&document.epilog));
+    }
+    object.into()
+}
+
+fn lossless_nodes(nodes: &[XmlNode]) -> Value {
+    nodes.iter().map(lossless_node).collect::<Vec<_>>().into()
+}
+
+fn lossless_node(node: &XmlNode) -> Value {
+    let (kind, value) = match node {
+        XmlNode::Element(child) => return lossless_element(child),
+        XmlNode::Text(text) => ("text", text),
+        XmlNode::Comment(text) => ("comment", text),
+        XmlNode::CData(text) => ("cdata", text),
+        XmlNode::ProcessingInstruction { target, data } => {
+            let mut object = Map::new();
+            object.insert("type".into(), "processingInstruction".into());
+            object.insert("target".into(), target.clone().into());
+            object.insert("data".into(), data.clone().into());
+            return object.into();
+        }
+    };
+    let mut object = Map::new();
+    object.insert("type".into(), kind.into());
+    object.insert("value".into(), value.clone().into());
+    object.into()
+}
+
+fn lossless_element(element: &XmlElement) -> Value {
+    let attributes: Map<String, Value> = element
+        .attributes
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone().into()))
+        .collect();
+
+    let mut object = Map::new();
+    object.insert("type".into(), "element".into());
+    object.insert("name".into(), element.name.clone().into());
+    object.insert("attributes".into(), attributes.into());
+    object.insert("children".into(), lossless_nodes(&element.children));
+    object.into()
+}
+
+fn read_lossless_document(value: &Value) -> Result<XmlDocument, JsonError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| unexpected("", "an object"))?;
+    let mut document = XmlDocument::default();
+
+    if let Some(declaration) = object.get("declaration") {
+        let path = "/declaration";
+        let fields = declaration
+            .as_object()
+            .ok_or_else(|| unexpected(path, "an object"))?;
+        document.declaration = Some(XmlDeclaration {
+            version: optional_string(fields, "version", path)?
+                .ok_or_else(|| unexpected("/declaration/version", "a string"))?,
+            encoding: optional_string(fields, "encoding", path)?,
+            standalone: match fields.get("standalone") {
+                None | Some(Value::Null) => None,
+                Some(Value::Bool(standalone)) => Some(*standalone),
+                Some(_) => return Err(unexpected("/declaration/standalone", "a boolean")),
+            },
+        });
+    }
+    if let Some(doctype) = object.get("doctype") {
+        let path = "/doctype";
+        let fields = doctype
+            .as_object()
+            .ok_or_else(|| unexpected(path, "an object"))?;
+        document.doctype = Some(DocumentType {
+            name: optional_string(fields, "name", path)?
+                .ok_or_else(|| unexpected("/doctype/name", "a string"))?,
+            external_id: optional_string(fields, "externalId", path)?,
+            internal_subset: optional_string(fields, "internalSubset", path)?,
+        });
+    }
+    if let Some(prolog) = object.get("prolog") {
+        document.prolog = read_lossless_nodes(prolog, "/prolog")?;
+    }
+    if let Some(root) = object.get("root") {
+        match read_lossless_node(root, "/root")? {
+            XmlNode::Element(root) => document.root = Some(root),
+            _ => return Err(unexpected("/root", "an element")),
+        }
+    }
+    if let Some(epilog) = object.get("epilog") {
+        document.epilog = read_lossless_nodes(epilog, "/epilog")?;
+    }
+    Ok(document)
+}
+
+// An optional string field; null counts as missing
+fn optional_string(
+    fields: &Map<String, Value>,
+    key: &str,
+    path: &str,
+) -> Result<Option<String>, JsonError> {
+    match fields.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(unexpected(&key_path(path, key), "a string")),
+    }
+}
+
+fn read_lossless_nodes(value: &Value, path: &str) -> Result<Vec<XmlNode>, JsonError> {
+    value
+        .as_array()
+        .ok_or_else(|| unexpected(path, "an array of nodes"))?
+        .iter()
+        .enumerate()
+        .map(|(index, node)| read_lossless_node(node, &format!("{}/{}", path, index)))
+        .collect()
+}
+
+fn read_lossless_node(value: &Value, path: &str) -> Result<XmlNode, JsonError> {
+    let fields = value
+        .as_object()
+        .ok_or_else(|| unexpected(path, "a node object"))?;
+    let required = |key: &str| {
+        optional_string(fields, key, path)?
+            .ok_or_else(|| unexpected(&key_path(path, key), "a string"))
+    };
+
+    let kind = required("type")?;
+    Ok(match kind.as_str() {
+        "element" => {
+            let name_path = key_path(path, "name");
+            let mut element = XmlElement::new(checked_name(&required("name")?, &name_path)?);
+            if let Some(attributes) = fields.get("attributes") {
+                let attributes_path = key_path(path, "attributes");
+                let attributes = attributes
+                    .as_object()
+                    .ok_or_else(|| unexpected(&attributes_path, "an object"))?;
+                for (key, value) in attributes {
+                    let value_path = key_path(&attributes_path, key);
+                    let value = value
+                        .as_str()
+                        .ok_or_else(|| unexpected(&value_path, "a string"))?;
+                    let name = checked_name(key, &value_path)?;
+                    let value = checked_text(value.to_string(), &value_path)?;
+                    element.attributes.insert(name, value);
+                }
+            }
+            if let Some(children) = fields.get("children") {
+                element.children = read_lossless_nodes(children, &key_path(path, "children"))?;
+            }
+            XmlNode::Element(element)
+        }
+        "text" => checked_node(XmlNode::Text(required("value")?), path)?,
+        "comment" => checked_node(XmlNode::Comment(required("value")?), path)?,
+        "cdata" => checked_node(XmlNode::CData(required("value")?), path)?,
+        "processingInstruction" => {
+            let target = checked_name(&required("target")?, &key_path(path, "target"))?;
+            let data = optional_string(fields, "data", path)?.unwrap_or_default();
+            checked_node(XmlNode::ProcessingInstruction { target, data }, path)?
+        }
+        _ => {
+            return Err(unexpected(
+                &key_path(path, "type"),
+                "element, text, comment, cdata or processingInstruction",
+            ))
+        }
+    })
+}
+
+// ============================================================================
+// BADGERFISH
+// ============================================================================
+
+fn badgerfish_document(document: &XmlDocument) -> Value {
+    let mut object = Map::new();
+    if let Some(root) = &document.root {
+        object.insert(root.name.clone(), badgerfish_element(root));
+    }
+    object.into()
+}
+
+fn badgerfish_element(element: &XmlElement) -> Value {
+    let text = own_text(element);
+    let mut children = element.child_elements().peekable();
+    if element.attributes.is_empty() && children.peek().is_none() {
+        return match text {
+            Some(text) => text.into(),
+            None => Map::new().into(),
+        };
+    }
+
+    let mut object = Map::new();
+    for (key, value) in element.attributes.iter() {
+        object.insert(format!("@{}", key), value.clone().into());
+    }
+    for child in children {
+        let value = badgerfish_element(child);
+        match object.get_mut(&child.name) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = vec![first, value].into();
+            }
+            None => {
+                object.insert(child.name.clone(), value);
+            }
+        }
+    }
+    if let Some(text) = text {
+        object.insert("$".into(), text.into());
+    }
+    object.into()
+}
+
+// The element's own text, unless it is only whitespace
+fn own_text(element: &XmlElement) -> Option<String> {
+    let text = element.get_text_content();
+    (!text.trim().is_empty()).then_some(text)
+}
+
+fn read_badgerfish_document(value: &Value) -> Result<XmlDocument, JsonError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| unexpected("", "an object"))?;
+    let mut entries = object.iter();
+    let (Some((name, root)), None) = (entries.next(), entries.next()) else {
+        return Err(unexpected(
+            "",
+            "an object with the root element as its only key",
+        ));
+    };
+    let path = key_path("", name);
+    Ok(XmlDocument {
+        root: Some(read_badgerfish_element(
+            checked_name(name, &path)?,
+            root,
+            &path,
+        )?),
+        ..Default::default()
+    })
+}
+
+fn read_badgerfish_element(
+    name: String,
+    value: &Value,
+    path: &str,
+) -> Result<XmlElement, JsonError> {
+    let mut element = XmlElement::new(name);
+    let fields = match value {
+        Value::Object(fields) => fields,
+        Value::Null => return Ok(element),
+        scalar => {
+            let text = checked_text(scalar_text(scalar, path)?, path)?;
+            element.children.push(XmlNode::Text(text));
+            return Ok(element);
+        }
+    };
+
+    let mut text = None;
+    for (key, value) in fields {
+        let value_path = key_path(path, key);
+        if key == "$" {
+            text = Some(checked_text(scalar_text(value, &value_path)?, &value_path)?);
+        } else if let Some(attribute) = key.strip_prefix('@') {
+            let name = checked_name(attribute, &value_path)?;
+            let value = checked_text(scalar_text(value, &value_path)?, &value_path)?;
+            element.attributes.insert(name, value);
+        } else {
+            let name = checked_name(key, &value_path)?;
+            match value {
+                Value::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        let item_path = format!("{}/{}", value_path, index);
+                        let child = read_badgerfish_element(name.clone(), item, &item_path)?;
+                        element.children.push(XmlNode::Element(child));
+                    }
+                }
+                _ => {
+                    let child = read_badgerfish_element(name, value, &value_path)?;
+                    element.children.push(XmlNode::Element(child));
+                }
+            }
+        }
+    }
+    // Mixed content keeps a single run of text, placed before the children
+    if let Some(text) = text {
+        element.children.insert(0, XmlNode::Text(text));
+    }
+    Ok(element)
+}
+
+// Numbers and booleans are accepted as their JSON spelling
+fn scalar_text(value: &Value, path: &str) -> Result<String, JsonError> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        _ => Err(unexpected(path, "a string, number or boolean")),
+    }
+}
@@ -1,319 +1,893 @@
+use crate::models::Attribute::Quote;
+use crate::models::DocumentType::DocumentType;
+use crate::models::Position::Position;
+use crate::models::Span::Span;
+use crate::models::XmlDeclaration::XmlDeclaration;
+use memchr::{memchr, memchr_iter, memmem, memrchr};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+// Names, text and attribute values borrow from the input unless entity decoding or
+// whitespace collapsing had to build a new string
 #[derive(Debug, Clone, PartialEq)]
-pub enum Token {
-    OpenTag(String),
-    CloseTag(String),
-    SelfClosingTag(String),
-    XmlDeclaration,
-    Attribute(String, String),
-    Text(String),
-    Comment(String),
+pub enum Token<'a> {
+    OpenTag(Cow<'a, str>),
+    CloseTag(Cow<'a, str>),
+    SelfClosingTag(Cow<'a, str>),
+    XmlDeclaration(XmlDeclaration),
+    ProcessingInstruction {
+        target: Cow<'a, str>,
+        data: Cow<'a, str>,
+    },
+    Doctype(DocumentType),
+    Attribute(Cow<'a, str>, Cow<'a, str>, Quote),
+    Text(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+    CData(Cow<'a, str>),
     EndOfFile,
 }
 
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        fn owned(value: Cow<'_, str>) -> Cow<'static, str> {
+            Cow::Owned(value.into_owned())
+        }
+
+        match self {
+            Token::OpenTag(name) => Token::OpenTag(owned(name)),
+            Token::CloseTag(name) => Token::CloseTag(owned(name)),
+            Token::SelfClosingTag(name) => Token::SelfClosingTag(owned(name)),
+            Token::XmlDeclaration(declaration) => Token::XmlDeclaration(declaration),
+            Token::ProcessingInstruction { target, data } => Token::ProcessingInstruction {
+                target: owned(target),
+                data: owned(data),
+            },
+            Token::Doctype(doctype) => Token::Doctype(doctype),
+            Token::Attribute(name, value, quote) => {
+                Token::Attribute(owned(name), owned(value), quote)
+            }
+            Token::Text(text) => Token::Text(owned(text)),
+            Token::Comment(comment) => Token::Comment(owned(comment)),
+            Token::CData(content) => Token::CData(owned(content)),
+            Token::EndOfFile => Token::EndOfFile,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
+
+impl SpannedToken<'_> {
+    pub fn into_owned(self) -> SpannedToken<'static> {
+        SpannedToken {
+            token: self.token.into_owned(),
+            span: self.span,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum TokenizeError {
-    UnexpectedEndOfInput,
-    MalformedTag,
-    MalformedAttribute,
+    UnexpectedEndOfInput {
+        position: Position,
+    },
+    MalformedTag {
+        position: Position,
+    },
+    MalformedAttribute {
+        position: Position,
+    },
+    MalformedDeclaration {
+        position: Position,
+    },
+    InvalidReference {
+        reference: String,
+        position: Position,
+    },
+    // Strict mode only
+    InvalidName {
+        name: String,
+        position: Position,
+    },
+    DuplicateAttribute {
+        name: String,
+        position: Position,
+    },
+    MissingAttributeWhitespace {
+        position: Position,
+    },
+    LessThanInAttributeValue {
+        position: Position,
+    },
+    DoubleHyphenInComment {
+        position: Position,
+    },
+    IllegalCharacter {
+        character: char,
+        position: Position,
+    },
 }
 
-pub fn tokenize(xml_string: &str) -> Result<Vec<Token>, TokenizeError> {
-    let mut tokens = Vec::new();
-    let mut chars = xml_string.chars().peekable();
+impl TokenizeError {
+    pub fn position(&self) -> Position {
+        match self {
+            TokenizeError::UnexpectedEndOfInput { position }
+            | TokenizeError::MalformedTag { position }
+            | TokenizeError::MalformedAttribute { position }
+            | TokenizeError::MalformedDeclaration { position }
+            | TokenizeError::InvalidReference { position, .. }
+            | TokenizeError::InvalidName { position, .. }
+            | TokenizeError::DuplicateAttribute { position, .. }
+            | TokenizeError::MissingAttributeWhitespace { position }
+            | TokenizeError::LessThanInAttributeValue { position }
+            | TokenizeError::DoubleHyphenInComment { position }
+            | TokenizeError::IllegalCharacter { position, .. } => *position,
+        }
+    }
+}
 
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '<' => {
-                let tag_tokens = parse_tag_with_attributes(&mut chars)?;
-                tokens.extend(tag_tokens);
+impl std::fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizeError::UnexpectedEndOfInput { position } => {
+                write!(f, "{}: unexpected end of input", position)
             }
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next(); // Skip whitespace
+            TokenizeError::MalformedTag { position } => write!(f, "{}: malformed tag", position),
+            TokenizeError::MalformedAttribute { position } => {
+                write!(f, "{}: malformed attribute", position)
             }
-            _ => {
-                let token = parse_text(&mut chars)?;
-                if !token.is_empty() {
-                    tokens.push(Token::Text(token));
-                }
+            TokenizeError::MalformedDeclaration { position } => {
+                write!(f, "{}: malformed XML declaration", position)
+            }
+            TokenizeError::InvalidReference {
+                reference,
+                position,
+            } => write!(f, "{}: invalid reference {}", position, reference),
+            TokenizeError::InvalidName { name, position } => {
+                write!(f, "{}: invalid name \"{}\"", position, name)
+            }
+            TokenizeError::DuplicateAttribute { name, position } => {
+                write!(f, "{}: duplicate attribute {}", position, name)
+            }
+            TokenizeError::MissingAttributeWhitespace { position } => {
+                write!(f, "{}: missing whitespace before attribute", position)
+            }
+            TokenizeError::LessThanInAttributeValue { position } => {
+                write!(f, "{}: '<' in attribute value", position)
             }
+            TokenizeError::DoubleHyphenInComment { position } => {
+                write!(f, "{}: '--' in comment", position)
+            }
+            TokenizeError::IllegalCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "{}: illegal character U+{:04X}",
+                position, *character as u32
+            ),
         }
     }
+}
 
-    tokens.push(Token::EndOfFile);
-    Ok(tokens)
+impl std::error::Error for TokenizeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespaceMode {
+    // Trim text and drop whitespace-only text between tags
+    #[default]
+    Trim,
+    // Trim text and collapse inner whitespace runs to a single space
+    Collapse,
+    // Keep every text run, including whitespace between tags, exactly as written
+    Preserve,
 }
 
-fn parse_tag(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, TokenizeError> {
-    chars.next(); // Consume '<'
+#[derive(Debug, Clone, Default)]
+pub struct TokenizerOptions {
+    pub whitespace: WhitespaceMode,
+    // Enforce the XML 1.0 well-formedness rules that the default lenient mode lets through:
+    // the Name production, unique attributes separated by whitespace, no '<' in attribute
+    // values, no "--" in comments and no characters outside the Char production
+    pub strict: bool,
+}
 
-    match chars.peek() {
-        Some('/') => parse_close_tag(chars),
-        Some('!') => parse_comment(chars),
-        Some('?') => parse_xml_declaration(chars),
-        Some(_) => parse_open_tag(chars),
-        None => Err(TokenizeError::UnexpectedEndOfInput),
+impl TokenizerOptions {
+    pub fn preserve_whitespace() -> Self {
+        Self {
+            whitespace: WhitespaceMode::Preserve,
+            ..Self::default()
+        }
+    }
+
+    pub fn collapse_whitespace() -> Self {
+        Self {
+            whitespace: WhitespaceMode::Collapse,
+            ..Self::default()
+        }
+    }
+
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            ..Self::default()
+        }
     }
 }
 
-fn parse_tag_with_attributes(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Vec<Token>, TokenizeError> {
-    chars.next(); // Consume '<'
+pub fn tokenize(xml_string: &str) -> Result<Vec<SpannedToken<'_>>, TokenizeError> {
+    tokenize_with(xml_string, &TokenizerOptions::default())
+}
 
-    match chars.peek() {
-        Some('/') => {
-            let token = parse_close_tag(chars)?;
-            Ok(vec![token])
+pub fn tokenize_with<'a>(
+    xml_string: &'a str,
+    options: &TokenizerOptions,
+) -> Result<Vec<SpannedToken<'a>>, TokenizeError> {
+    Lexer::new(xml_string, options).collect()
+}
+
+// Lexer over the raw bytes of the input, producing tokens on demand. An open tag's
+// attributes come out before the tag itself, and EndOfFile is always the last token.
+pub struct Lexer<'a> {
+    input: &'a str,
+    offset: usize,
+    position: Position,
+    whitespace: WhitespaceMode,
+    strict: bool,
+    // Whether each open element is under xml:space="preserve"
+    preserve_stack: Vec<bool>,
+    pending: VecDeque<SpannedToken<'a>>,
+    finished: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str, options: &TokenizerOptions) -> Self {
+        Self::resume(input, options, Position::start(), Vec::new())
+    }
+
+    // Continues lexing a document whose earlier part ended at `position` with the given
+    // xml:space stack, for callers that feed the input in pieces
+    pub(crate) fn resume(
+        input: &'a str,
+        options: &TokenizerOptions,
+        position: Position,
+        preserve_stack: Vec<bool>,
+    ) -> Self {
+        Self {
+            input,
+            offset: 0,
+            position,
+            whitespace: options.whitespace,
+            strict: options.strict,
+            preserve_stack,
+            pending: VecDeque::new(),
+            finished: false,
         }
-        Some('!') => {
-            let token = parse_comment(chars)?;
-            Ok(vec![token])
+    }
+
+    pub(crate) fn into_preserve_stack(self) -> Vec<bool> {
+        self.preserve_stack
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    fn bytes(&self) -> &'a [u8] {
+        self.input.as_bytes()
+    }
+
+    fn position_at(&self, offset: usize) -> Position {
+        advance_position(self.position, &self.bytes()[self.offset..offset])
+    }
+
+    // Every consumed byte passes through here, so strict mode checks for illegal characters
+    // in one place
+    fn advance_to(&mut self, offset: usize) -> Result<(), TokenizeError> {
+        if self.strict {
+            let consumed = &self.input[self.offset..offset];
+            if let Some((index, character)) =
+                consumed.char_indices().find(|&(_, ch)| !is_xml_char(ch))
+            {
+                return Err(TokenizeError::IllegalCharacter {
+                    character,
+                    position: self.position_at(self.offset + index),
+                });
+            }
         }
-        Some('?') => {
-            let token = parse_xml_declaration(chars)?;
-            Ok(vec![token])
+        self.position = self.position_at(offset);
+        self.offset = offset;
+        Ok(())
+    }
+
+    fn check_name(&self, name: &str, offset: usize) -> Result<(), TokenizeError> {
+        if self.strict && !is_valid_name(name) {
+            return Err(TokenizeError::InvalidName {
+                name: name.to_string(),
+                position: self.position_at(offset),
+            });
         }
-        Some(_) => parse_open_tag_with_attributes(chars),
-        None => Err(TokenizeError::UnexpectedEndOfInput),
+        Ok(())
     }
-}
 
-fn parse_open_tag(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Token, TokenizeError> {
-    let mut tag_name = String::new();
-
-    // Parse tag name
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next();
-                break;
-            }
-            '>' => {
-                chars.next();
-                return Ok(Token::OpenTag(tag_name));
+    fn end_of_input(&self) -> TokenizeError {
+        TokenizeError::UnexpectedEndOfInput {
+            position: self.position_at(self.input.len()),
+        }
+    }
+
+    fn preserving(&self) -> bool {
+        self.whitespace == WhitespaceMode::Preserve
+            || self.preserve_stack.last().copied().unwrap_or(false)
+    }
+
+    fn skip_whitespace_from(&self, mut offset: usize) -> usize {
+        let bytes = self.bytes();
+        while offset < bytes.len() && is_whitespace_byte(bytes[offset]) {
+            offset += 1;
+        }
+        offset
+    }
+
+    fn push(&mut self, token: Token<'a>, start: Position) {
+        self.pending.push_back(SpannedToken {
+            token,
+            span: Span::new(start, self.position),
+        });
+    }
+
+    fn next_token(&mut self) -> Result<Option<SpannedToken<'a>>, TokenizeError> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                return Ok(Some(token));
             }
-            '/' => {
-                chars.next();
-                if chars.next() == Some('>') {
-                    return Ok(Token::SelfClosingTag(tag_name));
+            if self.offset >= self.input.len() {
+                if self.finished {
+                    return Ok(None);
                 }
-                return Err(TokenizeError::MalformedTag);
+                self.finished = true;
+                return Ok(Some(SpannedToken {
+                    token: Token::EndOfFile,
+                    span: Span::new(self.position, self.position),
+                }));
             }
-            _ => {
-                tag_name.push(ch);
-                chars.next();
+
+            if self.bytes()[self.offset] == b'<' {
+                self.lex_markup()?;
+            } else if let Some(text) = self.lex_text()? {
+                return Ok(Some(text));
             }
         }
     }
 
-    // Parse attributes
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '>' => {
-                chars.next();
-                return Ok(Token::OpenTag(tag_name));
-            }
-            '/' => {
-                chars.next();
-                if chars.next() == Some('>') {
-                    return Ok(Token::SelfClosingTag(tag_name));
+    fn lex_markup(&mut self) -> Result<(), TokenizeError> {
+        match self.bytes().get(self.offset + 1) {
+            Some(b'/') => self.lex_close_tag(),
+            Some(b'!') => self.lex_markup_declaration(),
+            Some(b'?') => self.lex_processing_instruction(),
+            Some(_) => self.lex_open_tag(),
+            None => Err(self.end_of_input()),
+        }
+    }
+
+    fn lex_open_tag(&mut self) -> Result<(), TokenizeError> {
+        let start = self.position;
+        let bytes = self.bytes();
+        let name_start = self.offset + 1;
+        let mut cursor = name_start;
+        while cursor < bytes.len() && !is_name_terminator(bytes[cursor]) {
+            cursor += 1;
+        }
+        if cursor == name_start {
+            return Err(TokenizeError::MalformedTag {
+                position: self.position_at(name_start),
+            });
+        }
+        let name = Cow::Borrowed(&self.input[name_start..cursor]);
+        self.check_name(&name, name_start)?;
+        let mut xml_space = None;
+        let mut attribute_names = Vec::new();
+
+        loop {
+            let before_whitespace = cursor;
+            cursor = self.skip_whitespace_from(cursor);
+            match bytes.get(cursor) {
+                None => return Err(self.end_of_input()),
+                Some(b'>') => {
+                    self.advance_to(cursor + 1)?;
+                    let inherited = self.preserve_stack.last().copied().unwrap_or(false);
+                    self.preserve_stack.push(xml_space.unwrap_or(inherited));
+                    self.push(Token::OpenTag(name), start);
+                    return Ok(());
+                }
+                Some(b'/') => {
+                    if bytes.get(cursor + 1) != Some(&b'>') {
+                        return Err(TokenizeError::MalformedTag {
+                            position: self.position_at(cursor),
+                        });
+                    }
+                    self.advance_to(cursor + 2)?;
+                    self.push(Token::SelfClosingTag(name), start);
+                    return Ok(());
+                }
+                Some(_) => {
+                    // The tag name always ends at whitespace here, so this only catches
+                    // attributes that directly follow the previous value's closing quote
+                    if self.strict && cursor == before_whitespace {
+                        return Err(TokenizeError::MissingAttributeWhitespace {
+                            position: self.position_at(cursor),
+                        });
+                    }
+                    self.advance_to(cursor)?;
+                    let attribute_start = self.position;
+                    let (attribute_name, value) = self.lex_attribute()?;
+                    if self.strict {
+                        if attribute_names.contains(&attribute_name) {
+                            return Err(TokenizeError::DuplicateAttribute {
+                                name: attribute_name.to_string(),
+                                position: attribute_start,
+                            });
+                        }
+                        attribute_names.push(attribute_name);
+                    }
+                    if attribute_name == "xml:space" {
+                        xml_space = match value {
+                            "preserve" => Some(true),
+                            "default" => Some(false),
+                            _ => None,
+                        };
+                    }
+                    cursor = self.offset;
                 }
-                return Err(TokenizeError::MalformedTag);
-            }
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next();
-            }
-            _ => {
-                // Parse attribute but don't store it here - it will be handled by the main tokenizer
-                parse_attribute(chars)?;
             }
         }
     }
 
-    Err(TokenizeError::UnexpectedEndOfInput)
-}
+    // Lexes name="value" at the current offset and queues the Attribute token, returning
+    // the name and the raw value
+    fn lex_attribute(&mut self) -> Result<(&'a str, &'a str), TokenizeError> {
+        let start = self.position;
+        let bytes = self.bytes();
+        let name_start = self.offset;
+        let mut cursor = name_start;
+        while cursor < bytes.len() && !is_name_terminator(bytes[cursor]) && bytes[cursor] != b'=' {
+            cursor += 1;
+        }
+        let name = &self.input[name_start..cursor];
+        if !name.is_empty() {
+            self.check_name(name, name_start)?;
+        }
 
-fn parse_open_tag_with_attributes(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Vec<Token>, TokenizeError> {
-    let mut tokens = Vec::new();
-    let mut tag_name = String::new();
-
-    // Parse tag name
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next();
-                break;
+        cursor = self.skip_whitespace_from(cursor);
+        match bytes.get(cursor) {
+            Some(b'=') if !name.is_empty() => {}
+            None => return Err(self.end_of_input()),
+            Some(_) => {
+                return Err(TokenizeError::MalformedAttribute {
+                    position: self.position_at(cursor),
+                })
             }
-            '>' => {
-                chars.next();
-                tokens.push(Token::OpenTag(tag_name));
-                return Ok(tokens);
-            }
-            '/' => {
-                chars.next();
-                if chars.next() == Some('>') {
-                    tokens.push(Token::SelfClosingTag(tag_name));
-                    return Ok(tokens);
-                }
-                return Err(TokenizeError::MalformedTag);
+        }
+
+        cursor = self.skip_whitespace_from(cursor + 1);
+        let quote = match bytes.get(cursor) {
+            Some(&quote @ (b'"' | b'\'')) => quote,
+            None => return Err(self.end_of_input()),
+            Some(_) => {
+                return Err(TokenizeError::MalformedAttribute {
+                    position: self.position_at(cursor),
+                })
             }
-            _ => {
-                tag_name.push(ch);
-                chars.next();
+        };
+
+        let value_start = cursor + 1;
+        let value_end = match memchr(quote, &bytes[value_start..]) {
+            Some(length) => value_start + length,
+            None => return Err(self.end_of_input()),
+        };
+        let raw_value = &self.input[value_start..value_end];
+        if self.strict {
+            if let Some(index) = memchr(b'<', raw_value.as_bytes()) {
+                return Err(TokenizeError::LessThanInAttributeValue {
+                    position: self.position_at(value_start + index),
+                });
             }
         }
+
+        let value = decode_references_at(raw_value, self.position_at(value_start))?;
+        self.advance_to(value_end + 1)?;
+        let quote = if quote == b'"' {
+            Quote::Double
+        } else {
+            Quote::Single
+        };
+        self.push(Token::Attribute(Cow::Borrowed(name), value, quote), start);
+        Ok((name, raw_value))
     }
 
-    // Parse attributes
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '>' => {
-                chars.next();
-                tokens.push(Token::OpenTag(tag_name));
-                return Ok(tokens);
-            }
-            '/' => {
-                chars.next();
-                if chars.next() == Some('>') {
-                    tokens.push(Token::SelfClosingTag(tag_name));
-                    return Ok(tokens);
+    fn lex_close_tag(&mut self) -> Result<(), TokenizeError> {
+        let start = self.position;
+        let name_start = self.offset + 2;
+        let end = match memchr(b'>', &self.bytes()[name_start..]) {
+            Some(length) => name_start + length,
+            None => return Err(self.end_of_input()),
+        };
+
+        let name = self.input[name_start..end].trim_end_matches(is_xml_whitespace);
+        self.check_name(name, name_start)?;
+        self.advance_to(end + 1)?;
+        self.preserve_stack.pop();
+        self.push(Token::CloseTag(Cow::Borrowed(name)), start);
+        Ok(())
+    }
+
+    fn lex_markup_declaration(&mut self) -> Result<(), TokenizeError> {
+        let rest = &self.bytes()[self.offset..];
+        if rest.starts_with(b"<!--") {
+            let preserve = self.preserving();
+            self.lex_delimited(4, b"-->", |lexer, content_start, content| {
+                if lexer.strict {
+                    let hyphens = memmem::find(content.as_bytes(), b"--")
+                        .or_else(|| content.ends_with('-').then(|| content.len() - 1));
+                    if let Some(index) = hyphens {
+                        return Err(TokenizeError::DoubleHyphenInComment {
+                            position: lexer.position_at(content_start + index),
+                        });
+                    }
                 }
-                return Err(TokenizeError::MalformedTag);
+                let comment = if preserve { content } else { content.trim() };
+                Ok(Token::Comment(Cow::Borrowed(comment)))
+            })
+        } else if rest.starts_with(b"<![CDATA[") {
+            // CDATA content is taken verbatim up to the first ]]>
+            self.lex_delimited(9, b"]]>", |_, _, content| {
+                Ok(Token::CData(Cow::Borrowed(content)))
+            })
+        } else if rest.starts_with(b"<!DOCTYPE") {
+            self.lex_doctype()
+        } else if [&b"<!--"[..], b"<![CDATA[", b"<!DOCTYPE"]
+            .iter()
+            .any(|literal| literal.starts_with(rest))
+        {
+            Err(self.end_of_input())
+        } else {
+            Err(TokenizeError::MalformedTag {
+                position: self.position_at(self.offset + 1),
+            })
+        }
+    }
+
+    // Lexes markup whose content starts `prefix_length` bytes in and runs up to `terminator`
+    fn lex_delimited<F>(
+        &mut self,
+        prefix_length: usize,
+        terminator: &[u8],
+        make_token: F,
+    ) -> Result<(), TokenizeError>
+    where
+        F: FnOnce(&Self, usize, &'a str) -> Result<Token<'a>, TokenizeError>,
+    {
+        let start = self.position;
+        let content_start = self.offset + prefix_length;
+        let content_end = match memmem::find(&self.bytes()[content_start..], terminator) {
+            Some(length) => content_start + length,
+            None => return Err(self.end_of_input()),
+        };
+
+        let token = make_token(self, content_start, &self.input[content_start..content_end])?;
+        self.advance_to(content_end + terminator.len())?;
+        self.push(token, start);
+        Ok(())
+    }
+
+    fn lex_processing_instruction(&mut self) -> Result<(), TokenizeError> {
+        let question = self.position_at(self.offset + 1);
+        self.lex_delimited(2, b"?>", |lexer, content_start, content| {
+            let (target, data) = match content.find(is_xml_whitespace) {
+                Some(index) => (&content[..index], content[index..].trim_start()),
+                None => (content, ""),
+            };
+
+            if target.is_empty() {
+                return Err(TokenizeError::MalformedTag { position: question });
             }
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next();
+            if target == "xml" {
+                return parse_xml_declaration(data, question).map(Token::XmlDeclaration);
             }
-            _ => {
-                // Parse attribute and add it to tokens
-                let attr_token = parse_attribute(chars)?;
-                tokens.push(attr_token);
+            lexer.check_name(target, content_start)?;
+
+            Ok(Token::ProcessingInstruction {
+                target: Cow::Borrowed(target),
+                data: Cow::Borrowed(data),
+            })
+        })
+    }
+
+    fn lex_doctype(&mut self) -> Result<(), TokenizeError> {
+        let start = self.position;
+        let bytes = self.bytes();
+        let content_start = self.offset + "<!DOCTYPE".len();
+        let mut quote: Option<u8> = None;
+        let mut in_subset = false;
+
+        for (index, &byte) in bytes[content_start..].iter().enumerate() {
+            match (quote, byte) {
+                (Some(open), _) if byte == open => quote = None,
+                (Some(_), _) => {}
+                (None, b'"' | b'\'') => quote = Some(byte),
+                (None, b'[') if !in_subset => in_subset = true,
+                (None, b']') if in_subset => in_subset = false,
+                (None, b'>') if !in_subset => {
+                    let content_end = content_start + index;
+                    let bang = self.position_at(self.offset + 1);
+                    let token = split_doctype(&self.input[content_start..content_end], bang)?;
+                    self.advance_to(content_end + 1)?;
+                    self.push(token, start);
+                    return Ok(());
+                }
+                _ => {}
             }
         }
+
+        Err(self.end_of_input())
     }
 
-    Err(TokenizeError::UnexpectedEndOfInput)
-}
+    fn lex_text(&mut self) -> Result<Option<SpannedToken<'a>>, TokenizeError> {
+        let start = self.position;
+        let text_start = self.offset;
+        let text_end = memchr(b'<', &self.bytes()[text_start..])
+            .map(|length| text_start + length)
+            .unwrap_or(self.input.len());
+        let text = &self.input[text_start..text_end];
+        let preserve = self.preserving();
+        self.advance_to(text_end)?;
+
+        if preserve {
+            return Ok(Some(SpannedToken {
+                token: Token::Text(decode_references_at(text, start)?),
+                span: Span::new(start, self.position),
+            }));
+        }
+
+        let trimmed = text.trim_matches(is_xml_whitespace);
+        if trimmed.is_empty() {
+            return Ok(None);
+        }
 
-fn parse_close_tag(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Token, TokenizeError> {
-    chars.next(); // Consume '/'
-    let mut tag_name = String::new();
-
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '>' => {
-                chars.next();
-                return Ok(Token::CloseTag(tag_name));
+        let leading = text.len() - text.trim_start_matches(is_xml_whitespace).len();
+        let trimmed_start = advance_position(start, &text.as_bytes()[..leading]);
+        let span = Span::new(
+            trimmed_start,
+            advance_position(trimmed_start, trimmed.as_bytes()),
+        );
+        let decoded = match self.whitespace {
+            WhitespaceMode::Collapse => {
+                let collapsed = trimmed
+                    .split(is_xml_whitespace)
+                    .filter(|word| !word.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Cow::Owned(decode_references_at(&collapsed, trimmed_start)?.into_owned())
             }
-            _ => {
-                tag_name.push(ch);
-                chars.next();
+            _ => decode_references_at(trimmed, trimmed_start)?,
+        };
+
+        Ok(Some(SpannedToken {
+            token: Token::Text(decoded),
+            span,
+        }))
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken<'a>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token() {
+            Ok(token) => token.map(Ok),
+            Err(error) => {
+                // Nothing more is produced after an error
+                self.offset = self.input.len();
+                self.pending.clear();
+                self.finished = true;
+                Some(Err(error))
             }
         }
     }
+}
 
-    Err(TokenizeError::UnexpectedEndOfInput)
+// Moves `position` past `bytes`, counting lines and UTF-8 characters
+fn advance_position(mut position: Position, bytes: &[u8]) -> Position {
+    position.offset += bytes.len();
+    match memrchr(b'\n', bytes) {
+        Some(last_newline) => {
+            position.line += memchr_iter(b'\n', bytes).count();
+            position.column = 1 + count_chars(&bytes[last_newline + 1..]);
+        }
+        None => position.column += count_chars(bytes),
+    }
+    position
 }
 
-fn parse_comment(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, TokenizeError> {
-    chars.next(); // Consume '!'
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| (byte & 0xC0) != 0x80).count()
+}
 
-    // Check for <!--
-    if chars.next() != Some('-') || chars.next() != Some('-') {
-        return Err(TokenizeError::MalformedTag);
-    }
+fn is_whitespace_byte(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
 
-    let mut comment = String::new();
-    let mut prev_chars = [' ', ' '];
+fn is_name_terminator(byte: u8) -> bool {
+    is_whitespace_byte(byte) || byte == b'>' || byte == b'/'
+}
 
-    while let Some(ch) = chars.next() {
-        comment.push(ch);
-        prev_chars[0] = prev_chars[1];
-        prev_chars[1] = ch;
+fn is_xml_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\n' | '\r')
+}
 
-        if prev_chars == ['-', '-'] {
-            if chars.next() == Some('>') {
-                comment.pop(); // Remove last '-'
-                comment.pop(); // Remove second to last '-'
-                return Ok(Token::Comment(comment.trim().to_string()));
-            }
-        }
+// The Char production of XML 1.0; surrogates cannot occur in a str
+pub(crate) fn is_xml_char(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+// The Name production of XML 1.0
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if is_name_start_char(first) => chars.all(is_name_char),
+        _ => false,
     }
+}
 
-    Err(TokenizeError::UnexpectedEndOfInput)
+fn is_name_start_char(ch: char) -> bool {
+    matches!(ch,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}'
+        | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}'
+        | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
 }
 
-fn parse_xml_declaration(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Token, TokenizeError> {
-    chars.next(); // Consume '?'
+fn is_name_char(ch: char) -> bool {
+    is_name_start_char(ch)
+        || matches!(ch,
+            '-' | '.' | '0'..='9' | '\u{B7}'
+            | '\u{300}'..='\u{36F}'
+            | '\u{203F}'..='\u{2040}')
+}
 
-    // Skip until we find ?>
-    while let Some(ch) = chars.next() {
-        if ch == '?' {
-            if chars.next() == Some('>') {
-                return Ok(Token::XmlDeclaration);
+// Reads the version/encoding/standalone pseudo-attributes of an XML declaration
+fn parse_xml_declaration(data: &str, start: Position) -> Result<XmlDeclaration, TokenizeError> {
+    let malformed = || TokenizeError::MalformedDeclaration { position: start };
+    let mut version = None;
+    let mut encoding = None;
+    let mut standalone = None;
+    let mut rest = data.trim();
+
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=').ok_or_else(malformed)?;
+        let after_name = after_name.trim_start();
+        let quote = after_name.chars().next().filter(|&c| c == '"' || c == '\'');
+        let quote = quote.ok_or_else(malformed)?;
+        let (value, after_value) = after_name[1..].split_once(quote).ok_or_else(malformed)?;
+
+        match name.trim() {
+            "version" if version.is_none() => version = Some(value.to_string()),
+            "encoding" if encoding.is_none() => encoding = Some(value.to_string()),
+            "standalone" if standalone.is_none() => {
+                standalone = match value {
+                    "yes" => Some(true),
+                    "no" => Some(false),
+                    _ => return Err(malformed()),
+                }
             }
+            _ => return Err(malformed()),
         }
+
+        rest = after_value.trim_start();
     }
 
-    Err(TokenizeError::UnexpectedEndOfInput)
+    Ok(XmlDeclaration {
+        version: version.ok_or_else(malformed)?,
+        encoding,
+        standalone,
+    })
 }
 
-fn parse_attribute(
-    chars: &mut std::iter::Peekable<std::str::Chars>,
-) -> Result<Token, TokenizeError> {
-    let mut name = String::new();
-    let mut value = String::new();
-
-    // Parse attribute name
-    while let Some(&ch) = chars.peek() {
-        match ch {
-            '=' => {
-                chars.next();
-                break;
-            }
-            ' ' | '\t' | '\n' | '\r' => {
-                chars.next();
-            }
-            _ => {
-                name.push(ch);
-                chars.next();
-            }
-        }
+fn split_doctype(content: &str, bang: Position) -> Result<Token<'static>, TokenizeError> {
+    let content = content.trim();
+    let name_end = content
+        .find(|c: char| is_xml_whitespace(c) || c == '[')
+        .unwrap_or(content.len());
+    let name = &content[..name_end];
+    if name.is_empty() {
+        return Err(TokenizeError::MalformedTag { position: bang });
     }
 
-    // Parse attribute value
-    let quote_char = chars.next().ok_or(TokenizeError::UnexpectedEndOfInput)?;
-    if quote_char != '"' && quote_char != '\'' {
-        return Err(TokenizeError::MalformedAttribute);
+    let rest = &content[name_end..];
+    let (external_id, internal_subset) = match (rest.find('['), rest.rfind(']')) {
+        (Some(open), Some(close)) if open < close => {
+            (&rest[..open], Some(rest[open + 1..close].to_string()))
+        }
+        _ => (rest, None),
+    };
+    let external_id = external_id.trim();
+
+    Ok(Token::Doctype(DocumentType {
+        name: name.to_string(),
+        external_id: (!external_id.is_empty()).then(|| external_id.to_string()),
+        internal_subset,
+    }))
+}
+
+pub fn decode_references(raw: &str) -> Result<Cow<'_, str>, TokenizeError> {
+    decode_references_at(raw, Position::start())
+}
+
+// Decodes references in `raw`, reporting errors relative to where `raw` starts in the input
+fn decode_references_at(raw: &str, start: Position) -> Result<Cow<'_, str>, TokenizeError> {
+    if !raw.contains('&') {
+        return Ok(Cow::Borrowed(raw));
     }
 
-    while let Some(ch) = chars.next() {
-        if ch == quote_char {
-            return Ok(Token::Attribute(name, value));
+    let mut decoded = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        let reference = &rest[amp..];
+        let invalid = |text: &str| TokenizeError::InvalidReference {
+            reference: text.to_string(),
+            position: start.advanced_by(&raw[..raw.len() - reference.len()]),
+        };
+
+        // A reference runs up to the next ';' and may not contain whitespace or another '&'
+        let end = match reference[1..].find(|c: char| c == ';' || c == '&' || c.is_whitespace()) {
+            Some(offset) if reference[1 + offset..].starts_with(';') => 1 + offset,
+            Some(offset) => return Err(invalid(&reference[..1 + offset])),
+            None => return Err(invalid(reference)),
+        };
+
+        match resolve_reference(&reference[1..end]) {
+            Some(ch) => decoded.push(ch),
+            None => return Err(invalid(&reference[..=end])),
         }
-        value.push(ch);
+
+        rest = &reference[end + 1..];
     }
 
-    Err(TokenizeError::UnexpectedEndOfInput)
+    decoded.push_str(rest);
+    Ok(Cow::Owned(decoded))
 }
 
-fn parse_text(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, TokenizeError> {
-    let mut text = String::new();
-
-    while let Some(&ch) = chars.peek() {
-        if ch == '<' {
-            break;
+fn resolve_reference(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => {
+            let digits = name.strip_prefix('#')?;
+            let code_point = match digits.strip_prefix('x') {
+                Some(hex) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                    u32::from_str_radix(hex, 16).ok()?
+                }
+                None if digits.bytes().all(|b| b.is_ascii_digit()) => digits.parse().ok()?,
+                _ => return None,
+            };
+            char::from_u32(code_point).filter(|&ch| ch != '\0')
         }
-        text.push(ch);
-        chars.next();
     }
-
-    Ok(text.trim().to_string())
 }
@@ -0,0 +1,495 @@
+use crate::models::DocumentKind::DocumentKind;
+use crate::models::Position::Position;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::Tools::schema::child_paths;
+use std::collections::HashMap;
+
+// Structural rules for BattleScribe catalogues, game systems and rosters, written out here so
+// no XSD has to ship with the crate. Each document kind knows its element names, the attributes
+// every element needs and the values its enumerated attributes may take. Lint issues carry the
+// element path and position, plus a suggested fix when a near miss (by edit distance) is found.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    // An element name that does not belong in this kind of document
+    UnknownElement,
+    MissingAttribute,
+    // An enumerated or boolean attribute with a value outside its set
+    InvalidValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    // Element names from the root, with [n] on elements that have same-named siblings
+    pub path: String,
+    pub position: Option<Position>,
+    pub message: String,
+    // How to fix it, e.g. `rename it to <constraint>`
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{}: ", position)?;
+        }
+        write!(f, "{}: {}", self.path, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintReport {
+    // The kind the rules were chosen for; Unknown when the root is not a BattleScribe root
+    pub document_kind: DocumentKind,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn issues_of(&self, kind: LintKind) -> impl Iterator<Item = &LintIssue> {
+        self.issues.iter().filter(move |issue| issue.kind == kind)
+    }
+}
+
+impl std::fmt::Display for LintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+struct ElementRule {
+    name: &'static str,
+    required: &'static [&'static str],
+    values: &'static [(&'static str, &'static [&'static str])],
+}
+
+const fn rule(
+    name: &'static str,
+    required: &'static [&'static str],
+    values: &'static [(&'static str, &'static [&'static str])],
+) -> ElementRule {
+    ElementRule {
+        name,
+        required,
+        values,
+    }
+}
+
+// Wrapper elements such as <constraints>, which hold children and nothing else
+const fn container(name: &'static str) -> ElementRule {
+    rule(name, &[], &[])
+}
+
+const ENTRY_TYPES: &[&str] = &["upgrade", "model", "unit"];
+
+const MODIFIER_TYPES: &[&str] = &[
+    "set",
+    "increment",
+    "decrement",
+    "append",
+    "add",
+    "remove",
+    "set-primary",
+    "unset-primary",
+];
+
+const CONDITION_TYPES: &[&str] = &[
+    "lessThan",
+    "greaterThan",
+    "equalTo",
+    "notEqualTo",
+    "atLeast",
+    "atMost",
+    "instanceOf",
+    "notInstanceOf",
+];
+
+// The spellings ConstraintType accepts
+const CONSTRAINT_TYPES: &[&str] = &["min", "max", "equal", "notEqual", "atLeast", "atMost"];
+
+const BOOLEANS: &[&str] = &["true", "false"];
+
+// Checked on whichever element they appear
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "hidden",
+    "collective",
+    "collapsible",
+    "import",
+    "shared",
+    "primary",
+    "percentValue",
+    "includeChildSelections",
+    "includeChildForces",
+    "roundUp",
+    "library",
+    "importRootEntries",
+];
+
+// Found in every kind of document
+const COMMON_RULES: &[ElementRule] = &[
+    container("profiles"),
+    rule("profile", &["id", "name", "typeId"], &[]),
+    container("characteristics"),
+    rule("characteristic", &["name", "typeId"], &[]),
+    container("rules"),
+    rule("rule", &["id", "name"], &[]),
+    rule("description", &[], &[]),
+    container("costs"),
+    rule("cost", &["name", "typeId", "value"], &[]),
+    container("publications"),
+    rule("publication", &["id", "name"], &[]),
+    container("attributes"),
+    rule("attribute", &["name"], &[]),
+];
+
+// Catalogues and game systems
+const DATA_RULES: &[ElementRule] = &[
+    rule("comment", &[], &[]),
+    rule("readme", &[], &[]),
+    container("profileTypes"),
+    rule("profileType", &["id", "name"], &[]),
+    container("characteristicTypes"),
+    rule("characteristicType", &["id", "name"], &[]),
+    container("categoryEntries"),
+    rule("categoryEntry", &["id", "name"], &[]),
+    container("selectionEntries"),
+    container("sharedSelectionEntries"),
+    rule(
+        "selectionEntry",
+        &["id", "name", "type"],
+        &[("type", ENTRY_TYPES)],
+    ),
+    container("selectionEntryGroups"),
+    container("sharedSelectionEntryGroups"),
+    rule("selectionEntryGroup", &["id", "name"], &[]),
+    container("entryLinks"),
+    rule(
+        "entryLink",
+        &["id", "name", "targetId", "type"],
+        &[("type", &["selectionEntry", "selectionEntryGroup"])],
+    ),
+    container("sharedRules"),
+    container("sharedProfiles"),
+    container("infoLinks"),
+    rule(
+        "infoLink",
+        &["id", "name", "targetId", "type"],
+        &[("type", &["profile", "rule", "infoGroup"])],
+    ),
+    container("infoGroups"),
+    container("sharedInfoGroups"),
+    rule("infoGroup", &["id", "name"], &[]),
+    container("categoryLinks"),
+    rule("categoryLink", &["id", "targetId"], &[]),
+    container("constraints"),
+    rule(
+        "constraint",
+        &["id", "type", "value", "field"],
+        &[("type", CONSTRAINT_TYPES)],
+    ),
+    container("modifiers"),
+    rule(
+        "modifier",
+        &["type", "field", "value"],
+        &[("type", MODIFIER_TYPES)],
+    ),
+    container("modifierGroups"),
+    rule("modifierGroup", &[], &[("type", &["and", "or"])]),
+    container("conditions"),
+    rule(
+        "condition",
+        &["type", "field", "scope", "value", "childId"],
+        &[("type", CONDITION_TYPES)],
+    ),
+    container("conditionGroups"),
+    rule("conditionGroup", &["type"], &[("type", &["and", "or"])]),
+    container("repeats"),
+    rule(
+        "repeat",
+        &["field", "scope", "value", "repeats", "childId"],
+        &[],
+    ),
+    container("associations"),
+    rule("association", &["id", "name"], &[]),
+];
+
+const CATALOGUE_RULES: &[ElementRule] = &[
+    rule(
+        "catalogue",
+        &["id", "name", "revision", "gameSystemId"],
+        &[("type", &["catalogue"])],
+    ),
+    container("catalogueLinks"),
+    rule(
+        "catalogueLink",
+        &["id", "targetId", "type"],
+        &[("type", &["catalogue"])],
+    ),
+];
+
+const GAME_SYSTEM_RULES: &[ElementRule] = &[
+    rule(
+        "gameSystem",
+        &["id", "name", "revision"],
+        &[("type", &["gameSystem"])],
+    ),
+    container("costTypes"),
+    rule("costType", &["id", "name"], &[]),
+    container("forceEntries"),
+    rule("forceEntry", &["id", "name"], &[]),
+];
+
+const ROSTER_RULES: &[ElementRule] = &[
+    rule("roster", &["id", "name", "gameSystemId"], &[]),
+    container("costLimits"),
+    rule("costLimit", &["name", "typeId", "value"], &[]),
+    container("forces"),
+    rule("force", &["id", "name", "entryId", "catalogueId"], &[]),
+    container("selections"),
+    rule(
+        "selection",
+        &["id", "name", "entryId", "number"],
+        &[("type", ENTRY_TYPES)],
+    ),
+    container("categories"),
+    rule("category", &["id", "name", "entryId"], &[]),
+    rule("customNotes", &[], &[]),
+];
+
+fn rules_for(kind: DocumentKind) -> HashMap<&'static str, &'static ElementRule> {
+    let specific: &[&[ElementRule]] = match kind {
+        DocumentKind::Catalogue => &[DATA_RULES, CATALOGUE_RULES],
+        DocumentKind::GameSystem => &[DATA_RULES, GAME_SYSTEM_RULES],
+        DocumentKind::Roster => &[ROSTER_RULES],
+        DocumentKind::Unknown => &[],
+    };
+    std::iter::once(COMMON_RULES)
+        .chain(specific.iter().copied())
+        .flatten()
+        .map(|rule| (rule.name, rule))
+        .collect()
+}
+
+// The root name for each kind, used when a document has no BattleScribe namespace
+fn kind_of_root(name: &str) -> DocumentKind {
+    match name {
+        "catalogue" => DocumentKind::Catalogue,
+        "gameSystem" => DocumentKind::GameSystem,
+        "roster" => DocumentKind::Roster,
+        _ => DocumentKind::Unknown,
+    }
+}
+
+// ============================================================================
+// LINTING
+// ============================================================================
+
+// Lints against the rules for the document's kind, taken from its namespace or else its root name
+pub fn lint_document(document: &XmlDocument) -> LintReport {
+    let Some(root) = document.get_root_element() else {
+        return LintReport {
+            document_kind: DocumentKind::Unknown,
+            issues: Vec::new(),
+        };
+    };
+
+    let mut kind = document.document_kind();
+    if kind == DocumentKind::Unknown {
+        kind = kind_of_root(root.local_name());
+    }
+    if kind == DocumentKind::Unknown {
+        let suggestion = closest(root.local_name(), &["catalogue", "gameSystem", "roster"])
+            .map(|name| format!("rename it to <{}>", name));
+        return LintReport {
+            document_kind: kind,
+            issues: vec![LintIssue {
+                kind: LintKind::UnknownElement,
+                path: root.name.clone(),
+                position: position(root),
+                message: format!("<{}> is not a catalogue, game system or roster", root.name),
+                suggestion,
+            }],
+        };
+    }
+
+    LintReport {
+        document_kind: kind,
+        issues: lint_element(root, kind),
+    }
+}
+
+// Lints `element` and everything below it against the rules for `kind`
+pub fn lint_element(element: &XmlElement, kind: DocumentKind) -> Vec<LintIssue> {
+    let mut linter = Linter {
+        kind,
+        rules: rules_for(kind),
+        issues: Vec::new(),
+    };
+    linter.lint(element, &element.name);
+    linter.issues
+}
+
+fn position(element: &XmlElement) -> Option<Position> {
+    element.span.map(|span| span.start)
+}
+
+struct Linter {
+    kind: DocumentKind,
+    rules: HashMap<&'static str, &'static ElementRule>,
+    issues: Vec<LintIssue>,
+}
+
+impl Linter {
+    fn report(
+        &mut self,
+        kind: LintKind,
+        element: &XmlElement,
+        path: &str,
+        message: String,
+        suggestion: Option<String>,
+    ) {
+        self.issues.push(LintIssue {
+            kind,
+            path: path.to_string(),
+            position: position(element),
+            message,
+            suggestion,
+        });
+    }
+
+    fn lint(&mut self, element: &XmlElement, path: &str) {
+        // Prefixed elements belong to some other vocabulary
+        if element.prefix().is_some() {
+            return;
+        }
+
+        match self.rules.get(element.local_name()).copied() {
+            Some(rule) => self.check_attributes(rule, element, path),
+            None => {
+                let known: Vec<&str> = self.rules.keys().copied().collect();
+                let suggestion = closest(element.local_name(), &known)
+                    .map(|name| format!("rename it to <{}>", name));
+                let message = format!("<{}> does not belong in a {}", element.name, self.kind);
+                self.report(LintKind::UnknownElement, element, path, message, suggestion);
+            }
+        }
+        self.check_booleans(element, path);
+
+        // Children of an unknown element are still checked, since a misspelt wrapper usually
+        // holds well-formed entries
+        for (child_path, child) in child_paths(element, path) {
+            self.lint(child, &child_path);
+        }
+    }
+
+    fn check_attributes(&mut self, rule: &ElementRule, element: &XmlElement, path: &str) {
+        for &attribute in rule.required {
+            if element.get_attribute(attribute).is_some() {
+                continue;
+            }
+            // A misspelt attribute is more likely than a missing one
+            let present: Vec<&str> = element
+                .attributes
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .filter(|name| rule.required.iter().all(|required| required != name))
+                .collect();
+            let suggestion = match present
+                .into_iter()
+                .find(|name| closest(name, &[attribute]).is_some())
+            {
+                Some(misspelt) => format!("rename \"{}\" to \"{}\"", misspelt, attribute),
+                None => format!("add {}=\"...\"", attribute),
+            };
+            self.report(
+                LintKind::MissingAttribute,
+                element,
+                path,
+                format!(
+                    "<{}> is missing required attribute \"{}\"",
+                    element.name, attribute
+                ),
+                Some(suggestion),
+            );
+        }
+
+        for &(attribute, allowed) in rule.values {
+            self.check_value(element, path, attribute, allowed);
+        }
+    }
+
+    fn check_booleans(&mut self, element: &XmlElement, path: &str) {
+        for &attribute in BOOLEAN_ATTRIBUTES {
+            self.check_value(element, path, attribute, BOOLEANS);
+        }
+    }
+
+    fn check_value(&mut self, element: &XmlElement, path: &str, attribute: &str, allowed: &[&str]) {
+        let Some(value) = element.get_attribute(attribute) else {
+            return;
+        };
+        if allowed.contains(&value.as_str()) {
+            return;
+        }
+        let suggestion = closest(value, allowed).map(|fix| format!("change it to \"{}\"", fix));
+        self.report(
+            LintKind::InvalidValue,
+            element,
+            path,
+            format!(
+                "attribute \"{}\" is \"{}\", expected one of {}",
+                attribute,
+                value,
+                allowed.join(", ")
+            ),
+            suggestion,
+        );
+    }
+}
+
+// ============================================================================
+// SUGGESTIONS
+// ============================================================================
+
+// The candidate nearest to `value`, if it is close enough to be a plausible typo
+fn closest<'a>(value: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let limit = (value.chars().count() / 3).max(1);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(value, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, candidate)| (*distance, *candidate))
+        .map(|(_, candidate)| candidate)
+}
+
+// Levenshtein distance, ignoring case so "True" suggests "true"
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, left) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, right) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left != right);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,266 @@
+use crate::models::XmlElement::XmlElement;
+
+// Typed conversions from elements. #[derive(FromXmlElement)] from xml-parser-derive implements
+// FromXmlElement for a struct with calls to the helpers below, one per field:
+// - #[xml(attr = "typeId")] reads an attribute; fields without #[xml(attr/child/text)] read the
+//   attribute named after the field in camelCase
+// - #[xml(child = "characteristics/characteristic")] converts the first element at that path of
+//   child names, and with `many` every one of them into a Vec
+// - #[xml(text)] reads the element's text
+// - #[xml(with = "path")] parses an attribute or text with `fn(&str) -> Result<T, &'static str>`
+//   in place of FromXmlValue
+// - Option fields may be missing; #[xml(default)] or #[xml(default = "path")] fills in a value
+//   instead, and anything else missing is an error naming what was missing and where
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FromXmlError {
+    MissingAttribute {
+        path: String,
+        attribute: String,
+    },
+    MissingChild {
+        path: String,
+        child: String,
+    },
+    MissingText {
+        path: String,
+    },
+    InvalidAttribute {
+        path: String,
+        attribute: String,
+        value: String,
+        expected: &'static str,
+    },
+    InvalidText {
+        path: String,
+        value: String,
+        expected: &'static str,
+    },
+}
+
+impl FromXmlError {
+    // Element names from the converted element down to the one at fault, e.g.
+    // "profile/characteristics/characteristic[2]"
+    pub fn path(&self) -> &str {
+        match self {
+            FromXmlError::MissingAttribute { path, .. }
+            | FromXmlError::MissingChild { path, .. }
+            | FromXmlError::MissingText { path }
+            | FromXmlError::InvalidAttribute { path, .. }
+            | FromXmlError::InvalidText { path, .. } => path,
+        }
+    }
+
+    fn path_mut(&mut self) -> &mut String {
+        match self {
+            FromXmlError::MissingAttribute { path, .. }
+            | FromXmlError::MissingChild { path, .. }
+            | FromXmlError::MissingText { path }
+            | FromXmlError::InvalidAttribute { path, .. }
+            | FromXmlError::InvalidText { path, .. } => path,
+        }
+    }
+
+    // A child's error starts its path at the child's own name; swap that for the full path to it
+    fn relocate(mut self, path_to_child: &str) -> Self {
+        let path = self.path_mut();
+        *path = match path.split_once('/') {
+            Some((_, rest)) => format!("{}/{}", path_to_child, rest),
+            None => path_to_child.to_string(),
+        };
+        self
+    }
+}
+
+impl std::fmt::Display for FromXmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FromXmlError::MissingAttribute { path, attribute } => {
+                write!(f, "{}: missing attribute \"{}\"", path, attribute)
+            }
+            FromXmlError::MissingChild { path, child } => {
+                write!(f, "{}: missing child element <{}>", path, child)
+            }
+            FromXmlError::MissingText { path } => write!(f, "{}: missing text", path),
+            FromXmlError::InvalidAttribute {
+                path,
+                attribute,
+                value,
+                expected,
+            } => write!(
+                f,
+                "{}: invalid value \"{}\" for attribute \"{}\", expected {}",
+                path, value, attribute, expected
+            ),
+            FromXmlError::InvalidText {
+                path,
+                value,
+                expected,
+            } => write!(
+                f,
+                "{}: invalid text \"{}\", expected {}",
+                path, value, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FromXmlError {}
+
+pub trait FromXmlElement: Sized {
+    fn from_xml_element(element: &XmlElement) -> Result<Self, FromXmlError>;
+}
+
+// An attribute value or text. The error is what was expected instead, e.g. "an integer"
+pub trait FromXmlValue: Sized {
+    fn from_xml_value(value: &str) -> Result<Self, &'static str>;
+}
+
+// Text-only children, e.g. #[xml(child = "keyword", many)] keywords: Vec<String>
+impl<T: FromXmlValue> FromXmlElement for T {
+    fn from_xml_element(element: &XmlElement) -> Result<Self, FromXmlError> {
+        text(element)?.ok_or_else(|| FromXmlError::MissingText {
+            path: element.name.clone(),
+        })
+    }
+}
+
+impl FromXmlValue for String {
+    fn from_xml_value(value: &str) -> Result<Self, &'static str> {
+        Ok(value.to_string())
+    }
+}
+
+impl FromXmlValue for bool {
+    fn from_xml_value(value: &str) -> Result<Self, &'static str> {
+        match value.trim() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err("a boolean"),
+        }
+    }
+}
+
+macro_rules! from_xml_value_parsed {
+    ($($type:ty => $expected:literal),*) => {
+        $(
+            impl FromXmlValue for $type {
+                fn from_xml_value(value: &str) -> Result<Self, &'static str> {
+                    value.trim().parse().map_err(|_| $expected)
+                }
+            }
+        )*
+    };
+}
+
+from_xml_value_parsed! {
+    i8 => "an integer", i16 => "an integer", i32 => "an integer", i64 => "an integer",
+    i128 => "an integer", isize => "an integer",
+    u8 => "an unsigned integer", u16 => "an unsigned integer", u32 => "an unsigned integer",
+    u64 => "an unsigned integer", u128 => "an unsigned integer", usize => "an unsigned integer",
+    f32 => "a number", f64 => "a number", char => "a single character"
+}
+
+// ============================================================================
+// FIELD HELPERS
+// ============================================================================
+
+// None when the attribute is absent
+pub fn attribute<T: FromXmlValue>(
+    element: &XmlElement,
+    name: &str,
+) -> Result<Option<T>, FromXmlError> {
+    attribute_with(element, name, T::from_xml_value)
+}
+
+pub fn attribute_with<T>(
+    element: &XmlElement,
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, &'static str>,
+) -> Result<Option<T>, FromXmlError> {
+    let Some(value) = element.get_attribute(name) else {
+        return Ok(None);
+    };
+    parse(value)
+        .map(Some)
+        .map_err(|expected| FromXmlError::InvalidAttribute {
+            path: element.name.clone(),
+            attribute: name.to_string(),
+            value: value.clone(),
+            expected,
+        })
+}
+
+// None when the element has no text besides whitespace
+pub fn text<T: FromXmlValue>(element: &XmlElement) -> Result<Option<T>, FromXmlError> {
+    text_with(element, T::from_xml_value)
+}
+
+pub fn text_with<T>(
+    element: &XmlElement,
+    parse: impl FnOnce(&str) -> Result<T, &'static str>,
+) -> Result<Option<T>, FromXmlError> {
+    let value = element.get_text_content();
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    parse(&value)
+        .map(Some)
+        .map_err(|expected| FromXmlError::InvalidText {
+            path: element.name.clone(),
+            value,
+            expected,
+        })
+}
+
+// The first element at `path`, or None if there is none
+pub fn child<T: FromXmlElement>(
+    element: &XmlElement,
+    path: &str,
+) -> Result<Option<T>, FromXmlError> {
+    match elements_at(element, path).into_iter().next() {
+        Some((location, found)) => T::from_xml_element(found)
+            .map(Some)
+            .map_err(|error| error.relocate(&location)),
+        None => Ok(None),
+    }
+}
+
+// Every element at `path`, in document order
+pub fn children<T: FromXmlElement>(
+    element: &XmlElement,
+    path: &str,
+) -> Result<Vec<T>, FromXmlError> {
+    elements_at(element, path)
+        .into_iter()
+        .map(|(location, found)| {
+            T::from_xml_element(found).map_err(|error| error.relocate(&location))
+        })
+        .collect()
+}
+
+// Follows a slash-separated path of child names, pairing each element found with its location
+// for error messages. Elements with same-named siblings get their 1-based position, as in XPath
+fn elements_at<'a>(element: &'a XmlElement, path: &str) -> Vec<(String, &'a XmlElement)> {
+    let mut found = vec![(element.name.clone(), element)];
+    for step in path.split('/').filter(|step| !step.is_empty()) {
+        let mut next = Vec::new();
+        for (location, parent) in found {
+            let named: Vec<&XmlElement> = parent
+                .child_elements()
+                .filter(|child| child.name == step)
+                .collect();
+            let count = named.len();
+            for (index, child) in named.into_iter().enumerate() {
+                let location = if count > 1 {
+                    format!("{}/{}[{}]", location, step, index + 1)
+                } else {
+                    format!("{}/{}", location, step)
+                };
+                next.push((location, child));
+            }
+        }
+        found = next;
+    }
+    found
+}
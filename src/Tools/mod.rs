@@ -1,4 +1,19 @@
 pub mod decompression;
+#[cfg(feature = "serde")]
+pub mod deserializer;
+pub mod encoding;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod lexical_analysis;
+pub mod lint;
+pub mod mapping;
 pub mod parse_tokens;
+pub mod parser;
+pub mod reader;
+pub mod schema;
+pub mod selector;
+#[cfg(feature = "serde")]
+pub mod serializer;
 pub mod validator;
+pub mod writer;
+pub mod xpath;
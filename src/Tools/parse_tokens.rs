@@ -1,112 +1,523 @@
+use crate::models::Attribute::{Attribute, Attributes, Quote};
+use crate::models::Diagnostic::{Diagnostic, DiagnosticKind};
+use crate::models::Formatting::{AttributeFormat, DocumentFormat, ElementFormat, RawNode};
+use crate::models::NamespaceScope::NamespaceScope;
+use crate::models::Position::Position;
+use crate::models::Span::Span;
+use crate::models::XmlArena::{NodeId, XmlArena};
 use crate::models::XmlDocument::XmlDocument;
 use crate::models::XmlElement::XmlElement;
 use crate::models::XmlNode::XmlNode;
-use crate::Tools::lexical_analysis::Token;
-use std::collections::HashMap;
+use crate::Tools::lexical_analysis::{SpannedToken, Token};
+use crate::Tools::writer::escape;
 
 #[derive(Debug)]
 pub enum ParseError {
-    MismatchedTags,
-    IncompleteDocument,
-    UnexpectedToken(Token),
-    EmptyStack,
+    MismatchedTags {
+        expected: String,
+        found: String,
+        position: Position,
+    },
+    MultipleRootElements {
+        found: String,
+        position: Position,
+    },
+    IncompleteDocument {
+        expected: String,
+        position: Position,
+    },
+    UnexpectedToken {
+        token: Token<'static>,
+        position: Position,
+    },
+    EmptyStack {
+        found: String,
+        position: Position,
+    },
 }
 
-pub fn parse_tokens(tokens: Vec<Token>) -> Result<XmlDocument, ParseError> {
-    let mut token_iter = tokens.into_iter().peekable();
-    let mut stack = Vec::new();
-    let mut current_attributes = HashMap::new();
-    let mut root_element: Option<XmlElement> = None;
-
-    while let Some(token) = token_iter.next() {
-        match token {
-            Token::OpenTag(name) => {
-                // Create new element and push to stack
-                let element = XmlElement {
-                    name,
-                    attributes: current_attributes.clone(),
-                    children: Vec::new(),
-                };
-                stack.push(element);
-                current_attributes.clear();
+impl ParseError {
+    pub fn position(&self) -> Position {
+        match self {
+            ParseError::MismatchedTags { position, .. }
+            | ParseError::MultipleRootElements { position, .. }
+            | ParseError::IncompleteDocument { position, .. }
+            | ParseError::UnexpectedToken { position, .. }
+            | ParseError::EmptyStack { position, .. } => *position,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MismatchedTags {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "{}: expected </{}>, found </{}>",
+                position, expected, found
+            ),
+            ParseError::MultipleRootElements { found, position } => {
+                write!(
+                    f,
+                    "{}: unexpected second root element <{}>",
+                    position, found
+                )
             }
-            Token::CloseTag(name) => {
-                // Pop element from stack and add to parent
-                if let Some(element) = stack.pop() {
-                    if element.name != name {
-                        return Err(ParseError::MismatchedTags);
-                    }
+            ParseError::IncompleteDocument { expected, position } => {
+                write!(
+                    f,
+                    "{}: expected </{}>, found end of document",
+                    position, expected
+                )
+            }
+            ParseError::UnexpectedToken { token, position } => {
+                write!(f, "{}: unexpected token {:?}", position, token)
+            }
+            ParseError::EmptyStack { found, position } => {
+                write!(f, "{}: found </{}> with no open element", position, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse_tokens<'a, I>(tokens: I) -> Result<XmlDocument, ParseError>
+where
+    I: IntoIterator<Item = SpannedToken<'a>>,
+{
+    parse_tokens_arena(tokens).map(XmlDocument::from)
+}
+
+// Like parse_tokens, but records the source formatting of every node (see models::Formatting)
+// so the writer can reproduce `source` byte-for-byte. `tokens` must come from tokenizing
+// `source` with whitespace preserved.
+pub fn parse_tokens_lossless<'a, I>(tokens: I, source: &str) -> Result<XmlDocument, ParseError>
+where
+    I: IntoIterator<Item = SpannedToken<'a>>,
+{
+    TreeBuilder::new(false, Some(source))
+        .build(tokens)
+        .map(|(arena, _)| XmlDocument::from(arena))
+}
+
+// Builds the document as an XmlArena, for walking up to parents and across to siblings
+pub fn parse_tokens_arena<'a, I>(tokens: I) -> Result<XmlArena, ParseError>
+where
+    I: IntoIterator<Item = SpannedToken<'a>>,
+{
+    TreeBuilder::new(false, None)
+        .build(tokens)
+        .map(|(arena, _)| arena)
+}
+
+// Like parse_tokens, but repairs structural errors instead of stopping at the first one:
+// unclosed elements are closed, stray close tags skipped and extra root elements dropped.
+// Every repair is described by a Diagnostic.
+pub fn parse_tokens_recovering<'a, I>(tokens: I) -> (XmlDocument, Vec<Diagnostic>)
+where
+    I: IntoIterator<Item = SpannedToken<'a>>,
+{
+    // Errors become diagnostics when recovering, so this is always Ok
+    TreeBuilder::new(true, None)
+        .build(tokens)
+        .map(|(arena, diagnostics)| (XmlDocument::from(arena), diagnostics))
+        .unwrap_or_default()
+}
+
+// An element whose close tag has not been seen yet
+struct OpenElement {
+    id: NodeId,
+    // Child nodes added so far, which is the index of the next one
+    children: usize,
+}
 
-                    if let Some(parent) = stack.last_mut() {
-                        parent.children.push(XmlNode::Element(element));
-                    } else {
-                        // This is the root element
-                        if root_element.is_none() {
-                            root_element = Some(element);
-                        } else {
-                            // Multiple root elements - this is invalid XML
-                            return Err(ParseError::MismatchedTags);
+struct TreeBuilder<'s> {
+    stack: Vec<OpenElement>,
+    arena: XmlArena,
+    recover: bool,
+    diagnostics: Vec<Diagnostic>,
+    // The input, when parsing losslessly
+    source: Option<&'s str>,
+}
+
+impl<'s> TreeBuilder<'s> {
+    fn new(recover: bool, source: Option<&'s str>) -> Self {
+        let mut arena = XmlArena::default();
+        arena.format = source.map(|_| DocumentFormat::default());
+        Self {
+            stack: Vec::new(),
+            arena,
+            recover,
+            diagnostics: Vec::new(),
+            source,
+        }
+    }
+
+    fn build<'a, I>(mut self, tokens: I) -> Result<(XmlArena, Vec<Diagnostic>), ParseError>
+    where
+        I: IntoIterator<Item = SpannedToken<'a>>,
+    {
+        let mut current_attributes = Attributes::new();
+        let mut end = Position::start();
+
+        for SpannedToken { token, span } in tokens {
+            end = span.end;
+            match token {
+                Token::OpenTag(name) => {
+                    // Create new element and push to stack
+                    let mut element = build_element(
+                        name.into_owned(),
+                        std::mem::take(&mut current_attributes),
+                        span,
+                        self.open_element(),
+                    );
+                    element.format = self.element_format(span, false);
+                    let id = self.insert(element);
+                    self.stack.push(OpenElement { id, children: 0 });
+                }
+                Token::CloseTag(name) => self.close_tag(&name, span)?,
+                Token::SelfClosingTag(name) => {
+                    // Create self-closing element and add to current parent
+                    let mut element = build_element(
+                        name.into_owned(),
+                        std::mem::take(&mut current_attributes),
+                        span,
+                        self.open_element(),
+                    );
+                    element.format = self.element_format(span, true);
+                    let id = self.insert(element);
+                    self.attach(id)?;
+                }
+                Token::Attribute(name, value, quote) => {
+                    // Store attribute for the next opening tag
+                    let format = self
+                        .source
+                        .map(|source| attribute_format(source, &name, quote, span));
+                    current_attributes.push(Attribute {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                        quote,
+                        format,
+                    });
+                }
+                Token::Text(content) => {
+                    // Add text as child of current element; lossless parsing also keeps the
+                    // whitespace around the root element
+                    if !self.stack.is_empty() || self.source.is_some() {
+                        let raw = self.raw(span).filter(|raw| **raw != *escape(&content));
+                        self.push_node(XmlNode::Text(content.into_owned()), raw);
+                    }
+                }
+                Token::CData(content) => {
+                    // CDATA content is kept apart from text so it can be written back as-is
+                    if !self.stack.is_empty() {
+                        self.push_node(XmlNode::CData(content.into_owned()), None);
+                    }
+                }
+                Token::Comment(content) => {
+                    // Add comment as child of current element, or keep it in the prolog/epilog
+                    self.push_node(XmlNode::Comment(content.into_owned()), None);
+                }
+                Token::ProcessingInstruction { target, data } => {
+                    // The whitespace between target and data is not part of either
+                    let raw = self.raw(span);
+                    self.push_node(
+                        XmlNode::ProcessingInstruction {
+                            target: target.into_owned(),
+                            data: data.into_owned(),
+                        },
+                        raw,
+                    );
+                }
+                Token::XmlDeclaration(declaration) => {
+                    if self.arena.declaration.is_none() {
+                        if let (Some(format), Some(raw)) = (
+                            self.arena.format.as_mut(),
+                            self.source.map(|s| &s[span.byte_range()]),
+                        ) {
+                            format.declaration = Some((declaration.clone(), raw.to_string()));
                         }
+                        self.arena.declaration = Some(declaration);
                     }
-                } else {
-                    return Err(ParseError::EmptyStack);
                 }
-            }
-            Token::SelfClosingTag(name) => {
-                // Create self-closing element and add to current parent
-                let element = XmlElement {
-                    name,
-                    attributes: current_attributes.clone(),
-                    children: Vec::new(),
-                };
-
-                if let Some(parent) = stack.last_mut() {
-                    parent.children.push(XmlNode::Element(element));
-                } else {
-                    // Self-closing root element
-                    if root_element.is_none() {
-                        root_element = Some(element);
-                    } else {
-                        return Err(ParseError::MismatchedTags);
+                Token::Doctype(doctype) => {
+                    if self.arena.doctype.is_none() {
+                        if let (Some(format), Some(raw)) = (
+                            self.arena.format.as_mut(),
+                            self.source.map(|s| &s[span.byte_range()]),
+                        ) {
+                            format.doctype = Some((doctype.clone(), raw.to_string()));
+                            format.doctype_index = self.arena.prolog.len();
+                        }
+                        self.arena.doctype = Some(doctype);
                     }
                 }
-                current_attributes.clear();
-            }
-            Token::Attribute(name, value) => {
-                // Store attribute for the next opening tag
-                current_attributes.insert(name, value);
-            }
-            Token::Text(content) => {
-                // Add text as child of current element
-                if let Some(element) = stack.last_mut() {
-                    element.children.push(XmlNode::Text(content));
+                Token::EndOfFile => {
+                    // Check if we have a complete document
+                    return self.finish(span.end);
                 }
             }
-            Token::Comment(content) => {
-                // Add comment as child of current element
-                if let Some(element) = stack.last_mut() {
-                    element.children.push(XmlNode::Comment(content));
+        }
+
+        // If we reach here, check if we have a valid document
+        self.finish(end)
+    }
+
+    // Returns `error` in strict parsing; when recovering, records the repair and carries on
+    fn report(
+        &mut self,
+        error: ParseError,
+        kind: DiagnosticKind,
+        message: String,
+    ) -> Result<(), ParseError> {
+        if !self.recover {
+            return Err(error);
+        }
+        self.diagnostics.push(Diagnostic {
+            kind,
+            message,
+            position: error.position(),
+        });
+        Ok(())
+    }
+
+    fn open_element(&self) -> Option<&XmlElement> {
+        self.stack
+            .last()
+            .and_then(|open| self.arena.element(open.id))
+    }
+
+    fn name(&self, id: NodeId) -> &str {
+        self.arena
+            .element(id)
+            .map_or("", |element| element.name.as_str())
+    }
+
+    fn close_tag(&mut self, name: &str, span: Span) -> Result<(), ParseError> {
+        let Some(index) = self
+            .stack
+            .iter()
+            .rposition(|open| self.name(open.id) == name)
+        else {
+            // Nothing open by this name, so the close tag is skipped
+            let message = format!("skipped stray </{}>", name);
+            let error = match self.stack.last() {
+                Some(open) => ParseError::MismatchedTags {
+                    expected: self.name(open.id).to_string(),
+                    found: name.to_string(),
+                    position: span.start,
+                },
+                None => ParseError::EmptyStack {
+                    found: name.to_string(),
+                    position: span.start,
+                },
+            };
+            return self.report(error, DiagnosticKind::StrayCloseTag, message);
+        };
+
+        // Anything opened after the matching element is closed along with it
+        while let Some(open) = self.stack.pop() {
+            if self.stack.len() == index {
+                let raw = self.raw(span);
+                if let Some(element) = self.arena.element_mut(open.id) {
+                    // The element now spans from its open tag to the end of its close tag
+                    if let Some(open_span) = element.span {
+                        element.span = Some(Span::new(open_span.start, span.end));
+                    }
+                    if let (Some(format), Some(raw)) = (element.format.as_mut(), raw) {
+                        // Between "</name" and ">"
+                        format.end_tag_end = raw[2 + name.len()..raw.len() - 1].to_string();
+                    }
                 }
+                return self.attach(open.id);
             }
-            Token::XmlDeclaration => {
-                // XML declarations are ignored during parsing
-                // They don't affect the document structure
+
+            let expected = self.name(open.id).to_string();
+            let message = format!("closed <{}> at </{}>", expected, name);
+            let error = ParseError::MismatchedTags {
+                expected,
+                found: name.to_string(),
+                position: span.start,
+            };
+            self.report(error, DiagnosticKind::UnclosedElement, message)?;
+            self.close_unfinished(open.id, span.start)?;
+        }
+        Ok(())
+    }
+
+    // Adds a new element to the open element, or makes it the root. Another element at the top
+    // level stays detached until it is finished, when attach reports it.
+    fn insert(&mut self, element: XmlElement) -> NodeId {
+        let parent = self.stack.last_mut().map(|open| {
+            open.children += 1;
+            open.id
+        });
+        let id = self.arena.append(parent, XmlNode::Element(element));
+        if parent.is_none() && self.arena.root().is_none() {
+            self.arena.set_root(id);
+        }
+        id
+    }
+
+    // Checks a finished element: a detached one is a second root element, which is dropped
+    // along with everything under it
+    fn attach(&mut self, id: NodeId) -> Result<(), ParseError> {
+        if self.arena.parent(id).is_some() || self.arena.root() == Some(id) {
+            return Ok(());
+        }
+        // Multiple root elements - this is invalid XML
+        let (found, position) = match self.arena.element(id) {
+            Some(element) => (element.name.clone(), element.span.unwrap_or_default().start),
+            None => (String::new(), Position::start()),
+        };
+        let message = format!("dropped second root element <{}>", found);
+        let error = ParseError::MultipleRootElements { found, position };
+        self.report(error, DiagnosticKind::ExtraRootElement, message)?;
+        self.arena.truncate(id);
+        Ok(())
+    }
+
+    // Finishes an element that was never closed, ending its span at `end`
+    fn close_unfinished(&mut self, id: NodeId, end: Position) -> Result<(), ParseError> {
+        if let Some(element) = self.arena.element_mut(id) {
+            if let Some(open_span) = element.span {
+                element.span = Some(Span::new(open_span.start, end));
             }
-            Token::EndOfFile => {
-                // Check if we have a complete document
-                if stack.is_empty() {
-                    return Ok(XmlDocument { root: root_element });
-                } else {
-                    return Err(ParseError::IncompleteDocument);
-                }
+        }
+        self.attach(id)
+    }
+
+    // Adds a text, comment or processing instruction node to the open element, or to the
+    // prolog/epilog, recording its source text when that is given
+    fn push_node(&mut self, node: XmlNode, raw: Option<&str>) {
+        if let Some(open) = self.stack.last_mut() {
+            let raw_nodes = self
+                .arena
+                .element_mut(open.id)
+                .and_then(|element| element.format.as_mut())
+                .map(|format| &mut format.raw_children);
+            if let (Some(raw_nodes), Some(raw)) = (raw_nodes, raw) {
+                raw_nodes.push(RawNode {
+                    index: open.children,
+                    raw: raw.to_string(),
+                });
             }
+            open.children += 1;
+            self.arena.append(Some(open.id), node);
+            return;
         }
+
+        let (nodes, raw_nodes) = if self.arena.root().is_none() {
+            (
+                &mut self.arena.prolog,
+                self.arena
+                    .format
+                    .as_mut()
+                    .map(|format| &mut format.raw_prolog),
+            )
+        } else {
+            (
+                &mut self.arena.epilog,
+                self.arena
+                    .format
+                    .as_mut()
+                    .map(|format| &mut format.raw_epilog),
+            )
+        };
+        if let (Some(raw_nodes), Some(raw)) = (raw_nodes, raw) {
+            raw_nodes.push(RawNode {
+                index: nodes.len(),
+                raw: raw.to_string(),
+            });
+        }
+        nodes.push(node);
     }
 
-    // If we reach here, check if we have a valid document
-    if stack.is_empty() {
-        Ok(XmlDocument { root: root_element })
-    } else {
-        Err(ParseError::IncompleteDocument)
+    fn raw(&self, span: Span) -> Option<&'s str> {
+        self.source.map(|source| &source[span.byte_range()])
     }
+
+    fn element_format(&self, span: Span, self_closing: bool) -> Option<ElementFormat> {
+        let source = self.source?;
+        let tag_end = span.end.offset - if self_closing { 2 } else { 1 };
+        Some(ElementFormat {
+            start_tag_end: whitespace_before(source, tag_end).to_string(),
+            self_closing,
+            ..ElementFormat::default()
+        })
+    }
+
+    fn finish(mut self, end: Position) -> Result<(XmlArena, Vec<Diagnostic>), ParseError> {
+        while let Some(unclosed) = self.stack.pop() {
+            let expected = self.name(unclosed.id).to_string();
+            let message = format!("closed <{}> at end of document", expected);
+            let error = ParseError::IncompleteDocument {
+                expected,
+                position: end,
+            };
+            self.report(error, DiagnosticKind::UnclosedElement, message)?;
+            self.close_unfinished(unclosed.id, end)?;
+        }
+        Ok((self.arena, self.diagnostics))
+    }
+}
+
+// Creates an element, resolving its namespace against the parent's scope plus any
+// xmlns / xmlns:prefix declarations among its own attributes
+fn build_element(
+    name: String,
+    attributes: Attributes,
+    span: Span,
+    parent: Option<&XmlElement>,
+) -> XmlElement {
+    let mut element = XmlElement::new(name);
+    element.attributes = attributes;
+    element.span = Some(span);
+    resolve_namespace(&mut element, parent.map(|parent| &parent.namespaces));
+    element
+}
+
+// Sets `namespace` and `namespaces` on an element whose name and attributes are already in place
+pub(crate) fn resolve_namespace(element: &mut XmlElement, parent: Option<&NamespaceScope>) {
+    let declarations = element.attributes.iter().filter_map(|(key, value)| {
+        if key == "xmlns" {
+            Some(("", value.as_str()))
+        } else {
+            key.strip_prefix("xmlns:")
+                .map(|prefix| (prefix, value.as_str()))
+        }
+    });
+    let namespaces = match parent {
+        Some(parent) => parent.with_declarations(declarations),
+        None => NamespaceScope::default().with_declarations(declarations),
+    };
+
+    element.namespace = namespaces
+        .lookup(element.prefix().unwrap_or(""))
+        .map(str::to_string);
+    element.namespaces = namespaces;
+}
+
+// Splits the source of name="value" into the pieces the writer needs to reproduce it
+fn attribute_format(source: &str, name: &str, quote: Quote, span: Span) -> AttributeFormat {
+    let raw = &source[span.byte_range()];
+    let after_name = &raw[name.len()..];
+    let quote_index = after_name.find(quote.as_char()).unwrap_or(0);
+    AttributeFormat {
+        leading: whitespace_before(source, span.start.offset).to_string(),
+        equals: after_name[..quote_index].to_string(),
+        raw_value: after_name[quote_index + 1..after_name.len() - 1].to_string(),
+    }
+}
+
+fn whitespace_before(source: &str, offset: usize) -> &str {
+    let start = source[..offset]
+        .trim_end_matches([' ', '\t', '\n', '\r'])
+        .len();
+    &source[start..offset]
 }
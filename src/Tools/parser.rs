@@ -0,0 +1,184 @@
+use crate::models::Diagnostic::Diagnostic;
+use crate::models::Position::Position;
+use crate::models::XmlDocument::XmlDocument;
+use crate::Tools::encoding::{decode_bytes, EncodingError};
+use crate::Tools::lexical_analysis::{tokenize, tokenize_with, TokenizeError, TokenizerOptions};
+use crate::Tools::parse_tokens::{
+    parse_tokens, parse_tokens_lossless, parse_tokens_recovering, ParseError,
+};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+#[derive(Debug)]
+pub enum XmlError {
+    Io(io::Error),
+    Zip(ZipError),
+    Encoding(EncodingError),
+    Tokenize(TokenizeError),
+    Parse(ParseError),
+    // Any of the above, raised while reading `file`
+    InFile { file: String, error: Box<XmlError> },
+}
+
+impl XmlError {
+    pub fn in_file(self, file: impl Into<String>) -> Self {
+        XmlError::InFile {
+            file: file.into(),
+            error: Box::new(self),
+        }
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            XmlError::InFile { file, .. } => Some(file),
+            _ => None,
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            XmlError::Tokenize(error) => Some(error.position()),
+            XmlError::Parse(error) => Some(error.position()),
+            XmlError::InFile { error, .. } => error.position(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XmlError::Io(error) => write!(f, "{}", error),
+            XmlError::Zip(error) => write!(f, "{}", error),
+            XmlError::Encoding(error) => write!(f, "{}", error),
+            XmlError::Tokenize(error) => write!(f, "{}", error),
+            XmlError::Parse(error) => write!(f, "{}", error),
+            XmlError::InFile { file, error } => write!(f, "{}: {}", file, error),
+        }
+    }
+}
+
+impl std::error::Error for XmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XmlError::Io(error) => Some(error),
+            XmlError::Zip(error) => Some(error),
+            XmlError::Encoding(error) => Some(error),
+            XmlError::Tokenize(error) => Some(error),
+            XmlError::Parse(error) => Some(error),
+            XmlError::InFile { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<io::Error> for XmlError {
+    fn from(error: io::Error) -> Self {
+        XmlError::Io(error)
+    }
+}
+
+impl From<ZipError> for XmlError {
+    fn from(error: ZipError) -> Self {
+        XmlError::Zip(error)
+    }
+}
+
+impl From<EncodingError> for XmlError {
+    fn from(error: EncodingError) -> Self {
+        XmlError::Encoding(error)
+    }
+}
+
+impl From<TokenizeError> for XmlError {
+    fn from(error: TokenizeError) -> Self {
+        XmlError::Tokenize(error)
+    }
+}
+
+impl From<ParseError> for XmlError {
+    fn from(error: ParseError) -> Self {
+        XmlError::Parse(error)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParsedFile {
+    pub filename: String,
+    pub document: XmlDocument,
+}
+
+pub fn parse_str(xml: &str) -> Result<XmlDocument, XmlError> {
+    let tokens = tokenize(xml)?;
+    Ok(parse_tokens(tokens)?)
+}
+
+// Parses hand-edited or truncated files by repairing structural errors instead of failing.
+// Tokenize errors are still fatal since there is no reliable way to resync the lexer.
+pub fn parse_str_recovering(xml: &str) -> Result<(XmlDocument, Vec<Diagnostic>), XmlError> {
+    let tokens = tokenize(xml)?;
+    Ok(parse_tokens_recovering(tokens))
+}
+
+// Keeps all whitespace and the source formatting of every node, so that writing the document
+// with WriterOptions::lossless() gives back `xml` byte-for-byte, edits aside
+pub fn parse_str_lossless(xml: &str) -> Result<XmlDocument, XmlError> {
+    let tokens = tokenize_with(xml, &TokenizerOptions::preserve_whitespace())?;
+    Ok(parse_tokens_lossless(tokens, xml)?)
+}
+
+// Parses raw file content, transcoding UTF-16 and ISO-8859-1 input to UTF-8 first
+pub fn parse_bytes(bytes: &[u8]) -> Result<XmlDocument, XmlError> {
+    let text = decode_bytes(bytes)?;
+    parse_str(&text)
+}
+
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<XmlDocument, XmlError> {
+    let path = path.as_ref();
+    std::fs::read(path)
+        .map_err(XmlError::from)
+        .and_then(|bytes| parse_bytes(&bytes))
+        .map_err(|error| error.in_file(path.display().to_string()))
+}
+
+pub fn parse_file_lossless<P: AsRef<Path>>(path: P) -> Result<XmlDocument, XmlError> {
+    let path = path.as_ref();
+    std::fs::read(path)
+        .map_err(XmlError::from)
+        .and_then(|bytes| parse_str_lossless(&decode_bytes(&bytes)?))
+        .map_err(|error| error.in_file(path.display().to_string()))
+}
+
+// Decompresses a .rosz (or any zip) archive and parses every .ros/.cat file inside it
+pub fn parse_rosz<P: AsRef<Path>>(path: P) -> Result<Vec<ParsedFile>, XmlError> {
+    let path = path.as_ref();
+    let archive_name = path.display().to_string();
+    let mut archive = File::open(path)
+        .map_err(XmlError::from)
+        .and_then(|file| Ok(ZipArchive::new(BufReader::new(file))?))
+        .map_err(|error| error.in_file(archive_name.as_str()))?;
+
+    let mut parsed_files = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|error| XmlError::from(error).in_file(archive_name.as_str()))?;
+        let filename = entry.name().to_string();
+        if !(filename.ends_with(".ros") || filename.ends_with(".cat")) {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        let document = entry
+            .read_to_end(&mut bytes)
+            .map_err(XmlError::from)
+            .and_then(|_| parse_bytes(&bytes))
+            .map_err(|error| error.in_file(format!("{}/{}", archive_name, filename)))?;
+
+        parsed_files.push(ParsedFile { filename, document });
+    }
+
+    Ok(parsed_files)
+}
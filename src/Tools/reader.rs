@@ -0,0 +1,373 @@
+use crate::models::Attribute::{Attribute, Attributes};
+use crate::models::DocumentType::DocumentType;
+use crate::models::Position::Position;
+use crate::models::XmlDeclaration::XmlDeclaration;
+use crate::Tools::lexical_analysis::{Lexer, SpannedToken, Token, TokenizeError, TokenizerOptions};
+use crate::Tools::parse_tokens::ParseError;
+use memchr::memchr;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlEvent {
+    Declaration(XmlDeclaration),
+    Doctype(DocumentType),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+    // A self-closing tag produces a StartElement immediately followed by its EndElement.
+    // Attributes are in document order with the quote each was written with.
+    StartElement {
+        name: String,
+        attributes: Attributes,
+    },
+    EndElement {
+        name: String,
+    },
+    Text(String),
+    CData(String),
+    Comment(String),
+    EndOfFile,
+}
+
+#[derive(Debug)]
+pub enum ReaderError {
+    Io(io::Error),
+    InvalidUtf8 { position: Position },
+    Tokenize(TokenizeError),
+    Parse(ParseError),
+}
+
+impl std::fmt::Display for ReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReaderError::Io(error) => write!(f, "read error: {}", error),
+            ReaderError::InvalidUtf8 { position } => write!(f, "{}: invalid UTF-8", position),
+            ReaderError::Tokenize(error) => write!(f, "{}", error),
+            ReaderError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReaderError::Io(error) => Some(error),
+            ReaderError::InvalidUtf8 { .. } => None,
+            ReaderError::Tokenize(error) => Some(error),
+            ReaderError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for ReaderError {
+    fn from(error: io::Error) -> Self {
+        ReaderError::Io(error)
+    }
+}
+
+impl From<TokenizeError> for ReaderError {
+    fn from(error: TokenizeError) -> Self {
+        ReaderError::Tokenize(error)
+    }
+}
+
+impl From<ParseError> for ReaderError {
+    fn from(error: ParseError) -> Self {
+        ReaderError::Parse(error)
+    }
+}
+
+// Pull parser over any buffered source. Only one markup unit (a tag, comment, text run,
+// ...) is held in memory at a time; the open element names are kept so that mismatched
+// and unclosed tags are still reported.
+pub struct XmlReader<R: BufRead> {
+    source: R,
+    options: TokenizerOptions,
+    buffer: Vec<u8>,
+    // Where the next unit starts
+    cursor: Position,
+    // Where the last returned event starts
+    event_position: Position,
+    preserve_stack: Vec<bool>,
+    open_elements: Vec<String>,
+    seen_root: bool,
+    pending: VecDeque<(XmlEvent, Position)>,
+    finished: bool,
+}
+
+impl<R: BufRead> XmlReader<R> {
+    pub fn new(source: R) -> Self {
+        Self::with_options(source, TokenizerOptions::default())
+    }
+
+    pub fn with_options(source: R, options: TokenizerOptions) -> Self {
+        Self {
+            source,
+            options,
+            buffer: Vec::new(),
+            cursor: Position::start(),
+            event_position: Position::start(),
+            preserve_stack: Vec::new(),
+            open_elements: Vec::new(),
+            seen_root: false,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.event_position
+    }
+
+    pub fn depth(&self) -> usize {
+        self.open_elements.len()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    // Returns the next event, then EndOfFile forever once the document is done or an
+    // error has been returned
+    pub fn next_event(&mut self) -> Result<XmlEvent, ReaderError> {
+        let result = self.read_event();
+        if result.is_err() {
+            self.finished = true;
+            self.pending.clear();
+        }
+        result
+    }
+
+    fn read_event(&mut self) -> Result<XmlEvent, ReaderError> {
+        loop {
+            if let Some((event, position)) = self.pending.pop_front() {
+                self.event_position = position;
+                return Ok(event);
+            }
+            if self.finished {
+                self.event_position = self.cursor;
+                return Ok(XmlEvent::EndOfFile);
+            }
+
+            if !self.read_unit()? {
+                self.finished = true;
+                if let Some(unclosed) = self.open_elements.pop() {
+                    return Err(ParseError::IncompleteDocument {
+                        expected: unclosed,
+                        position: self.cursor,
+                    }
+                    .into());
+                }
+                continue;
+            }
+
+            let buffer = std::mem::take(&mut self.buffer);
+            let result = self.lex_unit(&buffer);
+            self.buffer = buffer;
+            result?;
+        }
+    }
+
+    // Reads the next markup unit or text run into the buffer; false at end of input
+    fn read_unit(&mut self) -> io::Result<bool> {
+        self.buffer.clear();
+        let first = match self.source.fill_buf()?.first() {
+            Some(&byte) => byte,
+            None => return Ok(false),
+        };
+
+        if first == b'<' {
+            while self.source.read_until(b'>', &mut self.buffer)? > 0 {
+                if markup_complete(&self.buffer) {
+                    break;
+                }
+            }
+            return Ok(true);
+        }
+
+        // Text runs up to the next '<', which is left for the following unit
+        loop {
+            let available = self.source.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            match memchr(b'<', available) {
+                Some(index) => {
+                    self.buffer.extend_from_slice(&available[..index]);
+                    self.source.consume(index);
+                    break;
+                }
+                None => {
+                    let length = available.len();
+                    self.buffer.extend_from_slice(available);
+                    self.source.consume(length);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn lex_unit(&mut self, bytes: &[u8]) -> Result<(), ReaderError> {
+        let unit = std::str::from_utf8(bytes).map_err(|error| {
+            let valid = String::from_utf8_lossy(&bytes[..error.valid_up_to()]);
+            ReaderError::InvalidUtf8 {
+                position: self.cursor.advanced_by(&valid),
+            }
+        })?;
+
+        let preserve_stack = std::mem::take(&mut self.preserve_stack);
+        let mut lexer = Lexer::resume(unit, &self.options, self.cursor, preserve_stack);
+        let mut attributes = Attributes::new();
+
+        for token in lexer.by_ref() {
+            let SpannedToken { token, span } = token?;
+            let start = span.start;
+            match token {
+                Token::Attribute(name, value, quote) => {
+                    attributes.push(Attribute {
+                        name: name.into_owned(),
+                        value: value.into_owned(),
+                        quote,
+                        format: None,
+                    });
+                }
+                Token::OpenTag(name) => {
+                    let name = name.into_owned();
+                    self.check_root(&name, start)?;
+                    self.open_elements.push(name.clone());
+                    let attributes = std::mem::take(&mut attributes);
+                    self.pending
+                        .push_back((XmlEvent::StartElement { name, attributes }, start));
+                }
+                Token::SelfClosingTag(name) => {
+                    let name = name.into_owned();
+                    self.check_root(&name, start)?;
+                    let attributes = std::mem::take(&mut attributes);
+                    let end = XmlEvent::EndElement { name: name.clone() };
+                    self.pending
+                        .push_back((XmlEvent::StartElement { name, attributes }, start));
+                    self.pending.push_back((end, start));
+                }
+                Token::CloseTag(name) => {
+                    let name = name.into_owned();
+                    match self.open_elements.pop() {
+                        Some(expected) if expected == name => {}
+                        Some(expected) => {
+                            return Err(ParseError::MismatchedTags {
+                                expected,
+                                found: name,
+                                position: start,
+                            }
+                            .into())
+                        }
+                        None => {
+                            return Err(ParseError::EmptyStack {
+                                found: name,
+                                position: start,
+                            }
+                            .into())
+                        }
+                    }
+                    self.pending
+                        .push_back((XmlEvent::EndElement { name }, start));
+                }
+                // Text outside the root element is ignored, as in parse_tokens
+                Token::Text(text) if !self.open_elements.is_empty() => {
+                    self.pending
+                        .push_back((XmlEvent::Text(text.into_owned()), start));
+                }
+                Token::CData(content) if !self.open_elements.is_empty() => {
+                    self.pending
+                        .push_back((XmlEvent::CData(content.into_owned()), start));
+                }
+                Token::Text(_) | Token::CData(_) => {}
+                Token::Comment(comment) => {
+                    self.pending
+                        .push_back((XmlEvent::Comment(comment.into_owned()), start));
+                }
+                Token::ProcessingInstruction { target, data } => {
+                    let event = XmlEvent::ProcessingInstruction {
+                        target: target.into_owned(),
+                        data: data.into_owned(),
+                    };
+                    self.pending.push_back((event, start));
+                }
+                Token::XmlDeclaration(declaration) => {
+                    self.pending
+                        .push_back((XmlEvent::Declaration(declaration), start));
+                }
+                Token::Doctype(doctype) => {
+                    self.pending.push_back((XmlEvent::Doctype(doctype), start));
+                }
+                // Only the end of this unit; the reader reports its own EndOfFile
+                Token::EndOfFile => {}
+            }
+        }
+
+        self.cursor = lexer.position();
+        self.preserve_stack = lexer.into_preserve_stack();
+        Ok(())
+    }
+
+    fn check_root(&mut self, name: &str, position: Position) -> Result<(), ParseError> {
+        if self.open_elements.is_empty() {
+            if self.seen_root {
+                return Err(ParseError::MultipleRootElements {
+                    found: name.to_string(),
+                    position,
+                });
+            }
+            self.seen_root = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for XmlReader<R> {
+    type Item = Result<XmlEvent, ReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished && self.pending.is_empty() {
+            return None;
+        }
+        Some(self.next_event())
+    }
+}
+
+// Whether `unit`, read up to a '>', holds a whole piece of markup. Tags and DOCTYPE end at
+// the first '>' outside quotes (and outside the DOCTYPE's internal subset).
+fn markup_complete(unit: &[u8]) -> bool {
+    if !unit.ends_with(b">") {
+        return false;
+    }
+    if unit.starts_with(b"<!--") {
+        return unit.len() >= "<!---->".len() && unit.ends_with(b"-->");
+    }
+    if unit.starts_with(b"<![CDATA[") {
+        return unit.len() >= "<![CDATA[]]>".len() && unit.ends_with(b"]]>");
+    }
+    if unit.starts_with(b"<?") {
+        return unit.len() >= "<??>".len() && unit.ends_with(b"?>");
+    }
+    if unit.starts_with(b"</") {
+        return true;
+    }
+
+    let has_subset = unit.starts_with(b"<!");
+    let mut quote: Option<u8> = None;
+    let mut in_subset = false;
+    for &byte in unit {
+        match (quote, byte) {
+            (Some(open), _) if byte == open => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (None, b'[') if has_subset && !in_subset => in_subset = true,
+            (None, b']') if in_subset => in_subset = false,
+            (None, b'>') if !in_subset => return true,
+            _ => {}
+        }
+    }
+    false
+}
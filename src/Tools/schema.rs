@@ -0,0 +1,1189 @@
+use crate::models::Position::Position;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::Tools::parser::{parse_file, parse_str, XmlError};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
+
+// Validates documents against the subset of XML Schema the BattleScribe schemas are written in:
+// - global and local xs:element declarations, element refs, named and inline types
+// - xs:complexType with xs:sequence / xs:choice (nested, with minOccurs / maxOccurs), xs:any,
+//   xs:attribute (use="required"), xs:anyAttribute, mixed content and xs:simpleContent extensions
+// - xs:simpleType restrictions with enumeration, minInclusive / maxInclusive and
+//   minLength / maxLength / length facets, over the built-in string, boolean, numeric and
+//   integer types
+// The other built-in types a schema may name (date, dateTime, time, duration, anyURI, QName,
+// Name, NCName, ID, IDREF(S), NMTOKEN(S), language, token, normalizedString, anySimpleType) are
+// accepted, but their values are only checked as strings: they are not parsed, and IDs are not
+// matched against IDREFs. Any other construct in a schema is refused with
+// SchemaError::Unsupported rather than ignored.
+// Elements are matched by local name; only the root's namespace is checked, against the
+// schema's targetNamespace.
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Xml(XmlError),
+    // The document's root is not xs:schema
+    NotASchema,
+    // An XSD construct outside the supported subset
+    Unsupported {
+        construct: String,
+        position: Option<Position>,
+    },
+    MissingAttribute {
+        element: String,
+        attribute: String,
+        position: Option<Position>,
+    },
+    InvalidValue {
+        attribute: String,
+        value: String,
+        position: Option<Position>,
+    },
+    // A type="..." or base="..." naming a type the schema does not define
+    UnknownType {
+        name: String,
+    },
+    // A ref="..." naming an element the schema does not declare globally
+    UnknownElement {
+        name: String,
+    },
+    // A simple type whose chain of base="..." types leads back to itself
+    CircularType {
+        name: String,
+    },
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let at = |position: &Option<Position>| match position {
+            Some(position) => format!("{}: ", position),
+            None => String::new(),
+        };
+        match self {
+            SchemaError::Xml(error) => write!(f, "{}", error),
+            SchemaError::NotASchema => write!(f, "the root element is not xs:schema"),
+            SchemaError::Unsupported {
+                construct,
+                position,
+            } => write!(
+                f,
+                "{}unsupported schema construct <{}>",
+                at(position),
+                construct
+            ),
+            SchemaError::MissingAttribute {
+                element,
+                attribute,
+                position,
+            } => write!(
+                f,
+                "{}<{}> is missing its \"{}\" attribute",
+                at(position),
+                element,
+                attribute
+            ),
+            SchemaError::InvalidValue {
+                attribute,
+                value,
+                position,
+            } => write!(
+                f,
+                "{}invalid value \"{}\" for \"{}\"",
+                at(position),
+                value,
+                attribute
+            ),
+            SchemaError::UnknownType { name } => write!(f, "unknown type \"{}\"", name),
+            SchemaError::UnknownElement { name } => {
+                write!(f, "no global element declaration for \"{}\"", name)
+            }
+            SchemaError::CircularType { name } => {
+                write!(f, "simple type \"{}\" is derived from itself", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<XmlError> for SchemaError {
+    fn from(error: XmlError) -> Self {
+        SchemaError::Xml(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    // No global declaration matches the element
+    UnknownElement,
+    // The root element is not in the schema's target namespace
+    WrongNamespace,
+    // A child element the content model does not allow at this point
+    UnexpectedElement,
+    // A required child element is absent
+    MissingElement,
+    MissingAttribute,
+    UndeclaredAttribute,
+    // An attribute value or text that does not fit its simple type
+    InvalidValue,
+    // Text inside an element that may only contain elements
+    UnexpectedText,
+}
+
+// One way a document breaks its schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    pub kind: ViolationKind,
+    // Element names from the root, with [n] on elements that have same-named siblings
+    pub path: String,
+    pub position: Option<Position>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{}: {}: {}", position, self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+// ============================================================================
+// SCHEMA MODEL
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Schema {
+    target_namespace: Option<String>,
+    elements: HashMap<String, ElementDecl>,
+    complex_types: HashMap<String, ComplexType>,
+    simple_types: HashMap<String, SimpleType>,
+}
+
+#[derive(Debug, Clone)]
+struct ElementDecl {
+    name: String,
+    kind: TypeRef,
+}
+
+#[derive(Debug, Clone)]
+enum TypeRef {
+    // xs:anyType, or no type given: anything goes
+    Any,
+    Simple(SimpleRef),
+    Complex(Box<ComplexType>),
+    // A named type, complex or simple
+    Named(String),
+}
+
+#[derive(Debug, Clone)]
+enum SimpleRef {
+    Builtin(Builtin),
+    Named(String),
+    Inline(Box<SimpleType>),
+}
+
+#[derive(Debug, Clone, Default)]
+struct ComplexType {
+    particle: Option<Particle>,
+    // Set for xs:simpleContent: text of this type and no child elements
+    simple_content: Option<SimpleRef>,
+    attributes: Vec<AttributeDecl>,
+    any_attribute: bool,
+    mixed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeDecl {
+    name: String,
+    kind: SimpleRef,
+    required: bool,
+}
+
+#[derive(Debug, Clone)]
+struct SimpleType {
+    base: SimpleRef,
+    enumeration: Vec<String>,
+    min_inclusive: Option<f64>,
+    max_inclusive: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    term: Term,
+    min: usize,
+    // None for maxOccurs="unbounded"
+    max: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Element(ElementDecl),
+    // ref="name", resolved against the global declarations when validating
+    ElementRef(String),
+    Any(ProcessContents),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessContents {
+    Strict,
+    Lax,
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    String,
+    Boolean,
+    Decimal,
+    Double,
+    Integer {
+        min: Option<i128>,
+        max: Option<i128>,
+    },
+}
+
+impl Builtin {
+    fn from_name(name: &str) -> Option<Self> {
+        let integer = |min: Option<i128>, max: Option<i128>| Builtin::Integer { min, max };
+        Some(match name {
+            "string" | "normalizedString" | "token" | "language" | "Name" | "NCName" | "ID"
+            | "IDREF" | "IDREFS" | "NMTOKEN" | "NMTOKENS" | "anyURI" | "QName" | "date"
+            | "dateTime" | "time" | "duration" | "anySimpleType" => Builtin::String,
+            "boolean" => Builtin::Boolean,
+            "decimal" => Builtin::Decimal,
+            "float" | "double" => Builtin::Double,
+            "integer" => integer(None, None),
+            "long" => integer(Some(i64::MIN.into()), Some(i64::MAX.into())),
+            "int" => integer(Some(i32::MIN.into()), Some(i32::MAX.into())),
+            "short" => integer(Some(i16::MIN.into()), Some(i16::MAX.into())),
+            "byte" => integer(Some(i8::MIN.into()), Some(i8::MAX.into())),
+            "nonNegativeInteger" => integer(Some(0), None),
+            "positiveInteger" => integer(Some(1), None),
+            "nonPositiveInteger" => integer(None, Some(0)),
+            "negativeInteger" => integer(None, Some(-1)),
+            "unsignedLong" => integer(Some(0), Some(u64::MAX.into())),
+            "unsignedInt" => integer(Some(0), Some(u32::MAX.into())),
+            "unsignedShort" => integer(Some(0), Some(u16::MAX.into())),
+            "unsignedByte" => integer(Some(0), Some(u8::MAX.into())),
+            _ => return None,
+        })
+    }
+
+    fn check(self, value: &str) -> Result<(), String> {
+        let trimmed = value.trim();
+        let valid = match self {
+            Builtin::String => true,
+            Builtin::Boolean => matches!(trimmed, "true" | "false" | "1" | "0"),
+            Builtin::Decimal => is_decimal(trimmed),
+            Builtin::Double => matches!(trimmed, "INF" | "-INF" | "NaN") || is_double(trimmed),
+            Builtin::Integer { min, max } => {
+                let Ok(number) = trimmed.parse::<i128>() else {
+                    return Err(format!("\"{}\" is not an integer", value));
+                };
+                if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                    return Err(format!("{} is out of range", number));
+                }
+                true
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            let expected = match self {
+                Builtin::Boolean => "a boolean",
+                _ => "a number",
+            };
+            Err(format!("\"{}\" is not {}", value, expected))
+        }
+    }
+}
+
+// [+-]digits[.digits], with at least one digit somewhere
+fn is_decimal(value: &str) -> bool {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    !(whole.is_empty() && fraction.is_empty())
+        && whole.bytes().all(|byte| byte.is_ascii_digit())
+        && fraction.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+// A decimal with an optional exponent, e.g. 1.5E-3. Rust's own spellings such as "inf" and
+// "nan" are not XSD doubles.
+fn is_double(value: &str) -> bool {
+    let (mantissa, exponent) = match value.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (value, None),
+    };
+    is_decimal(mantissa)
+        && exponent.is_none_or(|exponent| {
+            let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+        })
+}
+
+// ============================================================================
+// LOADING
+// ============================================================================
+
+impl Schema {
+    pub fn parse_str(xsd: &str) -> Result<Self, SchemaError> {
+        Self::from_document(&parse_str(xsd)?)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SchemaError> {
+        Self::from_document(&parse_file(path)?)
+    }
+
+    pub fn from_document(document: &XmlDocument) -> Result<Self, SchemaError> {
+        let root = document
+            .get_root_element()
+            .filter(|root| root.has_name(Some(XSD_NAMESPACE), "schema"))
+            .ok_or(SchemaError::NotASchema)?;
+
+        let mut schema = Schema {
+            target_namespace: root.get_attribute("targetNamespace").cloned(),
+            elements: HashMap::new(),
+            complex_types: HashMap::new(),
+            simple_types: HashMap::new(),
+        };
+        for child in xsd_children(root)? {
+            match child.local_name() {
+                "element" => {
+                    let declaration = element_decl(child)?;
+                    schema
+                        .elements
+                        .insert(declaration.name.clone(), declaration);
+                }
+                "complexType" => {
+                    let name = required(child, "name")?;
+                    schema.complex_types.insert(name, complex_type(child)?);
+                }
+                "simpleType" => {
+                    let name = required(child, "name")?;
+                    schema.simple_types.insert(name, simple_type(child)?);
+                }
+                _ => return Err(unsupported(child)),
+            }
+        }
+
+        schema.check_references()?;
+        Ok(schema)
+    }
+
+    // Every named type and element ref must exist, so validation never meets a dangling name
+    fn check_references(&self) -> Result<(), SchemaError> {
+        for declaration in self.elements.values() {
+            self.check_type(&declaration.kind)?;
+        }
+        for complex in self.complex_types.values() {
+            self.check_complex(complex)?;
+        }
+        for simple in self.simple_types.values() {
+            self.check_simple(&simple.base)?;
+        }
+        for name in self.simple_types.keys() {
+            self.check_derivation(name)?;
+        }
+        Ok(())
+    }
+
+    // Follows the base="..." chain from a named simple type down to a built-in, so validation
+    // never recurses around a cycle
+    fn check_derivation(&self, name: &str) -> Result<(), SchemaError> {
+        let mut visited = vec![name];
+        let mut base = &self.simple_types[name].base;
+        loop {
+            match base {
+                SimpleRef::Builtin(_) => return Ok(()),
+                SimpleRef::Inline(simple) => base = &simple.base,
+                SimpleRef::Named(next) => {
+                    if visited.contains(&next.as_str()) {
+                        return Err(SchemaError::CircularType {
+                            name: name.to_string(),
+                        });
+                    }
+                    visited.push(next);
+                    base = &self.simple_types[next].base;
+                }
+            }
+        }
+    }
+
+    fn check_type(&self, kind: &TypeRef) -> Result<(), SchemaError> {
+        match kind {
+            TypeRef::Any => Ok(()),
+            TypeRef::Simple(simple) => self.check_simple(simple),
+            TypeRef::Complex(complex) => self.check_complex(complex),
+            TypeRef::Named(name) => {
+                if self.complex_types.contains_key(name) || self.simple_types.contains_key(name) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::UnknownType { name: name.clone() })
+                }
+            }
+        }
+    }
+
+    fn check_complex(&self, complex: &ComplexType) -> Result<(), SchemaError> {
+        if let Some(particle) = &complex.particle {
+            self.check_particle(particle)?;
+        }
+        if let Some(simple) = &complex.simple_content {
+            self.check_simple(simple)?;
+        }
+        for attribute in &complex.attributes {
+            self.check_simple(&attribute.kind)?;
+        }
+        Ok(())
+    }
+
+    fn check_particle(&self, particle: &Particle) -> Result<(), SchemaError> {
+        match &particle.term {
+            Term::Element(declaration) => self.check_type(&declaration.kind),
+            Term::ElementRef(name) => {
+                if self.elements.contains_key(name) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::UnknownElement { name: name.clone() })
+                }
+            }
+            Term::Any(_) => Ok(()),
+            Term::Sequence(items) | Term::Choice(items) => {
+                items.iter().try_for_each(|item| self.check_particle(item))
+            }
+        }
+    }
+
+    fn check_simple(&self, simple: &SimpleRef) -> Result<(), SchemaError> {
+        match simple {
+            SimpleRef::Builtin(_) => Ok(()),
+            SimpleRef::Named(name) => {
+                if self.simple_types.contains_key(name) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::UnknownType { name: name.clone() })
+                }
+            }
+            SimpleRef::Inline(simple) => self.check_simple(&simple.base),
+        }
+    }
+}
+
+fn position(element: &XmlElement) -> Option<Position> {
+    element.span.map(|span| span.start)
+}
+
+fn unsupported(element: &XmlElement) -> SchemaError {
+    SchemaError::Unsupported {
+        construct: element.name.clone(),
+        position: position(element),
+    }
+}
+
+fn required(element: &XmlElement, attribute: &str) -> Result<String, SchemaError> {
+    element
+        .get_attribute(attribute)
+        .cloned()
+        .ok_or_else(|| SchemaError::MissingAttribute {
+            element: element.name.clone(),
+            attribute: attribute.to_string(),
+            position: position(element),
+        })
+}
+
+fn invalid(element: &XmlElement, attribute: &str, value: &str) -> SchemaError {
+    SchemaError::InvalidValue {
+        attribute: attribute.to_string(),
+        value: value.to_string(),
+        position: position(element),
+    }
+}
+
+// The XSD child elements, skipping documentation; anything from another namespace is refused
+fn xsd_children(element: &XmlElement) -> Result<Vec<&XmlElement>, SchemaError> {
+    let mut children = Vec::new();
+    for child in element.child_elements() {
+        if child.namespace.as_deref() != Some(XSD_NAMESPACE) {
+            return Err(unsupported(child));
+        }
+        if child.local_name() != "annotation" {
+            children.push(child);
+        }
+    }
+    Ok(children)
+}
+
+// A QName attribute value: built-in when its prefix maps to the XSD namespace
+enum TypeName {
+    Builtin(String),
+    Schema(String),
+}
+
+fn type_name(element: &XmlElement, attribute: &str) -> Option<TypeName> {
+    let value = element.get_attribute(attribute)?;
+    let (prefix, local) = value.split_once(':').unwrap_or(("", value));
+    Some(
+        if element.namespaces.lookup(prefix) == Some(XSD_NAMESPACE) {
+            TypeName::Builtin(local.to_string())
+        } else {
+            TypeName::Schema(local.to_string())
+        },
+    )
+}
+
+fn builtin(element: &XmlElement, attribute: &str, name: &str) -> Result<Builtin, SchemaError> {
+    Builtin::from_name(name).ok_or_else(|| invalid(element, attribute, name))
+}
+
+fn element_decl(element: &XmlElement) -> Result<ElementDecl, SchemaError> {
+    let name = required(element, "name")?;
+    let mut kind = match type_name(element, "type") {
+        Some(TypeName::Builtin(name)) if name == "anyType" => TypeRef::Any,
+        Some(TypeName::Builtin(name)) => {
+            TypeRef::Simple(SimpleRef::Builtin(builtin(element, "type", &name)?))
+        }
+        Some(TypeName::Schema(name)) => TypeRef::Named(name),
+        None => TypeRef::Any,
+    };
+    for child in xsd_children(element)? {
+        kind = match child.local_name() {
+            "complexType" => TypeRef::Complex(Box::new(complex_type(child)?)),
+            "simpleType" => TypeRef::Simple(SimpleRef::Inline(Box::new(simple_type(child)?))),
+            _ => return Err(unsupported(child)),
+        };
+    }
+    Ok(ElementDecl { name, kind })
+}
+
+fn complex_type(element: &XmlElement) -> Result<ComplexType, SchemaError> {
+    let mut complex = ComplexType {
+        mixed: element.get_attribute("mixed").is_some_and(|v| v == "true"),
+        ..Default::default()
+    };
+    for child in xsd_children(element)? {
+        match child.local_name() {
+            "sequence" | "choice" if complex.particle.is_none() => {
+                complex.particle = Some(particle(child)?);
+            }
+            "attribute" => complex.attributes.push(attribute_decl(child)?),
+            "anyAttribute" => complex.any_attribute = true,
+            "simpleContent" => simple_content(child, &mut complex)?,
+            _ => return Err(unsupported(child)),
+        }
+    }
+    Ok(complex)
+}
+
+// <xs:simpleContent><xs:extension base="..."> plus attributes
+fn simple_content(element: &XmlElement, complex: &mut ComplexType) -> Result<(), SchemaError> {
+    let children = xsd_children(element)?;
+    let [extension] = children.as_slice() else {
+        return Err(unsupported(element));
+    };
+    if extension.local_name() != "extension" {
+        return Err(unsupported(extension));
+    }
+    complex.simple_content = Some(simple_ref(extension, "base")?);
+    for child in xsd_children(extension)? {
+        match child.local_name() {
+            "attribute" => complex.attributes.push(attribute_decl(child)?),
+            "anyAttribute" => complex.any_attribute = true,
+            _ => return Err(unsupported(child)),
+        }
+    }
+    Ok(())
+}
+
+fn simple_ref(element: &XmlElement, attribute: &str) -> Result<SimpleRef, SchemaError> {
+    Ok(match type_name(element, attribute) {
+        Some(TypeName::Builtin(name)) => SimpleRef::Builtin(builtin(element, attribute, &name)?),
+        Some(TypeName::Schema(name)) => SimpleRef::Named(name),
+        None => SimpleRef::Builtin(Builtin::String),
+    })
+}
+
+fn attribute_decl(element: &XmlElement) -> Result<AttributeDecl, SchemaError> {
+    let name = required(element, "name")?;
+    let mut kind = simple_ref(element, "type")?;
+    for child in xsd_children(element)? {
+        if child.local_name() != "simpleType" {
+            return Err(unsupported(child));
+        }
+        kind = SimpleRef::Inline(Box::new(simple_type(child)?));
+    }
+    let required = match element.get_attribute("use").map(String::as_str) {
+        None | Some("optional") => false,
+        Some("required") => true,
+        Some(other) => return Err(invalid(element, "use", other)),
+    };
+    Ok(AttributeDecl {
+        name,
+        kind,
+        required,
+    })
+}
+
+fn simple_type(element: &XmlElement) -> Result<SimpleType, SchemaError> {
+    let children = xsd_children(element)?;
+    let [restriction] = children.as_slice() else {
+        return Err(unsupported(element));
+    };
+    if restriction.local_name() != "restriction" {
+        return Err(unsupported(restriction));
+    }
+
+    let mut simple = SimpleType {
+        base: simple_ref(restriction, "base")?,
+        enumeration: Vec::new(),
+        min_inclusive: None,
+        max_inclusive: None,
+        min_length: None,
+        max_length: None,
+    };
+    for facet in xsd_children(restriction)? {
+        if facet.local_name() == "simpleType" {
+            simple.base = SimpleRef::Inline(Box::new(simple_type(facet)?));
+            continue;
+        }
+        let value = required(facet, "value")?;
+        let number = || {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| invalid(facet, "value", &value))
+        };
+        let length = || {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| invalid(facet, "value", &value))
+        };
+        match facet.local_name() {
+            "enumeration" => simple.enumeration.push(value.clone()),
+            "minInclusive" => simple.min_inclusive = Some(number()?),
+            "maxInclusive" => simple.max_inclusive = Some(number()?),
+            "minLength" => simple.min_length = Some(length()?),
+            "maxLength" => simple.max_length = Some(length()?),
+            "length" => {
+                simple.min_length = Some(length()?);
+                simple.max_length = simple.min_length;
+            }
+            // Only affects how values are compared, and every comparison here trims already
+            "whiteSpace" => {}
+            _ => return Err(unsupported(facet)),
+        }
+    }
+    Ok(simple)
+}
+
+fn occurs(element: &XmlElement) -> Result<(usize, Option<usize>), SchemaError> {
+    let min = match element.get_attribute("minOccurs") {
+        Some(value) => value
+            .parse()
+            .map_err(|_| invalid(element, "minOccurs", value))?,
+        None => 1,
+    };
+    let max = match element.get_attribute("maxOccurs").map(String::as_str) {
+        Some("unbounded") => None,
+        Some(value) => Some(
+            value
+                .parse()
+                .map_err(|_| invalid(element, "maxOccurs", value))?,
+        ),
+        None => Some(1),
+    };
+    Ok((min, max))
+}
+
+fn particle(element: &XmlElement) -> Result<Particle, SchemaError> {
+    let (min, max) = occurs(element)?;
+    let term = match element.local_name() {
+        "element" => match element.get_attribute("ref") {
+            Some(name) => Term::ElementRef(
+                name.split_once(':')
+                    .map_or(name.as_str(), |(_, local)| local)
+                    .to_string(),
+            ),
+            None => Term::Element(element_decl(element)?),
+        },
+        "any" => Term::Any(
+            match element.get_attribute("processContents").map(String::as_str) {
+                None | Some("strict") => ProcessContents::Strict,
+                Some("lax") => ProcessContents::Lax,
+                Some("skip") => ProcessContents::Skip,
+                Some(other) => return Err(invalid(element, "processContents", other)),
+            },
+        ),
+        "sequence" | "choice" => {
+            let items = xsd_children(element)?
+                .into_iter()
+                .map(particle)
+                .collect::<Result<Vec<_>, _>>()?;
+            if element.local_name() == "sequence" {
+                Term::Sequence(items)
+            } else {
+                Term::Choice(items)
+            }
+        }
+        _ => return Err(unsupported(element)),
+    };
+    Ok(Particle { term, min, max })
+}
+
+// ============================================================================
+// VALIDATION
+// ============================================================================
+
+impl Schema {
+    // Every violation in the document, in document order
+    pub fn validate(&self, document: &XmlDocument) -> Vec<SchemaViolation> {
+        match document.get_root_element() {
+            Some(root) => self.validate_element(root),
+            None => Vec::new(),
+        }
+    }
+
+    // Checks `element` against the global declaration of its name
+    pub fn validate_element(&self, element: &XmlElement) -> Vec<SchemaViolation> {
+        let mut checker = Checker {
+            schema: self,
+            violations: Vec::new(),
+        };
+        let path = element.name.clone();
+        match self.elements.get(element.local_name()) {
+            Some(declaration) => {
+                if self.target_namespace.is_some()
+                    && element.namespace.as_deref() != self.target_namespace.as_deref()
+                {
+                    checker.report(
+                        ViolationKind::WrongNamespace,
+                        &path,
+                        element,
+                        format!(
+                            "expected namespace \"{}\", found {}",
+                            self.target_namespace.as_deref().unwrap_or_default(),
+                            element
+                                .namespace
+                                .as_deref()
+                                .map_or("none".to_string(), |ns| format!("\"{}\"", ns))
+                        ),
+                    );
+                }
+                checker.check_element(&declaration.kind, element, &path);
+            }
+            None => checker.report(
+                ViolationKind::UnknownElement,
+                &path,
+                element,
+                format!("<{}> is not declared in the schema", element.name),
+            ),
+        }
+        checker.violations
+    }
+
+    pub fn is_valid(&self, document: &XmlDocument) -> bool {
+        self.validate(document).is_empty()
+    }
+}
+
+// The resolved type of an element
+enum Resolved<'s> {
+    Any,
+    Simple(Cow<'s, SimpleRef>),
+    Complex(&'s ComplexType),
+}
+
+struct Checker<'s> {
+    schema: &'s Schema,
+    violations: Vec<SchemaViolation>,
+}
+
+// Each child element with its path under `path`; names shared by several siblings get [n]
+pub(crate) fn child_paths<'e>(parent: &'e XmlElement, path: &str) -> Vec<(String, &'e XmlElement)> {
+    let mut totals: HashMap<&str, usize> = HashMap::new();
+    for child in parent.child_elements() {
+        *totals.entry(child.name.as_str()).or_default() += 1;
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    parent
+        .child_elements()
+        .map(|child| {
+            let name = child.name.as_str();
+            let label = if totals[name] > 1 {
+                let index = seen.entry(name).or_default();
+                *index += 1;
+                format!("{}/{}[{}]", path, name, index)
+            } else {
+                format!("{}/{}", path, name)
+            };
+            (label, child)
+        })
+        .collect()
+}
+
+// The child elements of the element being checked, consumed front to back by the content model
+struct Children<'e> {
+    elements: Vec<(String, &'e XmlElement)>,
+    index: usize,
+}
+
+impl<'e> Children<'e> {
+    fn new(parent: &'e XmlElement, path: &str) -> Self {
+        Self {
+            elements: child_paths(parent, path),
+            index: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&'e XmlElement> {
+        self.elements.get(self.index).map(|(_, element)| *element)
+    }
+
+    fn next(&mut self) -> Option<(String, &'e XmlElement)> {
+        let next = self.elements.get(self.index).cloned();
+        self.index += 1;
+        next
+    }
+}
+
+impl<'s> Checker<'s> {
+    fn report(&mut self, kind: ViolationKind, path: &str, element: &XmlElement, message: String) {
+        self.violations.push(SchemaViolation {
+            kind,
+            path: path.to_string(),
+            position: position(element),
+            message,
+        });
+    }
+
+    fn resolve(&self, kind: &'s TypeRef) -> Resolved<'s> {
+        match kind {
+            TypeRef::Any => Resolved::Any,
+            TypeRef::Simple(simple) => Resolved::Simple(Cow::Borrowed(simple)),
+            TypeRef::Complex(complex) => Resolved::Complex(complex),
+            // check_references made sure a named type is one or the other
+            TypeRef::Named(name) => match self.schema.complex_types.get(name) {
+                Some(complex) => Resolved::Complex(complex),
+                None => Resolved::Simple(Cow::Owned(SimpleRef::Named(name.clone()))),
+            },
+        }
+    }
+
+    fn check_element(&mut self, kind: &'s TypeRef, element: &XmlElement, path: &str) {
+        match self.resolve(kind) {
+            Resolved::Any => {}
+            Resolved::Simple(simple) => {
+                self.check_attributes(&[], false, element, path);
+                self.check_no_children(element, path);
+                self.check_text(&simple, element, path);
+            }
+            Resolved::Complex(complex) => {
+                self.check_attributes(&complex.attributes, complex.any_attribute, element, path);
+                if let Some(simple) = &complex.simple_content {
+                    self.check_no_children(element, path);
+                    self.check_text(simple, element, path);
+                    return;
+                }
+                if !complex.mixed && !element.get_text_content().trim().is_empty() {
+                    self.report(
+                        ViolationKind::UnexpectedText,
+                        path,
+                        element,
+                        format!("<{}> may only contain elements", element.name),
+                    );
+                }
+
+                let mut children = Children::new(element, path);
+                if let Some(particle) = &complex.particle {
+                    self.match_particle(particle, &mut children, element, path);
+                }
+                while let Some((child_path, child)) = children.next() {
+                    self.report(
+                        ViolationKind::UnexpectedElement,
+                        &child_path,
+                        child,
+                        format!("<{}> is not allowed here", child.name),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_attributes(
+        &mut self,
+        declarations: &'s [AttributeDecl],
+        any_attribute: bool,
+        element: &XmlElement,
+        path: &str,
+    ) {
+        for declaration in declarations {
+            match element.get_attribute(&declaration.name) {
+                Some(value) => {
+                    if let Err(reason) = self.check_simple(&declaration.kind, value) {
+                        self.report(
+                            ViolationKind::InvalidValue,
+                            path,
+                            element,
+                            format!("attribute \"{}\": {}", declaration.name, reason),
+                        );
+                    }
+                }
+                None if declaration.required => self.report(
+                    ViolationKind::MissingAttribute,
+                    path,
+                    element,
+                    format!("missing required attribute \"{}\"", declaration.name),
+                ),
+                None => {}
+            }
+        }
+        if any_attribute {
+            return;
+        }
+        // Namespace declarations and attributes from other namespaces (xml:, xsi:) are not the
+        // schema's business
+        for (name, _) in element.attributes.iter() {
+            if name == "xmlns" || name.contains(':') {
+                continue;
+            }
+            if !declarations.iter().any(|declared| declared.name == *name) {
+                self.report(
+                    ViolationKind::UndeclaredAttribute,
+                    path,
+                    element,
+                    format!("attribute \"{}\" is not declared", name),
+                );
+            }
+        }
+    }
+
+    fn check_no_children(&mut self, element: &XmlElement, path: &str) {
+        for (child_path, child) in child_paths(element, path) {
+            self.report(
+                ViolationKind::UnexpectedElement,
+                &child_path,
+                child,
+                format!("<{}> may only contain text", element.name),
+            );
+        }
+    }
+
+    fn check_text(&mut self, simple: &SimpleRef, element: &XmlElement, path: &str) {
+        let text = element.get_text_content();
+        if let Err(reason) = self.check_simple(simple, &text) {
+            self.report(ViolationKind::InvalidValue, path, element, reason);
+        }
+    }
+
+    fn check_simple(&self, simple: &SimpleRef, value: &str) -> Result<(), String> {
+        match simple {
+            SimpleRef::Builtin(builtin) => builtin.check(value),
+            SimpleRef::Named(name) => match self.schema.simple_types.get(name) {
+                Some(simple) => self.check_restriction(simple, value),
+                None => Ok(()),
+            },
+            SimpleRef::Inline(simple) => self.check_restriction(simple, value),
+        }
+    }
+
+    fn check_restriction(&self, simple: &SimpleType, value: &str) -> Result<(), String> {
+        self.check_simple(&simple.base, value)?;
+        let trimmed = value.trim();
+        if !simple.enumeration.is_empty() && !simple.enumeration.iter().any(|v| v == trimmed) {
+            return Err(format!(
+                "\"{}\" is not one of {}",
+                value,
+                simple.enumeration.join(", ")
+            ));
+        }
+        if simple.min_inclusive.is_some() || simple.max_inclusive.is_some() {
+            let number = trimmed
+                .parse::<f64>()
+                .map_err(|_| format!("\"{}\" is not a number", value))?;
+            if let Some(min) = simple.min_inclusive.filter(|min| number < *min) {
+                return Err(format!("{} is less than the minimum {}", trimmed, min));
+            }
+            if let Some(max) = simple.max_inclusive.filter(|max| number > *max) {
+                return Err(format!("{} is greater than the maximum {}", trimmed, max));
+            }
+        }
+        let length = value.chars().count();
+        if let Some(min) = simple.min_length.filter(|min| length < *min) {
+            return Err(format!(
+                "length {} is less than the minimum {}",
+                length, min
+            ));
+        }
+        if let Some(max) = simple.max_length.filter(|max| length > *max) {
+            return Err(format!(
+                "length {} is greater than the maximum {}",
+                length, max
+            ));
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // CONTENT MODELS
+    // ========================================================================
+
+    // XSD's Unique Particle Attribution rule means the next child always decides which particle
+    // it belongs to, so content models are matched greedily without backtracking
+    fn match_particle(
+        &mut self,
+        particle: &'s Particle,
+        children: &mut Children,
+        parent: &XmlElement,
+        path: &str,
+    ) {
+        let mut count = 0;
+        while particle.max.is_none_or(|max| count < max)
+            && children
+                .peek()
+                .is_some_and(|next| self.starts(&particle.term, next))
+        {
+            let before = children.index;
+            self.match_term(&particle.term, children, parent, path);
+            count += 1;
+            if children.index == before {
+                break;
+            }
+        }
+
+        if count < particle.min && !self.term_nullable(&particle.term) {
+            let expected = self.describe(&particle.term);
+            let message = if count == 0 {
+                format!("expected {}", expected)
+            } else {
+                format!(
+                    "expected at least {} of {}, found {}",
+                    particle.min, expected, count
+                )
+            };
+            // Point at the child where the missing element should have been, if there is one
+            let at = children.peek().unwrap_or(parent);
+            self.report(ViolationKind::MissingElement, path, at, message);
+        }
+    }
+
+    fn match_term(
+        &mut self,
+        term: &'s Term,
+        children: &mut Children,
+        parent: &XmlElement,
+        path: &str,
+    ) {
+        match term {
+            Term::Element(declaration) => {
+                let (child_path, child) = children.next().unwrap();
+                self.check_element(&declaration.kind, child, &child_path);
+            }
+            Term::ElementRef(name) => {
+                let (child_path, child) = children.next().unwrap();
+                let declaration = &self.schema.elements[name];
+                self.check_element(&declaration.kind, child, &child_path);
+            }
+            Term::Any(process) => {
+                let (child_path, child) = children.next().unwrap();
+                match (self.schema.elements.get(child.local_name()), process) {
+                    (_, ProcessContents::Skip) => {}
+                    (Some(declaration), _) => {
+                        self.check_element(&declaration.kind, child, &child_path)
+                    }
+                    (None, ProcessContents::Lax) => self.check_lax(child, &child_path),
+                    (None, ProcessContents::Strict) => self.report(
+                        ViolationKind::UnknownElement,
+                        &child_path,
+                        child,
+                        format!("<{}> is not declared in the schema", child.name),
+                    ),
+                }
+            }
+            Term::Sequence(items) => {
+                for item in items {
+                    self.match_particle(item, children, parent, path);
+                }
+            }
+            Term::Choice(items) => {
+                let next = children.peek();
+                if let Some(item) = items
+                    .iter()
+                    .find(|item| next.is_some_and(|next| self.starts(&item.term, next)))
+                {
+                    self.match_particle(item, children, parent, path);
+                }
+            }
+        }
+    }
+
+    // An undeclared element under a lax wildcard is skipped, but declared descendants still count
+    fn check_lax(&mut self, element: &XmlElement, path: &str) {
+        for (child_path, child) in child_paths(element, path) {
+            match self.schema.elements.get(child.local_name()) {
+                Some(declaration) => self.check_element(&declaration.kind, child, &child_path),
+                None => self.check_lax(child, &child_path),
+            }
+        }
+    }
+
+    // Whether `term` can begin with `element`
+    fn starts(&self, term: &Term, element: &XmlElement) -> bool {
+        match term {
+            Term::Element(declaration) => element.local_name() == declaration.name,
+            Term::ElementRef(name) => element.local_name() == name,
+            Term::Any(_) => true,
+            Term::Sequence(items) => {
+                for item in items {
+                    if self.starts(&item.term, element) {
+                        return true;
+                    }
+                    if !self.nullable(item) {
+                        return false;
+                    }
+                }
+                false
+            }
+            Term::Choice(items) => items.iter().any(|item| self.starts(&item.term, element)),
+        }
+    }
+
+    fn nullable(&self, particle: &Particle) -> bool {
+        particle.min == 0 || self.term_nullable(&particle.term)
+    }
+
+    fn term_nullable(&self, term: &Term) -> bool {
+        match term {
+            Term::Element(_) | Term::ElementRef(_) | Term::Any(_) => false,
+            Term::Sequence(items) => items.iter().all(|item| self.nullable(item)),
+            Term::Choice(items) => items.is_empty() || items.iter().any(|i| self.nullable(i)),
+        }
+    }
+
+    fn describe(&self, term: &Term) -> String {
+        match term {
+            Term::Element(declaration) => format!("<{}>", declaration.name),
+            Term::ElementRef(name) => format!("<{}>", name),
+            Term::Any(_) => "an element".to_string(),
+            Term::Sequence(items) => items
+                .iter()
+                .find(|item| !self.nullable(item))
+                .or(items.first())
+                .map_or_else(String::new, |item| self.describe(&item.term)),
+            Term::Choice(items) => {
+                let options: Vec<String> =
+                    items.iter().map(|item| self.describe(&item.term)).collect();
+                format!("one of {}", options.join(", "))
+            }
+        }
+    }
+}
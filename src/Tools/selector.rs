@@ -0,0 +1,549 @@
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+
+// CSS-style selectors over the owned tree: type selectors and "*", attribute selectors
+// ([a], [a=v], [a^=v], [a$=v], [a*=v]), :nth-child(an+b | odd | even), the descendant and ">"
+// combinators, and comma-separated lists. Attribute values may be quoted or bare.
+// Prefixed names use CSS's "|": bs|selectionEntry matches <bs:selectionEntry>, *|entry matches
+// an entry under any prefix or none, bs|* any element prefixed bs, and [xlink|href] the
+// xlink:href attribute. Prefixes are compared as written, not resolved to namespace URIs.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectorError {
+    UnexpectedEnd { position: usize },
+    UnexpectedCharacter { found: char, position: usize },
+    UnknownPseudoClass { name: String, position: usize },
+}
+
+impl std::fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectorError::UnexpectedEnd { position } => {
+                write!(f, "offset {}: unexpected end of selector", position)
+            }
+            SelectorError::UnexpectedCharacter { found, position } => {
+                write!(f, "offset {}: unexpected '{}'", position, found)
+            }
+            SelectorError::UnknownPseudoClass { name, position } => {
+                write!(f, "offset {}: unknown pseudo-class :{}", position, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AttributeMatch {
+    Exists,
+    Equals(String),
+    Prefix(String),
+    Suffix(String),
+    Contains(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AttributeSelector {
+    name: String,
+    matcher: AttributeMatch,
+}
+
+// :nth-child(an+b), with positions counted from 1 among element siblings
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NthChild {
+    step: i64,
+    offset: i64,
+}
+
+impl NthChild {
+    fn matches(self, position: usize) -> bool {
+        let position = position as i64;
+        if self.step == 0 {
+            return position == self.offset;
+        }
+        let n = position - self.offset;
+        n % self.step == 0 && n / self.step >= 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+enum TypeSelector {
+    // "*" or "*|*"
+    #[default]
+    Any,
+    // "name", "prefix|name" as "prefix:name", or "|name"
+    Name(String),
+    // "*|name"
+    LocalName(String),
+    // "prefix|*", or "|*" for elements without a prefix
+    Prefix(Option<String>),
+}
+
+impl TypeSelector {
+    fn matches(&self, element: &XmlElement) -> bool {
+        match self {
+            TypeSelector::Any => true,
+            TypeSelector::Name(name) => element.name == *name,
+            TypeSelector::LocalName(name) => element.local_name() == name,
+            TypeSelector::Prefix(prefix) => element.prefix() == prefix.as_deref(),
+        }
+    }
+}
+
+// Everything that applies to a single element, e.g. selectionEntry[type=unit]:nth-child(2)
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Compound {
+    name: TypeSelector,
+    attributes: Vec<AttributeSelector>,
+    nth_child: Vec<NthChild>,
+}
+
+impl Compound {
+    fn matches(&self, element: &XmlElement, position: usize) -> bool {
+        self.name.matches(element)
+            && self.attributes.iter().all(|selector| {
+                let Some(value) = element.get_attribute(&selector.name) else {
+                    return false;
+                };
+                match &selector.matcher {
+                    AttributeMatch::Exists => true,
+                    AttributeMatch::Equals(expected) => value == expected,
+                    AttributeMatch::Prefix(prefix) => value.starts_with(prefix.as_str()),
+                    AttributeMatch::Suffix(suffix) => value.ends_with(suffix.as_str()),
+                    AttributeMatch::Contains(part) => value.contains(part.as_str()),
+                }
+            })
+            && self.nth_child.iter().all(|nth| nth.matches(position))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+// Compounds joined by combinators, e.g. "profiles > profile"; the combinator belongs to the
+// compound on its right
+#[derive(Debug, Clone, PartialEq)]
+struct Complex {
+    compounds: Vec<(Combinator, Compound)>,
+}
+
+impl Complex {
+    // `path` is the element being tested preceded by its ancestors, each with its position
+    fn matches(&self, path: &[(&XmlElement, usize)]) -> bool {
+        Self::matches_at(&self.compounds, path)
+    }
+
+    fn matches_at(compounds: &[(Combinator, Compound)], path: &[(&XmlElement, usize)]) -> bool {
+        let Some(((combinator, compound), rest)) = compounds.split_last() else {
+            return true;
+        };
+        let Some((&(element, position), ancestors)) = path.split_last() else {
+            return false;
+        };
+        if !compound.matches(element, position) {
+            return false;
+        }
+        if rest.is_empty() {
+            return true;
+        }
+        match combinator {
+            Combinator::Child => Self::matches_at(rest, ancestors),
+            Combinator::Descendant => {
+                (1..=ancestors.len()).any(|end| Self::matches_at(rest, &ancestors[..end]))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        Parser::new(selector).parse()
+    }
+
+    // Matches below `element`, in document order; `element` itself is not a candidate but
+    // can satisfy combinators
+    pub fn select<'a>(&self, element: &'a XmlElement) -> Select<'a> {
+        Select {
+            selector: self.clone(),
+            path: vec![(element, 1)],
+            stack: vec![(element.children.iter(), 0)],
+            pending_root: None,
+        }
+    }
+
+    // Matches anywhere in the document, the root element included
+    pub fn select_document<'a>(&self, document: &'a XmlDocument) -> Select<'a> {
+        Select {
+            selector: self.clone(),
+            path: Vec::new(),
+            stack: Vec::new(),
+            pending_root: document.root.as_ref(),
+        }
+    }
+}
+
+impl std::str::FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        Selector::parse(selector)
+    }
+}
+
+// Iterator over the elements matching a selector, in document order
+pub struct Select<'a> {
+    selector: Selector,
+    // The element whose children are being visited, preceded by its ancestors
+    path: Vec<(&'a XmlElement, usize)>,
+    // Remaining children of each element in `path`, with the number of elements seen so far
+    stack: Vec<(std::slice::Iter<'a, XmlNode>, usize)>,
+    pending_root: Option<&'a XmlElement>,
+}
+
+impl<'a> Select<'a> {
+    fn enter(&mut self, element: &'a XmlElement, position: usize) -> bool {
+        self.path.push((element, position));
+        self.stack.push((element.children.iter(), 0));
+        self.selector
+            .alternatives
+            .iter()
+            .any(|complex| complex.matches(&self.path))
+    }
+}
+
+impl<'a> Iterator for Select<'a> {
+    type Item = &'a XmlElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.pending_root.take() {
+            if self.enter(root, 1) {
+                return Some(root);
+            }
+        }
+
+        loop {
+            let (children, seen) = self.stack.last_mut()?;
+            match children.next() {
+                Some(XmlNode::Element(child)) => {
+                    *seen += 1;
+                    let position = *seen;
+                    if self.enter(child, position) {
+                        return Some(child);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
+impl XmlElement {
+    // Parses the selector on every call; use Selector::parse to reuse one
+    pub fn select(&self, selector: &str) -> Result<Select<'_>, SelectorError> {
+        Ok(Selector::parse(selector)?.select(self))
+    }
+
+    pub fn select_first(&self, selector: &str) -> Result<Option<&XmlElement>, SelectorError> {
+        Ok(self.select(selector)?.next())
+    }
+}
+
+impl XmlDocument {
+    pub fn select(&self, selector: &str) -> Result<Select<'_>, SelectorError> {
+        Ok(Selector::parse(selector)?.select_document(self))
+    }
+}
+
+// ============================================================================
+// PARSER
+// ============================================================================
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn new(selector: &str) -> Self {
+        Self {
+            chars: selector.chars().collect(),
+            index: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn unexpected(&self) -> SelectorError {
+        match self.peek() {
+            Some(found) => SelectorError::UnexpectedCharacter {
+                found,
+                position: self.index,
+            },
+            None => SelectorError::UnexpectedEnd {
+                position: self.index,
+            },
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SelectorError> {
+        if self.peek() == Some(expected) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    // Skips whitespace and reports whether there was any
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.index;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.index += 1;
+        }
+        self.index > start
+    }
+
+    fn parse(mut self) -> Result<Selector, SelectorError> {
+        let mut alternatives = vec![self.parse_complex()?];
+        while self.peek() == Some(',') {
+            self.index += 1;
+            alternatives.push(self.parse_complex()?);
+        }
+        match self.peek() {
+            None => Ok(Selector { alternatives }),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_complex(&mut self) -> Result<Complex, SelectorError> {
+        self.skip_whitespace();
+        let mut compounds = vec![(Combinator::Descendant, self.parse_compound()?)];
+        loop {
+            let spaced = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.index += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                None | Some(',') => break,
+                Some(_) if spaced => Combinator::Descendant,
+                Some(_) => return Err(self.unexpected()),
+            };
+            compounds.push((combinator, self.parse_compound()?));
+        }
+        Ok(Complex { compounds })
+    }
+
+    fn parse_compound(&mut self) -> Result<Compound, SelectorError> {
+        let mut compound = Compound::default();
+        let start = self.index;
+        if matches!(self.peek(), Some(c) if c == '*' || c == '|' || is_name_char(c)) {
+            compound.name = self.parse_type_selector()?;
+        }
+
+        loop {
+            match self.peek() {
+                Some('[') => compound.attributes.push(self.parse_attribute()?),
+                Some(':') => compound.nth_child.push(self.parse_pseudo_class()?),
+                _ => break,
+            }
+        }
+
+        if self.index == start {
+            return Err(self.unexpected());
+        }
+        Ok(compound)
+    }
+
+    fn parse_name(&mut self) -> Result<String, SelectorError> {
+        let start = self.index;
+        while self.peek().is_some_and(is_name_char) {
+            self.index += 1;
+        }
+        if self.index == start {
+            return Err(self.unexpected());
+        }
+        Ok(self.chars[start..self.index].iter().collect())
+    }
+
+    // name, *, prefix|name, prefix|*, *|name, *|*, |name or |*
+    fn parse_type_selector(&mut self) -> Result<TypeSelector, SelectorError> {
+        let first = self.parse_type_part();
+        if self.peek() != Some('|') {
+            return match first {
+                None => Ok(TypeSelector::Any),
+                Some(name) if name.is_empty() => Err(self.unexpected()),
+                Some(name) => Ok(TypeSelector::Name(name)),
+            };
+        }
+        self.index += 1;
+        let local = self.parse_type_part();
+        Ok(match (first, local) {
+            (_, Some(local)) if local.is_empty() => return Err(self.unexpected()),
+            (None, None) => TypeSelector::Any,
+            (None, Some(local)) => TypeSelector::LocalName(local),
+            (Some(prefix), None) => TypeSelector::Prefix(Some(prefix).filter(|p| !p.is_empty())),
+            (Some(prefix), Some(local)) if prefix.is_empty() => TypeSelector::Name(local),
+            (Some(prefix), Some(local)) => TypeSelector::Name(format!("{}:{}", prefix, local)),
+        })
+    }
+
+    // None for "*", otherwise the name, which may be empty
+    fn parse_type_part(&mut self) -> Option<String> {
+        if self.peek() == Some('*') {
+            self.index += 1;
+            return None;
+        }
+        let start = self.index;
+        while self.peek().is_some_and(is_name_char) {
+            self.index += 1;
+        }
+        Some(self.chars[start..self.index].iter().collect())
+    }
+
+    fn parse_attribute(&mut self) -> Result<AttributeSelector, SelectorError> {
+        self.expect('[')?;
+        self.skip_whitespace();
+        let mut name = self.parse_name()?;
+        // [prefix|name], but not the start of a "|=" operator
+        if self.peek() == Some('|') && self.chars.get(self.index + 1) != Some(&'=') {
+            self.index += 1;
+            name = format!("{}:{}", name, self.parse_name()?);
+        }
+        self.skip_whitespace();
+
+        let operator = match self.peek() {
+            Some(']') => None,
+            Some('=') => Some('='),
+            Some(operator @ ('^' | '$' | '*')) => {
+                self.index += 1;
+                if self.peek() != Some('=') {
+                    return Err(self.unexpected());
+                }
+                Some(operator)
+            }
+            _ => return Err(self.unexpected()),
+        };
+
+        let matcher = match operator {
+            None => AttributeMatch::Exists,
+            Some(operator) => {
+                self.expect('=')?;
+                self.skip_whitespace();
+                let value = self.parse_value()?;
+                self.skip_whitespace();
+                match operator {
+                    '^' => AttributeMatch::Prefix(value),
+                    '$' => AttributeMatch::Suffix(value),
+                    '*' => AttributeMatch::Contains(value),
+                    _ => AttributeMatch::Equals(value),
+                }
+            }
+        };
+        self.expect(']')?;
+        Ok(AttributeSelector { name, matcher })
+    }
+
+    fn parse_value(&mut self) -> Result<String, SelectorError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => {
+                let start = self.index + 1;
+                let length = self.chars[start..].iter().position(|&c| c == quote).ok_or(
+                    SelectorError::UnexpectedEnd {
+                        position: self.chars.len(),
+                    },
+                )?;
+                self.index = start + length + 1;
+                Ok(self.chars[start..start + length].iter().collect())
+            }
+            _ => self.parse_name(),
+        }
+    }
+
+    fn parse_pseudo_class(&mut self) -> Result<NthChild, SelectorError> {
+        self.expect(':')?;
+        let start = self.index;
+        let name = self.parse_name()?;
+        match name.as_str() {
+            "first-child" => Ok(NthChild { step: 0, offset: 1 }),
+            "nth-child" => {
+                self.expect('(')?;
+                self.skip_whitespace();
+                let nth = self.parse_nth()?;
+                self.skip_whitespace();
+                self.expect(')')?;
+                Ok(nth)
+            }
+            _ => Err(SelectorError::UnknownPseudoClass {
+                name,
+                position: start,
+            }),
+        }
+    }
+
+    // odd, even, b, an, an+b or an-b
+    fn parse_nth(&mut self) -> Result<NthChild, SelectorError> {
+        let start = self.index;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | ' '))
+        {
+            self.index += 1;
+        }
+        let text: String = self.chars[start..self.index]
+            .iter()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let invalid = SelectorError::UnexpectedCharacter {
+            found: self.chars.get(start).copied().unwrap_or(')'),
+            position: start,
+        };
+
+        let nth = match text.as_str() {
+            "odd" => NthChild { step: 2, offset: 1 },
+            "even" => NthChild { step: 2, offset: 0 },
+            _ => match text.split_once('n') {
+                Some((step, offset)) => NthChild {
+                    step: match step {
+                        "" | "+" => 1,
+                        "-" => -1,
+                        _ => step.parse().map_err(|_| invalid.clone())?,
+                    },
+                    offset: match offset {
+                        "" => 0,
+                        _ => offset
+                            .strip_prefix('+')
+                            .unwrap_or(offset)
+                            .parse()
+                            .map_err(|_| invalid.clone())?,
+                    },
+                },
+                None => NthChild {
+                    step: 0,
+                    offset: text.parse().map_err(|_| invalid.clone())?,
+                },
+            },
+        };
+        Ok(nth)
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
@@ -0,0 +1,426 @@
+use crate::models::EditError::EditError;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::lexical_analysis::is_valid_name;
+use serde::ser::{self, Impossible, Serialize};
+
+// The reverse of the deserializer's mapping: scalar fields become attributes ("@name" fields
+// always do), "$value" becomes text, structs and maps become child elements named after their
+// field, and sequences become one child element per item. A field renamed "wrapper/item" is
+// written as <wrapper> holding one <item> per value, like BattleScribe's
+// <constraints><constraint/></constraints>; without it the items sit directly under the parent,
+// so read a wrapper back through a plain field but write it through a "wrapper/item" one.
+// None fields are left out.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SerializeError {
+    Custom(String),
+    // The value has no XML form here, e.g. a sequence nested directly in a sequence
+    Unsupported(&'static str),
+    // A field or map key is not a valid element or attribute name
+    InvalidName(EditError),
+    // A value holds something XML cannot represent, e.g. a control character
+    InvalidValue(EditError),
+}
+
+impl std::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerializeError::Custom(message) => write!(f, "{}", message),
+            SerializeError::Unsupported(what) => write!(f, "cannot serialize {} as XML", what),
+            SerializeError::InvalidName(error) | SerializeError::InvalidValue(error) => {
+                write!(f, "{}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        SerializeError::Custom(message.to_string())
+    }
+}
+
+impl From<EditError> for SerializeError {
+    fn from(error: EditError) -> Self {
+        match error {
+            EditError::InvalidElementName(_) | EditError::InvalidAttributeName(_) => {
+                SerializeError::InvalidName(error)
+            }
+            _ => SerializeError::InvalidValue(error),
+        }
+    }
+}
+
+// Serializes `value` as an element called `name`
+pub fn to_element<T: Serialize + ?Sized>(
+    value: &T,
+    name: &str,
+) -> Result<XmlElement, SerializeError> {
+    match value.serialize(ValueSerializer { name })? {
+        Value::Element(element) => Ok(*element),
+        Value::Text(text) => {
+            let mut element = XmlElement::new(name);
+            element.set_text(text)?;
+            Ok(element)
+        }
+        Value::None => Ok(XmlElement::new(name)),
+        Value::List(_) => Err(SerializeError::Unsupported(
+            "a sequence as the root element",
+        )),
+    }
+}
+
+// What a value turns into before it is placed in its parent
+enum Value {
+    Text(String),
+    Element(Box<XmlElement>),
+    List(Vec<Value>),
+    None,
+}
+
+// Adds a serialized field to `element` under `key`
+fn place(element: &mut XmlElement, key: &str, value: Value) -> Result<(), SerializeError> {
+    match value {
+        Value::None => {}
+        Value::Text(text) if key == "$value" || key == "$text" => {
+            element.append_child(XmlNode::Text(text))?;
+        }
+        Value::Text(text) => {
+            element.set_attribute(key.strip_prefix('@').unwrap_or(key), text)?;
+        }
+        Value::Element(child) => element.append_child(*child)?,
+        Value::List(items) => {
+            for item in items {
+                match item {
+                    Value::Text(text) => {
+                        let mut child = XmlElement::new(key);
+                        child.set_text(text)?;
+                        element.append_child(child)?;
+                    }
+                    Value::Element(child) => element.append_child(*child)?,
+                    Value::None => {}
+                    Value::List(_) => {
+                        return Err(SerializeError::Unsupported("a sequence of sequences"))
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// VALUES
+// ============================================================================
+
+struct ValueSerializer<'n> {
+    // Name for the element this value becomes, if it becomes one
+    name: &'n str,
+}
+
+fn text(value: impl ToString) -> Result<Value, SerializeError> {
+    Ok(Value::Text(value.to_string()))
+}
+
+impl<'n> ser::Serializer for ValueSerializer<'n> {
+    type Ok = Value;
+    type Error = SerializeError;
+    type SerializeSeq = ListSerializer<'n>;
+    type SerializeTuple = ListSerializer<'n>;
+    type SerializeTupleStruct = ListSerializer<'n>;
+    type SerializeTupleVariant = Impossible<Value, SerializeError>;
+    type SerializeMap = ElementSerializer;
+    type SerializeStruct = ElementSerializer;
+    type SerializeStructVariant = Impossible<Value, SerializeError>;
+
+    fn serialize_bool(self, value: bool) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_char(self, value: char) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Value, Self::Error> {
+        text(value)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Value, Self::Error> {
+        Err(SerializeError::Unsupported("raw bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Value, Self::Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Self::Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Self::Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Self::Error> {
+        text(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, Self::Error> {
+        Err(SerializeError::Unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(ListSerializer {
+            name: self.name,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(SerializeError::Unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        ElementSerializer::new(self.name)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        ElementSerializer::new(self.name)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(SerializeError::Unsupported("an enum variant with data"))
+    }
+}
+
+struct ListSerializer<'n> {
+    name: &'n str,
+    items: Vec<Value>,
+}
+
+impl ListSerializer<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let item = value.serialize(ValueSerializer { name: self.name })?;
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer<'_> {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::List(self.items))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer<'_> {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::List(self.items))
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer<'_> {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::List(self.items))
+    }
+}
+
+// ============================================================================
+// ELEMENTS
+// ============================================================================
+
+struct ElementSerializer {
+    element: XmlElement,
+    // Map key waiting for its value
+    key: Option<String>,
+}
+
+impl ElementSerializer {
+    fn new(name: &str) -> Result<Self, SerializeError> {
+        // Checked here since the element is never appended anywhere when it is the root
+        if !is_valid_name(name) {
+            return Err(EditError::InvalidElementName(name.to_string()).into());
+        }
+        Ok(Self {
+            element: XmlElement::new(name),
+            key: None,
+        })
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerializeError> {
+        if let Some((wrapper, item)) = key.split_once('/') {
+            let value = match value.serialize(ValueSerializer { name: item })? {
+                Value::None => return Ok(()),
+                Value::List(items) => Value::List(items),
+                single => Value::List(vec![single]),
+            };
+            let mut wrapper = ElementSerializer::new(wrapper)?.element;
+            place(&mut wrapper, item, value)?;
+            return Ok(self.element.append_child(wrapper)?);
+        }
+
+        let name = key.strip_prefix('@').unwrap_or(key);
+        let value = value.serialize(ValueSerializer { name })?;
+        place(&mut self.element, key, value)
+    }
+}
+
+impl ser::SerializeStruct for ElementSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::Element(Box::new(self.element)))
+    }
+}
+
+impl ser::SerializeMap for ElementSerializer {
+    type Ok = Value;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        match key.serialize(ValueSerializer { name: "" })? {
+            Value::Text(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(SerializeError::Unsupported(
+                "a map key that is not a string",
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <SerializeError as ser::Error>::custom("map value without a key"))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Value, Self::Error> {
+        Ok(Value::Element(Box::new(self.element)))
+    }
+}
@@ -2,6 +2,7 @@ use crate::models::Constraint::Constraint;
 use crate::models::ConstraintType::ConstraintType;
 use crate::models::ValidationResult::ValidationResult;
 use crate::models::XmlElement::XmlElement;
+use crate::Tools::mapping::{FromXmlElement, FromXmlError};
 
 pub struct ConstraintValidator {
     constraints: Vec<Constraint>,
@@ -36,65 +37,18 @@ impl ConstraintValidator {
     }
 
     pub fn parse_constraint_element(&self, element: &XmlElement) -> Result<Constraint, String> {
-        let constraint_type = match element.get_attribute("type") {
-            Some(type_str) => match type_str.as_str() {
-                "min" => ConstraintType::Min,
-                "max" => ConstraintType::Max,
-                "equal" => ConstraintType::Equal,
-                "notEqual" => ConstraintType::NotEqual,
-                "atLeast" => ConstraintType::AtLeast,
-                "atMost" => ConstraintType::AtMost,
-                _ => return Err(format!("Unknown constraint type: {}", type_str)),
-            },
-            None => return Err("Constraint type is required".to_string()),
-        };
-
-        let value = element
-            .get_attribute("value")
-            .ok_or("Constraint value is required")?
-            .parse::<i32>()
-            .map_err(|_| "Constraint value must be a valid integer")?;
-
-        let field = element
-            .get_attribute("field")
-            .ok_or("Constraint field is required")?
-            .clone();
-
-        let scope = element
-            .get_attribute("scope")
-            .unwrap_or(&"parent".to_string())
-            .clone();
-
-        let shared = element
-            .get_attribute("shared")
-            .map(|s| s == "true")
-            .unwrap_or(false);
-
-        let id = element
-            .get_attribute("id")
-            .ok_or("Constraint id is required")?
-            .clone();
-
-        let include_child_selections = element
-            .get_attribute("includeChildSelections")
-            .map(|s| s == "true");
-
-        let include_child_forces = element
-            .get_attribute("includeChildForces")
-            .map(|s| s == "true");
-
-        let percent_value = element.get_attribute("percentValue").map(|s| s == "true");
-
-        Ok(Constraint {
-            constraint_type,
-            value,
-            field,
-            scope,
-            shared,
-            id,
-            include_child_selections,
-            include_child_forces,
-            percent_value,
+        // Keeps the messages this method gave before it used FromXmlElement
+        Constraint::from_xml_element(element).map_err(|error| match error {
+            FromXmlError::MissingAttribute { attribute, .. } => {
+                format!("Constraint {} is required", attribute)
+            }
+            FromXmlError::InvalidAttribute {
+                attribute, value, ..
+            } if attribute == "type" => format!("Unknown constraint type: {}", value),
+            FromXmlError::InvalidAttribute { attribute, .. } if attribute == "value" => {
+                "Constraint value must be a valid integer".to_string()
+            }
+            error => error.to_string(),
         })
     }
 
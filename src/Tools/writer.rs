@@ -0,0 +1,380 @@
+use crate::models::Attribute::Attribute;
+use crate::models::DocumentType::DocumentType;
+use crate::models::Formatting::{DocumentFormat, RawNode};
+use crate::models::XmlDeclaration::XmlDeclaration;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use crate::Tools::lexical_analysis::decode_references;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriterOptions {
+    // None writes the whole tree on one line, like newrecruit's .ros files. Some(unit) puts
+    // every element on its own line, indented by `unit` per level, like BSData's .cat files.
+    // Elements holding text are always written on one line so the text is left untouched.
+    pub indent: Option<String>,
+    // Write the document's XML declaration, or a default UTF-8 one if it has none. Output is
+    // always UTF-8, so a declared encoding is written as UTF-8 whatever the source used.
+    pub declaration: bool,
+    // Write elements without children as <name/> rather than <name></name>
+    pub self_close_empty: bool,
+    // Write <name /> rather than <name/>; newrecruit uses the former, BattleScribe the latter
+    pub space_before_slash: bool,
+    // Reuse the formatting recorded by lossless parsing for every node still holding the
+    // value it was parsed with; the other options then only apply to new or edited nodes
+    pub preserve_formatting: bool,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        Self {
+            indent: None,
+            declaration: true,
+            self_close_empty: true,
+            space_before_slash: true,
+            preserve_formatting: false,
+        }
+    }
+}
+
+impl WriterOptions {
+    pub fn compact() -> Self {
+        Self::default()
+    }
+
+    pub fn indented() -> Self {
+        Self {
+            indent: Some("  ".to_string()),
+            space_before_slash: false,
+            ..Self::default()
+        }
+    }
+
+    // Writes a losslessly parsed document back exactly as it was read
+    pub fn lossless() -> Self {
+        Self {
+            space_before_slash: false,
+            preserve_formatting: true,
+            ..Self::default()
+        }
+    }
+}
+
+pub struct XmlWriter<W: Write> {
+    out: W,
+    options: WriterOptions,
+}
+
+impl<W: Write> XmlWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self::with_options(out, WriterOptions::default())
+    }
+
+    pub fn with_options(out: W, options: WriterOptions) -> Self {
+        Self { out, options }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    pub fn write_document(&mut self, document: &XmlDocument) -> io::Result<()> {
+        let depth = self.top_level_depth();
+        let format = document
+            .format
+            .as_ref()
+            .filter(|_| self.options.preserve_formatting);
+        match format {
+            // The whitespace around the declaration and doctype is part of the prolog here
+            Some(format) => self.write_formatted_prolog(document, format)?,
+            None => {
+                if self.options.declaration {
+                    let default = XmlDeclaration::default();
+                    self.write_declaration(document.declaration.as_ref().unwrap_or(&default))?;
+                    self.out.write_all(b"\n")?;
+                }
+                if let Some(doctype) = &document.doctype {
+                    self.write_doctype(doctype)?;
+                    self.out.write_all(b"\n")?;
+                }
+                self.write_nodes(&document.prolog, &[], 0, depth)?;
+            }
+        }
+
+        if let Some(root) = &document.root {
+            self.write_element_at(root, depth)?;
+        }
+        let raw_epilog = format.map_or(&[][..], |format| &format.raw_epilog);
+        self.write_nodes(&document.epilog, raw_epilog, 0, depth)?;
+        self.out.flush()
+    }
+
+    pub fn write_element(&mut self, element: &XmlElement) -> io::Result<()> {
+        self.write_element_at(element, self.top_level_depth())
+    }
+
+    pub fn write_node(&mut self, node: &XmlNode) -> io::Result<()> {
+        self.write_node_at(node, self.top_level_depth())
+    }
+
+    // Depth is None when writing inline, either in compact mode or inside mixed content
+    fn top_level_depth(&self) -> Option<usize> {
+        self.options.indent.is_some().then_some(0)
+    }
+
+    fn write_formatted_prolog(
+        &mut self,
+        document: &XmlDocument,
+        format: &DocumentFormat,
+    ) -> io::Result<()> {
+        // A document parsed without a declaration keeps going without one
+        let declaration = document
+            .declaration
+            .as_ref()
+            .filter(|_| self.options.declaration);
+        if let Some(declaration) = declaration {
+            match &format.declaration {
+                Some((parsed, raw)) if parsed == declaration && declares_utf8(declaration) => {
+                    self.out.write_all(raw.as_bytes())?
+                }
+                _ => self.write_declaration(declaration)?,
+            }
+        }
+
+        let doctype_index = format.doctype_index.min(document.prolog.len());
+        let (before, after) = document.prolog.split_at(doctype_index);
+        self.write_nodes(before, &format.raw_prolog, 0, None)?;
+        if let Some(doctype) = &document.doctype {
+            match &format.doctype {
+                Some((parsed, raw)) if parsed == doctype => self.out.write_all(raw.as_bytes())?,
+                _ => self.write_doctype(doctype)?,
+            }
+        }
+        self.write_nodes(after, &format.raw_prolog, doctype_index, None)
+    }
+
+    fn write_declaration(&mut self, declaration: &XmlDeclaration) -> io::Result<()> {
+        write!(self.out, "<?xml version=\"{}\"", declaration.version)?;
+        if declaration.encoding.is_some() {
+            self.out.write_all(b" encoding=\"UTF-8\"")?;
+        }
+        if let Some(standalone) = declaration.standalone {
+            let standalone = if standalone { "yes" } else { "no" };
+            write!(self.out, " standalone=\"{}\"", standalone)?;
+        }
+        self.out.write_all(b"?>")
+    }
+
+    fn write_doctype(&mut self, doctype: &DocumentType) -> io::Result<()> {
+        write!(self.out, "<!DOCTYPE {}", doctype.name)?;
+        if let Some(external_id) = &doctype.external_id {
+            write!(self.out, " {}", external_id)?;
+        }
+        if let Some(internal_subset) = &doctype.internal_subset {
+            write!(self.out, " [{}]", internal_subset)?;
+        }
+        self.out.write_all(b">")
+    }
+
+    fn write_node_at(&mut self, node: &XmlNode, depth: Option<usize>) -> io::Result<()> {
+        match node {
+            XmlNode::Element(element) => return self.write_element_at(element, depth),
+            XmlNode::Text(content) => return self.out.write_all(escape(content).as_bytes()),
+            XmlNode::CData(content) => {
+                self.write_indent(depth)?;
+                write!(self.out, "<![CDATA[{}]]>", content)?;
+            }
+            XmlNode::Comment(content) => {
+                self.write_indent(depth)?;
+                write!(self.out, "<!--{}-->", content)?;
+            }
+            XmlNode::ProcessingInstruction { target, data } => {
+                self.write_indent(depth)?;
+                if data.is_empty() {
+                    write!(self.out, "<?{}?>", target)?;
+                } else {
+                    write!(self.out, "<?{} {}?>", target, data)?;
+                }
+            }
+        }
+        self.end_line(depth)
+    }
+
+    fn write_element_at(&mut self, element: &XmlElement, depth: Option<usize>) -> io::Result<()> {
+        let format = element
+            .format
+            .as_ref()
+            .filter(|_| self.options.preserve_formatting);
+
+        self.write_indent(depth)?;
+        write!(self.out, "<{}", element.name)?;
+        for attribute in element.attributes_in_order() {
+            self.write_attribute(attribute)?;
+        }
+        if let Some(format) = format {
+            self.out.write_all(format.start_tag_end.as_bytes())?;
+        }
+
+        let self_closing = match format {
+            Some(format) => format.self_closing,
+            None => self.options.self_close_empty,
+        };
+        if element.children.is_empty() && self_closing {
+            let end: &[u8] = if self.options.space_before_slash && format.is_none() {
+                b" />"
+            } else {
+                b"/>"
+            };
+            self.out.write_all(end)?;
+            return self.end_line(depth);
+        }
+        self.out.write_all(b">")?;
+        let raw_children = format.map_or(&[][..], |format| &format.raw_children);
+
+        let has_text = element
+            .children
+            .iter()
+            .any(|child| matches!(child, XmlNode::Text(_) | XmlNode::CData(_)));
+        match depth {
+            Some(depth) if !has_text && !element.children.is_empty() => {
+                self.end_line(Some(depth))?;
+                self.write_nodes(&element.children, raw_children, 0, Some(depth + 1))?;
+                self.write_indent(Some(depth))?;
+            }
+            _ => self.write_nodes(&element.children, raw_children, 0, None)?,
+        }
+
+        write!(self.out, "</{}", element.name)?;
+        if let Some(format) = format {
+            self.out.write_all(format.end_tag_end.as_bytes())?;
+        }
+        self.out.write_all(b">")?;
+        self.end_line(depth)
+    }
+
+    fn write_attribute(&mut self, attribute: &Attribute) -> io::Result<()> {
+        let quote = attribute.quote.as_char();
+        let format = attribute
+            .format
+            .as_ref()
+            .filter(|_| self.options.preserve_formatting);
+        let (leading, equals) = match format {
+            Some(format) => (format.leading.as_str(), format.equals.as_str()),
+            None => (" ", "="),
+        };
+        let value = match format {
+            Some(format)
+                if !format.raw_value.contains(quote)
+                    && decode_references(&format.raw_value)
+                        .is_ok_and(|decoded| decoded == attribute.value) =>
+            {
+                Cow::Borrowed(format.raw_value.as_str())
+            }
+            _ => escape(&attribute.value),
+        };
+        write!(
+            self.out,
+            "{}{}{}{}{}{}",
+            leading, attribute.name, equals, quote, value, quote
+        )
+    }
+
+    // Writes `nodes`, using the recorded source text of those that still match it. The nodes
+    // start at `first_index` in the list `raw_nodes` was recorded against.
+    fn write_nodes(
+        &mut self,
+        nodes: &[XmlNode],
+        raw_nodes: &[RawNode],
+        first_index: usize,
+        depth: Option<usize>,
+    ) -> io::Result<()> {
+        let mut raw_nodes = raw_nodes
+            .iter()
+            .skip_while(|raw| raw.index < first_index)
+            .peekable();
+        for (index, node) in (first_index..).zip(nodes) {
+            let raw = raw_nodes.next_if(|raw| raw.index == index);
+            match raw {
+                Some(raw) if raw_matches(node, &raw.raw) => {
+                    if matches!(node, XmlNode::Text(_)) {
+                        self.out.write_all(raw.raw.as_bytes())?;
+                    } else {
+                        self.write_indent(depth)?;
+                        self.out.write_all(raw.raw.as_bytes())?;
+                        self.end_line(depth)?;
+                    }
+                }
+                _ => self.write_node_at(node, depth)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_indent(&mut self, depth: Option<usize>) -> io::Result<()> {
+        if let (Some(depth), Some(indent)) = (depth, &self.options.indent) {
+            for _ in 0..depth {
+                self.out.write_all(indent.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end_line(&mut self, depth: Option<usize>) -> io::Result<()> {
+        match depth {
+            Some(_) => self.out.write_all(b"\n"),
+            None => Ok(()),
+        }
+    }
+}
+
+pub fn document_to_string(document: &XmlDocument, options: &WriterOptions) -> String {
+    let mut writer = XmlWriter::with_options(Vec::new(), options.clone());
+    // Writing to a Vec cannot fail, and every piece written is a &str
+    let _ = writer.write_document(document);
+    String::from_utf8(writer.into_inner())
+        .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned())
+}
+
+// Whether the source declaration can be copied as it is: anything it was decoded from, the
+// writer's output is UTF-8
+fn declares_utf8(declaration: &XmlDeclaration) -> bool {
+    match &declaration.encoding {
+        Some(encoding) => encoding.eq_ignore_ascii_case("UTF-8"),
+        None => true,
+    }
+}
+
+// Whether `raw` is still the source text of `node`
+fn raw_matches(node: &XmlNode, raw: &str) -> bool {
+    match node {
+        XmlNode::Text(text) => decode_references(raw).is_ok_and(|decoded| decoded == *text),
+        XmlNode::ProcessingInstruction { target, data } => raw
+            .strip_prefix("<?")
+            .and_then(|raw| raw.strip_suffix("?>"))
+            .and_then(|raw| raw.strip_prefix(target.as_str()))
+            .is_some_and(|raw| raw.trim_start() == data),
+        _ => false,
+    }
+}
+
+// Escapes the five predefined entities, as BattleScribe does in both text and attributes
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 16);
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(ch),
+        }
+    }
+    Cow::Owned(escaped)
+}
@@ -0,0 +1,950 @@
+use crate::models::Attribute::Attribute;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+
+// Supported: absolute and relative paths, "//", "*", ".", "@name", "@*", "text()",
+// predicates with paths, string and number literals, = != < <= > >=, "and", "or",
+// positional predicates and the functions position(), last(), count(), not(), true(), false(),
+// boolean(), contains(), starts-with(), string() and normalize-space(). There is no parent
+// axis, since the owned tree has no parent links; query an XmlArena for that.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XPathError {
+    UnexpectedEnd { position: usize },
+    UnexpectedToken { found: String, position: usize },
+    UnknownFunction { name: String, position: usize },
+}
+
+impl XPathError {
+    // Character offset in the expression
+    pub fn position(&self) -> usize {
+        match self {
+            XPathError::UnexpectedEnd { position }
+            | XPathError::UnexpectedToken { position, .. }
+            | XPathError::UnknownFunction { position, .. } => *position,
+        }
+    }
+}
+
+impl std::fmt::Display for XPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XPathError::UnexpectedEnd { position } => {
+                write!(f, "offset {}: unexpected end of expression", position)
+            }
+            XPathError::UnexpectedToken { found, position } => {
+                write!(f, "offset {}: unexpected \"{}\"", position, found)
+            }
+            XPathError::UnknownFunction { name, position } => {
+                write!(f, "offset {}: unknown function {}()", position, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for XPathError {}
+
+// A node selected by a query
+#[derive(Debug, Clone, Copy)]
+pub enum XPathNode<'d> {
+    Element(&'d XmlElement),
+    Attribute(&'d Attribute),
+    Text(&'d str),
+}
+
+impl<'d> XPathNode<'d> {
+    pub fn as_element(&self) -> Option<&'d XmlElement> {
+        match self {
+            XPathNode::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn as_attribute(&self) -> Option<&'d Attribute> {
+        match self {
+            XPathNode::Attribute(attribute) => Some(attribute),
+            _ => None,
+        }
+    }
+
+    // An element's text is the text of all its descendants, as in XPath
+    pub fn string_value(&self) -> String {
+        match self {
+            XPathNode::Element(element) => {
+                let mut text = String::new();
+                collect_text(element, &mut text);
+                text
+            }
+            XPathNode::Attribute(attribute) => attribute.value.clone(),
+            XPathNode::Text(text) => text.to_string(),
+        }
+    }
+}
+
+// A compiled query, reusable across documents
+#[derive(Debug, Clone)]
+pub struct XPath {
+    path: LocationPath,
+}
+
+impl XPath {
+    pub fn compile(expression: &str) -> Result<Self, XPathError> {
+        let mut parser = Parser::new(expression)?;
+        let path = parser.parse_location_path()?;
+        parser.expect_end()?;
+        Ok(Self { path })
+    }
+
+    // Every match in document order
+    pub fn select<'d>(&self, document: &'d XmlDocument) -> Vec<XPathNode<'d>> {
+        let root = Context::Document(document);
+        nodes(self.path.evaluate(root.clone(), &root))
+    }
+
+    // Evaluates relative paths from `element`; absolute paths, including those in predicates,
+    // treat it as the root element
+    pub fn select_from<'d>(&self, element: &'d XmlElement) -> Vec<XPathNode<'d>> {
+        let root = Context::Root(element);
+        let context = if self.path.absolute {
+            root.clone()
+        } else {
+            Context::Node(Context::root_element(element))
+        };
+        nodes(self.path.evaluate(context, &root))
+    }
+
+    pub fn select_elements<'d>(&self, document: &'d XmlDocument) -> Vec<&'d XmlElement> {
+        self.select(document)
+            .iter()
+            .filter_map(XPathNode::as_element)
+            .collect()
+    }
+}
+
+pub fn select<'d>(
+    document: &'d XmlDocument,
+    expression: &str,
+) -> Result<Vec<XPathNode<'d>>, XPathError> {
+    Ok(XPath::compile(expression)?.select(document))
+}
+
+// ============================================================================
+// SYNTAX TREE
+// ============================================================================
+
+#[derive(Debug, Clone)]
+struct LocationPath {
+    absolute: bool,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    // Preceded by "//", so it applies to the context node and all its descendants
+    descendant: bool,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone)]
+enum NodeTest {
+    // None matches any name
+    Element(Option<String>),
+    Attribute(Option<String>),
+    Text,
+    SelfNode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Path(LocationPath),
+    Literal(String),
+    Number(f64),
+    Function(Function, Vec<Expr>),
+    Compare(Box<Expr>, Operator, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Position,
+    Last,
+    Count,
+    Not,
+    True,
+    False,
+    Boolean,
+    Contains,
+    StartsWith,
+    String,
+    NormalizeSpace,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        // Name and number of arguments
+        match name {
+            "position" => Some((Function::Position, 0)),
+            "last" => Some((Function::Last, 0)),
+            "count" => Some((Function::Count, 1)),
+            "not" => Some((Function::Not, 1)),
+            "true" => Some((Function::True, 0)),
+            "false" => Some((Function::False, 0)),
+            "boolean" => Some((Function::Boolean, 1)),
+            "contains" => Some((Function::Contains, 2)),
+            "starts-with" => Some((Function::StartsWith, 2)),
+            "string" => Some((Function::String, 1)),
+            "normalize-space" => Some((Function::NormalizeSpace, 1)),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// PARSER
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    At,
+    Star,
+    Dot,
+    Comma,
+    Operator(Operator),
+    Literal(String),
+    Number(f64),
+    Name(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Slash => write!(f, "/"),
+            Token::DoubleSlash => write!(f, "//"),
+            Token::OpenBracket => write!(f, "["),
+            Token::CloseBracket => write!(f, "]"),
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+            Token::At => write!(f, "@"),
+            Token::Star => write!(f, "*"),
+            Token::Dot => write!(f, "."),
+            Token::Comma => write!(f, ","),
+            Token::Operator(operator) => {
+                let symbol = match operator {
+                    Operator::Equal => "=",
+                    Operator::NotEqual => "!=",
+                    Operator::Less => "<",
+                    Operator::LessOrEqual => "<=",
+                    Operator::Greater => ">",
+                    Operator::GreaterOrEqual => ">=",
+                };
+                write!(f, "{}", symbol)
+            }
+            Token::Literal(value) => write!(f, "'{}'", value),
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+fn lex(expression: &str) -> Result<Vec<(Token, usize)>, XPathError> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let ch = chars[i];
+        let token = match ch {
+            ' ' | '\t' | '\n' | '\r' => {
+                i += 1;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                i += 2;
+                Token::DoubleSlash
+            }
+            '/' => {
+                i += 1;
+                Token::Slash
+            }
+            '[' | ']' | '(' | ')' | '@' | '*' | ',' | '=' => {
+                i += 1;
+                match ch {
+                    '[' => Token::OpenBracket,
+                    ']' => Token::CloseBracket,
+                    '(' => Token::OpenParen,
+                    ')' => Token::CloseParen,
+                    '@' => Token::At,
+                    '*' => Token::Star,
+                    ',' => Token::Comma,
+                    _ => Token::Operator(Operator::Equal),
+                }
+            }
+            '!' | '<' | '>' => {
+                let or_equal = chars.get(i + 1) == Some(&'=');
+                i += if or_equal { 2 } else { 1 };
+                match (ch, or_equal) {
+                    ('!', true) => Token::Operator(Operator::NotEqual),
+                    ('<', false) => Token::Operator(Operator::Less),
+                    ('<', true) => Token::Operator(Operator::LessOrEqual),
+                    ('>', false) => Token::Operator(Operator::Greater),
+                    ('>', true) => Token::Operator(Operator::GreaterOrEqual),
+                    _ => {
+                        return Err(XPathError::UnexpectedToken {
+                            found: ch.to_string(),
+                            position: start,
+                        })
+                    }
+                }
+            }
+            '\'' | '"' => {
+                let length = chars[i + 1..].iter().position(|&c| c == ch).ok_or(
+                    XPathError::UnexpectedEnd {
+                        position: chars.len(),
+                    },
+                )?;
+                i += length + 2;
+                Token::Literal(chars[start + 1..start + 1 + length].iter().collect())
+            }
+            '0'..='9' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                Token::Number(text.parse().map_err(|_| XPathError::UnexpectedToken {
+                    found: text.clone(),
+                    position: start,
+                })?)
+            }
+            '.' => {
+                i += 1;
+                Token::Dot
+            }
+            _ if ch.is_alphabetic() || ch == '_' => {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '-' | '.' | ':'))
+                {
+                    i += 1;
+                }
+                Token::Name(chars[start..i].iter().collect())
+            }
+            _ => {
+                return Err(XPathError::UnexpectedToken {
+                    found: ch.to_string(),
+                    position: start,
+                })
+            }
+        };
+        tokens.push((token, start));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn new(expression: &str) -> Result<Self, XPathError> {
+        Ok(Self {
+            tokens: lex(expression)?,
+            index: 0,
+            end: expression.chars().count(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.end, |&(_, position)| position)
+    }
+
+    fn next(&mut self) -> Result<Token, XPathError> {
+        let token = self
+            .tokens
+            .get(self.index)
+            .map(|(token, _)| token.clone())
+            .ok_or(XPathError::UnexpectedEnd { position: self.end })?;
+        self.index += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), XPathError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), XPathError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn unexpected(&self) -> XPathError {
+        match self.tokens.get(self.index) {
+            Some((token, position)) => XPathError::UnexpectedToken {
+                found: token.to_string(),
+                position: *position,
+            },
+            None => XPathError::UnexpectedEnd { position: self.end },
+        }
+    }
+
+    fn parse_location_path(&mut self) -> Result<LocationPath, XPathError> {
+        let mut absolute = false;
+        let mut descendant = false;
+        if self.eat(&Token::DoubleSlash) {
+            absolute = true;
+            descendant = true;
+        } else if self.eat(&Token::Slash) {
+            absolute = true;
+            // "/" on its own selects the root element's parent, i.e. nothing useful here
+            if !matches!(
+                self.peek(),
+                Some(Token::Name(_) | Token::Star | Token::At | Token::Dot)
+            ) {
+                return Err(self.unexpected());
+            }
+        }
+
+        let mut steps = vec![self.parse_step(descendant)?];
+        loop {
+            let descendant = if self.eat(&Token::DoubleSlash) {
+                true
+            } else if self.eat(&Token::Slash) {
+                false
+            } else {
+                break;
+            };
+            steps.push(self.parse_step(descendant)?);
+        }
+        Ok(LocationPath { absolute, steps })
+    }
+
+    fn parse_step(&mut self, descendant: bool) -> Result<Step, XPathError> {
+        let test = match self.next()? {
+            Token::Dot => NodeTest::SelfNode,
+            Token::Star => NodeTest::Element(None),
+            Token::At => match self.next()? {
+                Token::Star => NodeTest::Attribute(None),
+                Token::Name(name) => NodeTest::Attribute(Some(name)),
+                _ => {
+                    self.index -= 1;
+                    return Err(self.unexpected());
+                }
+            },
+            Token::Name(name) if name == "text" && self.peek() == Some(&Token::OpenParen) => {
+                self.expect(&Token::OpenParen)?;
+                self.expect(&Token::CloseParen)?;
+                NodeTest::Text
+            }
+            Token::Name(name) => NodeTest::Element(Some(name)),
+            _ => {
+                self.index -= 1;
+                return Err(self.unexpected());
+            }
+        };
+
+        let mut predicates = Vec::new();
+        while self.eat(&Token::OpenBracket) {
+            predicates.push(self.parse_or()?);
+            self.expect(&Token::CloseBracket)?;
+        }
+        Ok(Step {
+            descendant,
+            test,
+            predicates,
+        })
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, XPathError> {
+        let mut expr = self.parse_and()?;
+        while self.eat(&Token::Name("or".to_string())) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, XPathError> {
+        let mut expr = self.parse_comparison()?;
+        while self.eat(&Token::Name("and".to_string())) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, XPathError> {
+        let mut expr = self.parse_primary()?;
+        while let Some(&Token::Operator(operator)) = self.peek() {
+            self.index += 1;
+            expr = Expr::Compare(Box::new(expr), operator, Box::new(self.parse_primary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, XPathError> {
+        let position = self.position();
+        match self.peek().cloned() {
+            Some(Token::Literal(value)) => {
+                self.index += 1;
+                Ok(Expr::Literal(value))
+            }
+            Some(Token::Number(value)) => {
+                self.index += 1;
+                Ok(Expr::Number(value))
+            }
+            Some(Token::OpenParen) => {
+                self.index += 1;
+                let expr = self.parse_or()?;
+                self.expect(&Token::CloseParen)?;
+                Ok(expr)
+            }
+            Some(Token::Name(name))
+                if name != "text"
+                    && self.tokens.get(self.index + 1).map(|(token, _)| token)
+                        == Some(&Token::OpenParen) =>
+            {
+                let (function, arity) = Function::from_name(&name)
+                    .ok_or(XPathError::UnknownFunction { name, position })?;
+                self.index += 2;
+                let mut arguments = Vec::new();
+                while arguments.len() < arity {
+                    if !arguments.is_empty() {
+                        self.expect(&Token::Comma)?;
+                    }
+                    arguments.push(self.parse_or()?);
+                }
+                self.expect(&Token::CloseParen)?;
+                Ok(Expr::Function(function, arguments))
+            }
+            Some(_) => Ok(Expr::Path(self.parse_location_path()?)),
+            None => Err(self.unexpected()),
+        }
+    }
+}
+
+// ============================================================================
+// EVALUATION
+// ============================================================================
+
+// A selected node with its place in the document: the child index (plus one) of every node on
+// the way down from the root, then for an attribute a 0 and its index. Comparing these orders
+// nodes as XPath does, an element before its attributes and its attributes before its children,
+// and tells apart nodes that hold equal values, such as two empty text nodes.
+#[derive(Clone)]
+struct Located<'d> {
+    node: XPathNode<'d>,
+    order: Vec<usize>,
+}
+
+impl<'d> Located<'d> {
+    fn child(&self, node: XPathNode<'d>, index: usize) -> Self {
+        let mut order = self.order.clone();
+        order.push(index + 1);
+        Located { node, order }
+    }
+
+    fn attribute(&self, attribute: &'d Attribute, index: usize) -> Self {
+        let mut order = self.order.clone();
+        order.extend([0, index]);
+        Located {
+            node: XPathNode::Attribute(attribute),
+            order,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Context<'d> {
+    // Above the root element, where absolute paths start
+    Document(&'d XmlDocument),
+    // Like Document, for queries run against a detached element
+    Root(&'d XmlElement),
+    Node(Located<'d>),
+}
+
+impl<'d> Context<'d> {
+    // The root element, reached from either kind of root context
+    fn root_element(element: &'d XmlElement) -> Located<'d> {
+        Located {
+            node: XPathNode::Element(element),
+            order: vec![1],
+        }
+    }
+
+    fn child_elements(&self) -> Vec<Located<'d>> {
+        match self {
+            Context::Document(document) => document.root.iter().map(Self::root_element).collect(),
+            Context::Root(element) => vec![Self::root_element(element)],
+            Context::Node(parent) => match parent.node {
+                XPathNode::Element(element) => element
+                    .children
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, child)| match child {
+                        XmlNode::Element(element) => {
+                            Some(parent.child(XPathNode::Element(element), index))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    // The context itself followed by every element below it, in document order
+    fn self_and_descendants(self) -> Vec<Context<'d>> {
+        let mut contexts = Vec::new();
+        let mut stack = vec![self];
+        while let Some(context) = stack.pop() {
+            // Reversed so the first child is visited next
+            stack.extend(
+                context
+                    .child_elements()
+                    .into_iter()
+                    .rev()
+                    .map(Context::Node),
+            );
+            contexts.push(context);
+        }
+        contexts
+    }
+
+    fn candidates(&self, test: &NodeTest) -> Vec<Located<'d>> {
+        let element = match self {
+            Context::Node(located) => located.node.as_element().map(|element| (located, element)),
+            _ => None,
+        };
+        match test {
+            NodeTest::Element(name) => self
+                .child_elements()
+                .into_iter()
+                .filter(|located| {
+                    let element = located.node.as_element();
+                    name.as_ref()
+                        .is_none_or(|name| element.is_some_and(|e| e.name == *name))
+                })
+                .collect(),
+            NodeTest::Attribute(name) => match element {
+                Some((located, element)) => element
+                    .attributes_in_order()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, attribute)| {
+                        name.as_ref().is_none_or(|name| attribute.name == *name)
+                    })
+                    .map(|(index, attribute)| located.attribute(attribute, index))
+                    .collect(),
+                None => Vec::new(),
+            },
+            NodeTest::Text => match element {
+                Some((located, element)) => element
+                    .children
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, child)| match child {
+                        XmlNode::Text(text) | XmlNode::CData(text) => {
+                            Some(located.child(XPathNode::Text(text.as_str()), index))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+                None => Vec::new(),
+            },
+            NodeTest::SelfNode => match self {
+                Context::Node(located) => vec![located.clone()],
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+impl LocationPath {
+    // `root` is where absolute paths start, both this one and any in its predicates
+    fn evaluate<'d>(&self, context: Context<'d>, root: &Context<'d>) -> Vec<Located<'d>> {
+        let mut contexts = vec![context];
+        let mut nodes = Vec::new();
+        for step in &self.steps {
+            nodes = step.evaluate(contexts, root);
+            contexts = nodes.iter().cloned().map(Context::Node).collect();
+        }
+        nodes
+    }
+}
+
+impl Step {
+    fn evaluate<'d>(&self, contexts: Vec<Context<'d>>, root: &Context<'d>) -> Vec<Located<'d>> {
+        let mut selected = Vec::new();
+        for context in contexts {
+            let parents = if self.descendant {
+                context.self_and_descendants()
+            } else {
+                vec![context]
+            };
+            // Predicates see each parent's candidates on their own, so //x[1] is the first x
+            // child of every parent, as in XPath
+            for parent in &parents {
+                let mut candidates = parent.candidates(&self.test);
+                for predicate in &self.predicates {
+                    candidates = filter(candidates, predicate, root);
+                }
+                selected.extend(candidates);
+            }
+        }
+
+        // Candidates come grouped by parent, and overlapping contexts select some nodes twice
+        selected.sort_by(|a, b| a.order.cmp(&b.order));
+        selected.dedup_by(|a, b| a.order == b.order);
+        selected
+    }
+}
+
+fn filter<'d>(
+    candidates: Vec<Located<'d>>,
+    predicate: &Expr,
+    root: &Context<'d>,
+) -> Vec<Located<'d>> {
+    let size = candidates.len();
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter(|(index, node)| {
+            let context = EvalContext {
+                root,
+                node,
+                position: index + 1,
+                size,
+            };
+            match predicate.evaluate(&context) {
+                Value::Number(number) => number == (index + 1) as f64,
+                value => value.to_bool(),
+            }
+        })
+        .map(|(_, node)| node)
+        .collect()
+}
+
+fn nodes(located: Vec<Located<'_>>) -> Vec<XPathNode<'_>> {
+    located.into_iter().map(|located| located.node).collect()
+}
+
+struct EvalContext<'a, 'd> {
+    // Where absolute paths start
+    root: &'a Context<'d>,
+    node: &'a Located<'d>,
+    position: usize,
+    size: usize,
+}
+
+enum Value<'d> {
+    Nodes(Vec<XPathNode<'d>>),
+    Number(f64),
+    Text(String),
+    Boolean(bool),
+}
+
+impl Value<'_> {
+    fn to_bool(&self) -> bool {
+        match self {
+            Value::Nodes(nodes) => !nodes.is_empty(),
+            Value::Number(number) => *number != 0.0 && !number.is_nan(),
+            Value::Text(text) => !text.is_empty(),
+            Value::Boolean(boolean) => *boolean,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Value::Nodes(nodes) => nodes
+                .first()
+                .map(XPathNode::string_value)
+                .unwrap_or_default(),
+            Value::Number(number) => number.to_string(),
+            Value::Text(text) => text.clone(),
+            Value::Boolean(boolean) => boolean.to_string(),
+        }
+    }
+
+    // The values this one stands for in a comparison: every node of a node-set, or itself
+    fn atoms(&self) -> Vec<Atom> {
+        match self {
+            Value::Nodes(nodes) => nodes
+                .iter()
+                .map(|node| Atom::Text(node.string_value()))
+                .collect(),
+            Value::Number(number) => vec![Atom::Number(*number)],
+            Value::Text(text) => vec![Atom::Text(text.clone())],
+            Value::Boolean(boolean) => vec![Atom::Boolean(*boolean)],
+        }
+    }
+}
+
+enum Atom {
+    Number(f64),
+    Text(String),
+    Boolean(bool),
+}
+
+impl Atom {
+    // XPath's boolean(): non-empty strings and numbers other than zero and NaN are true
+    fn to_bool(&self) -> bool {
+        match self {
+            Atom::Number(number) => *number != 0.0 && !number.is_nan(),
+            Atom::Text(text) => !text.is_empty(),
+            Atom::Boolean(boolean) => *boolean,
+        }
+    }
+
+    fn to_number(&self) -> f64 {
+        match self {
+            Atom::Number(number) => *number,
+            Atom::Text(text) => text.trim().parse().unwrap_or(f64::NAN),
+            Atom::Boolean(boolean) => f64::from(u8::from(*boolean)),
+        }
+    }
+}
+
+fn compare_values(left: &Value, operator: Operator, right: &Value) -> bool {
+    let is_equality = matches!(operator, Operator::Equal | Operator::NotEqual);
+    if is_equality && (matches!(left, Value::Boolean(_)) || matches!(right, Value::Boolean(_))) {
+        // Against a boolean, a whole node-set counts as true when it is non-empty, rather than
+        // node by node
+        let equal = left.to_bool() == right.to_bool();
+        return equal == (operator == Operator::Equal);
+    }
+
+    let right = right.atoms();
+    left.atoms().iter().any(|left| {
+        right
+            .iter()
+            .any(|right| compare_atoms(left, operator, right))
+    })
+}
+
+fn compare_atoms(left: &Atom, operator: Operator, right: &Atom) -> bool {
+    match operator {
+        Operator::Equal | Operator::NotEqual => {
+            let equal = match (left, right) {
+                (Atom::Boolean(left), right) | (right, Atom::Boolean(left)) => {
+                    *left == right.to_bool()
+                }
+                (Atom::Number(_), _) | (_, Atom::Number(_)) => {
+                    left.to_number() == right.to_number()
+                }
+                (Atom::Text(left), Atom::Text(right)) => left == right,
+            };
+            equal == (operator == Operator::Equal)
+        }
+        Operator::Less => left.to_number() < right.to_number(),
+        Operator::LessOrEqual => left.to_number() <= right.to_number(),
+        Operator::Greater => left.to_number() > right.to_number(),
+        Operator::GreaterOrEqual => left.to_number() >= right.to_number(),
+    }
+}
+
+impl Expr {
+    fn evaluate<'d>(&self, context: &EvalContext<'_, 'd>) -> Value<'d> {
+        match self {
+            Expr::Path(path) => {
+                let start = if path.absolute {
+                    context.root.clone()
+                } else {
+                    Context::Node(context.node.clone())
+                };
+                Value::Nodes(nodes(path.evaluate(start, context.root)))
+            }
+            Expr::Literal(text) => Value::Text(text.clone()),
+            Expr::Number(number) => Value::Number(*number),
+            Expr::And(left, right) => Value::Boolean(
+                left.evaluate(context).to_bool() && right.evaluate(context).to_bool(),
+            ),
+            Expr::Or(left, right) => Value::Boolean(
+                left.evaluate(context).to_bool() || right.evaluate(context).to_bool(),
+            ),
+            Expr::Compare(left, operator, right) => Value::Boolean(compare_values(
+                &left.evaluate(context),
+                *operator,
+                &right.evaluate(context),
+            )),
+            Expr::Function(function, arguments) => {
+                let argument = |index: usize| arguments[index].evaluate(context);
+                match function {
+                    Function::Position => Value::Number(context.position as f64),
+                    Function::Last => Value::Number(context.size as f64),
+                    Function::Count => match argument(0) {
+                        Value::Nodes(nodes) => Value::Number(nodes.len() as f64),
+                        _ => Value::Number(f64::NAN),
+                    },
+                    Function::Not => Value::Boolean(!argument(0).to_bool()),
+                    Function::True => Value::Boolean(true),
+                    Function::False => Value::Boolean(false),
+                    Function::Boolean => Value::Boolean(argument(0).to_bool()),
+                    Function::Contains => {
+                        Value::Boolean(argument(0).to_text().contains(&argument(1).to_text()))
+                    }
+                    Function::StartsWith => {
+                        Value::Boolean(argument(0).to_text().starts_with(&argument(1).to_text()))
+                    }
+                    Function::String => Value::Text(argument(0).to_text()),
+                    Function::NormalizeSpace => Value::Text(
+                        argument(0)
+                            .to_text()
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn collect_text(element: &XmlElement, text: &mut String) {
+    for child in &element.children {
+        match child {
+            XmlNode::Text(content) | XmlNode::CData(content) => text.push_str(content),
+            XmlNode::Element(child) => collect_text(child, text),
+            _ => {}
+        }
+    }
+}
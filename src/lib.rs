@@ -1,3 +1,25 @@
 pub mod Tests;
 pub mod Tools;
 pub mod models;
+
+// Lets code generated by xml-parser-derive name ::xml_parser from inside this crate too
+extern crate self as xml_parser;
+
+pub use xml_parser_derive::FromXmlElement;
+pub use Tools::lint::{lint_document, lint_element, LintIssue, LintKind, LintReport};
+pub use Tools::mapping::{FromXmlElement, FromXmlError, FromXmlValue};
+pub use Tools::parser::{
+    parse_bytes, parse_file, parse_file_lossless, parse_rosz, parse_str, parse_str_lossless,
+    parse_str_recovering, ParsedFile, XmlError,
+};
+pub use Tools::schema::{Schema, SchemaError, SchemaViolation, ViolationKind};
+
+#[cfg(feature = "serde")]
+pub use Tools::deserializer::{from_element, DeserializeError};
+#[cfg(feature = "json")]
+pub use Tools::json::{
+    document_from_json, document_from_json_str, document_to_json, document_to_json_string,
+    JsonError, JsonMode,
+};
+#[cfg(feature = "serde")]
+pub use Tools::serializer::{to_element, SerializeError};
@@ -0,0 +1,137 @@
+use crate::models::Formatting::AttributeFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quote {
+    #[default]
+    Double,
+    Single,
+}
+
+impl Quote {
+    pub fn as_char(self) -> char {
+        match self {
+            Quote::Double => '"',
+            Quote::Single => '\'',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    pub name: String,
+    // Value with entity and character references already decoded
+    pub value: String,
+    // Quote character the value was written with
+    pub quote: Quote,
+    // Source spelling, when parsed losslessly
+    pub format: Option<AttributeFormat>,
+}
+
+impl Attribute {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            quote: Quote::default(),
+            format: None,
+        }
+    }
+}
+
+// An element's attributes in document order. Lookups are linear, which beats hashing for the
+// handful of attributes a BattleScribe element carries.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Attributes {
+    entries: Vec<Attribute>,
+}
+
+impl Attributes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.attribute(name).map(|attribute| &attribute.value)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.entries.iter().find(|attribute| attribute.name == name)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+
+    // Replaces the value of an existing attribute in place, keeping its position and quote,
+    // or appends a new one. Returns the previous value.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let name = name.into();
+        match self
+            .entries
+            .iter_mut()
+            .find(|existing| existing.name == name)
+        {
+            Some(existing) => Some(std::mem::replace(&mut existing.value, value.into())),
+            None => {
+                self.entries.push(Attribute::new(name, value));
+                None
+            }
+        }
+    }
+
+    // Like insert, but a replaced attribute takes on the new attribute's quote and format as well
+    pub fn push(&mut self, attribute: Attribute) -> Option<String> {
+        match self
+            .entries
+            .iter_mut()
+            .find(|existing| existing.name == attribute.name)
+        {
+            Some(existing) => {
+                existing.quote = attribute.quote;
+                existing.format = attribute.format;
+                Some(std::mem::replace(&mut existing.value, attribute.value))
+            }
+            None => {
+                self.entries.push(attribute);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self
+            .entries
+            .iter()
+            .position(|attribute| attribute.name == name)?;
+        Some(self.entries.remove(index).value)
+    }
+
+    // (name, value) pairs in document order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.entries
+            .iter()
+            .map(|attribute| (&attribute.name, &attribute.value))
+    }
+
+    pub fn as_slice(&self) -> &[Attribute] {
+        &self.entries
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for Attributes {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> Self {
+        let mut attributes = Attributes::new();
+        for (name, value) in iter {
+            attributes.insert(name, value);
+        }
+        attributes
+    }
+}
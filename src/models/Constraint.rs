@@ -1,18 +1,42 @@
 use crate::models::ConstraintType::ConstraintType;
+use xml_parser_derive::FromXmlElement;
 
-#[derive(Debug, Clone)]
+// Maps straight from a BattleScribe <constraint> element's attributes, through FromXmlElement
+// or, with the serde feature, serde
+#[derive(Debug, Clone, FromXmlElement)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct Constraint {
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    #[xml(attr = "type")]
     pub constraint_type: ConstraintType,
     pub value: i32,
     pub field: String,
+    #[cfg_attr(feature = "serde", serde(default = "default_scope"))]
+    #[xml(default = "default_scope")]
     pub scope: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[xml(default, with = "flag")]
     pub shared: bool,
     pub id: String,
+    #[xml(with = "flag")]
     pub include_child_selections: Option<bool>,
+    #[xml(with = "flag")]
     pub include_child_forces: Option<bool>,
+    #[xml(with = "flag")]
     pub percent_value: Option<bool>,
 }
 
+fn default_scope() -> String {
+    "parent".to_string()
+}
+
+// Constraint flags have always read anything but "true" as false, so catalogues with other
+// spellings keep loading
+fn flag(value: &str) -> Result<bool, &'static str> {
+    Ok(value == "true")
+}
+
 impl std::fmt::Display for Constraint {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -1,4 +1,8 @@
+use crate::Tools::mapping::FromXmlValue;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub enum ConstraintType {
     Min,
     Max,
@@ -20,3 +24,17 @@ impl std::fmt::Display for ConstraintType {
         }
     }
 }
+
+impl FromXmlValue for ConstraintType {
+    fn from_xml_value(value: &str) -> Result<Self, &'static str> {
+        match value {
+            "min" => Ok(ConstraintType::Min),
+            "max" => Ok(ConstraintType::Max),
+            "equal" => Ok(ConstraintType::Equal),
+            "notEqual" => Ok(ConstraintType::NotEqual),
+            "atLeast" => Ok(ConstraintType::AtLeast),
+            "atMost" => Ok(ConstraintType::AtMost),
+            _ => Err("one of min, max, equal, notEqual, atLeast or atMost"),
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::models::Position::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    // An element left open was closed at `position`
+    UnclosedElement,
+    // A close tag with no matching open element was skipped
+    StrayCloseTag,
+    // A second root element was dropped along with its content
+    ExtraRootElement,
+}
+
+// A repair made by the recovering parser
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub position: Position,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
@@ -0,0 +1,42 @@
+pub const CATALOGUE_NAMESPACE: &str = "http://www.battlescribe.net/schema/catalogueSchema";
+pub const ROSTER_NAMESPACE: &str = "http://www.battlescribe.net/schema/rosterSchema";
+pub const GAME_SYSTEM_NAMESPACE: &str = "http://www.battlescribe.net/schema/gameSystemSchema";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Catalogue,
+    Roster,
+    GameSystem,
+    Unknown,
+}
+
+impl DocumentKind {
+    pub fn from_namespace(namespace: Option<&str>) -> Self {
+        match namespace {
+            Some(CATALOGUE_NAMESPACE) => DocumentKind::Catalogue,
+            Some(ROSTER_NAMESPACE) => DocumentKind::Roster,
+            Some(GAME_SYSTEM_NAMESPACE) => DocumentKind::GameSystem,
+            _ => DocumentKind::Unknown,
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&'static str> {
+        match self {
+            DocumentKind::Catalogue => Some(CATALOGUE_NAMESPACE),
+            DocumentKind::Roster => Some(ROSTER_NAMESPACE),
+            DocumentKind::GameSystem => Some(GAME_SYSTEM_NAMESPACE),
+            DocumentKind::Unknown => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentKind::Catalogue => write!(f, "catalogue"),
+            DocumentKind::Roster => write!(f, "roster"),
+            DocumentKind::GameSystem => write!(f, "gameSystem"),
+            DocumentKind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentType {
+    pub name: String,
+    pub external_id: Option<String>,
+    pub internal_subset: Option<String>,
+}
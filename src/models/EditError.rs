@@ -0,0 +1,65 @@
+// Why an edit to an XmlElement was refused; the element is left unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    InvalidElementName(String),
+    InvalidAttributeName(String),
+    InvalidProcessingInstructionTarget(String),
+    // A character outside XML 1.0's Char production in text, an attribute value or a comment
+    InvalidCharacter(char),
+    // Contains "--" or ends with "-"
+    InvalidComment(String),
+    // Contains "]]>"
+    InvalidCData(String),
+    // Contains "?>"
+    InvalidProcessingInstructionData(String),
+    IndexOutOfBounds { index: usize, len: usize },
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::InvalidElementName(name) => {
+                write!(f, "\"{}\" is not a valid element name", name)
+            }
+            EditError::InvalidAttributeName(name) => {
+                write!(f, "\"{}\" is not a valid attribute name", name)
+            }
+            EditError::InvalidProcessingInstructionTarget(target) => {
+                write!(
+                    f,
+                    "\"{}\" is not a valid processing instruction target",
+                    target
+                )
+            }
+            EditError::InvalidCharacter(ch) => {
+                write!(f, "U+{:04X} is not allowed in XML", *ch as u32)
+            }
+            EditError::InvalidComment(comment) => {
+                write!(
+                    f,
+                    "comment \"{}\" contains \"--\" or ends with \"-\"",
+                    comment
+                )
+            }
+            EditError::InvalidCData(content) => {
+                write!(f, "CDATA section \"{}\" contains \"]]>\"", content)
+            }
+            EditError::InvalidProcessingInstructionData(data) => {
+                write!(
+                    f,
+                    "processing instruction data \"{}\" contains \"?>\"",
+                    data
+                )
+            }
+            EditError::IndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "child index {} is out of bounds for {} children",
+                    index, len
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
@@ -0,0 +1,46 @@
+use crate::models::DocumentType::DocumentType;
+use crate::models::XmlDeclaration::XmlDeclaration;
+
+// Source formatting recorded by lossless parsing. The writer uses it, when asked to, for every
+// node whose value still matches what was parsed, so an unmodified document is written back
+// byte-for-byte and an edited one only changes where it was edited.
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AttributeFormat {
+    // Whitespace between the previous attribute (or the tag name) and this one
+    pub leading: String,
+    // Everything between the name and the opening quote, usually just "="
+    pub equals: String,
+    // The value as written, with its original entity and character references
+    pub raw_value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ElementFormat {
+    // Whitespace before the start tag's ">" or "/>"
+    pub start_tag_end: String,
+    // Written as <name/> rather than <name></name>
+    pub self_closing: bool,
+    // Whitespace between the end tag's name and ">"
+    pub end_tag_end: String,
+    // Children whose source text the writer could not reproduce from their value
+    pub raw_children: Vec<RawNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DocumentFormat {
+    // The declaration and doctype as parsed, next to their source text
+    pub declaration: Option<(XmlDeclaration, String)>,
+    pub doctype: Option<(DocumentType, String)>,
+    // Number of prolog nodes, such as whitespace, before the doctype
+    pub doctype_index: usize,
+    pub raw_prolog: Vec<RawNode>,
+    pub raw_epilog: Vec<RawNode>,
+}
+
+// Source text of the node at `index` in a list of nodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawNode {
+    pub index: usize,
+    pub raw: String,
+}
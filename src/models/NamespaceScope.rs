@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+// Prefix-to-URI bindings in scope at an element; the default namespace uses the empty prefix.
+// Elements that declare nothing share their parent's bindings.
+#[derive(Debug, Clone, Default)]
+pub struct NamespaceScope {
+    bindings: Option<Arc<HashMap<String, String>>>,
+    // The parent's bindings, kept so an element's own declarations can be edited later
+    inherited: Option<Arc<HashMap<String, String>>>,
+}
+
+// Two scopes are equal when they bind the same prefixes, however they were declared
+impl PartialEq for NamespaceScope {
+    fn eq(&self, other: &Self) -> bool {
+        self.bindings == other.bindings
+    }
+}
+
+impl NamespaceScope {
+    pub fn lookup(&self, prefix: &str) -> Option<&str> {
+        match prefix {
+            "xml" => Some(XML_NAMESPACE),
+            "xmlns" => Some(XMLNS_NAMESPACE),
+            _ => self
+                .bindings
+                .as_ref()?
+                .get(prefix)
+                .map(String::as_str)
+                .filter(|uri| !uri.is_empty()),
+        }
+    }
+
+    pub fn default_namespace(&self) -> Option<&str> {
+        self.lookup("")
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.bindings
+            .iter()
+            .flat_map(|bindings| bindings.iter())
+            .filter(|(_, uri)| !uri.is_empty())
+            .map(|(prefix, uri)| (prefix.as_str(), uri.as_str()))
+    }
+
+    // Returns the scope for a child element declaring `declarations` (prefix, uri).
+    // An empty uri undeclares the prefix, e.g. xmlns="".
+    pub fn with_declarations<'a, I>(&self, declarations: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut declarations = declarations.into_iter().peekable();
+        if declarations.peek().is_none() {
+            return Self {
+                bindings: self.bindings.clone(),
+                inherited: self.bindings.clone(),
+            };
+        }
+
+        let mut bindings = self
+            .bindings
+            .as_ref()
+            .map(|bindings| bindings.as_ref().clone())
+            .unwrap_or_default();
+        for (prefix, uri) in declarations {
+            bindings.insert(prefix.to_string(), uri.to_string());
+        }
+
+        Self {
+            bindings: Some(Arc::new(bindings)),
+            inherited: self.bindings.clone(),
+        }
+    }
+
+    // The bindings in scope at the parent element, before this element's own declarations
+    pub fn inherited(&self) -> Self {
+        Self {
+            bindings: self.inherited.clone(),
+            inherited: None,
+        }
+    }
+}
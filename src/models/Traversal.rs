@@ -0,0 +1,117 @@
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+use std::collections::VecDeque;
+
+// Element iterators and the visitor used by XmlElement::visit
+
+// Depth-first, in document order, not including the element it started from
+pub struct Descendants<'a> {
+    stack: Vec<std::slice::Iter<'a, XmlNode>>,
+}
+
+impl<'a> Descendants<'a> {
+    pub(crate) fn new(element: &'a XmlElement) -> Self {
+        Self {
+            stack: vec![element.children.iter()],
+        }
+    }
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = &'a XmlElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(XmlNode::Element(element)) => {
+                    self.stack.push(element.children.iter());
+                    return Some(element);
+                }
+                Some(_) => {}
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+// Level by level: all children, then all grandchildren, and so on
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<&'a XmlElement>,
+}
+
+impl<'a> BreadthFirst<'a> {
+    pub(crate) fn new(element: &'a XmlElement) -> Self {
+        Self {
+            queue: element.child_elements().collect(),
+        }
+    }
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a XmlElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.queue.pop_front()?;
+        self.queue.extend(element.child_elements());
+        Some(element)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitControl {
+    Continue,
+    // Don't visit this element's children; its leave hook still runs
+    SkipChildren,
+    // End the walk without calling any more hooks
+    Stop,
+}
+
+// Hooks for XmlElement::visit. Depth is 0 for the element the walk started from.
+// A closure taking (&XmlElement, depth) and returning VisitControl works as an enter-only visitor.
+pub trait Visitor {
+    fn enter(&mut self, element: &XmlElement, depth: usize) -> VisitControl;
+
+    fn leave(&mut self, _element: &XmlElement, _depth: usize) {}
+
+    // Text, CDATA, comments and processing instructions, at their parent's depth plus one
+    fn content(&mut self, _node: &XmlNode, _depth: usize) {}
+}
+
+impl<F> Visitor for F
+where
+    F: FnMut(&XmlElement, usize) -> VisitControl,
+{
+    fn enter(&mut self, element: &XmlElement, depth: usize) -> VisitControl {
+        self(element, depth)
+    }
+}
+
+// Returns false once the visitor has asked to stop
+pub(crate) fn visit<V: Visitor + ?Sized>(
+    element: &XmlElement,
+    visitor: &mut V,
+    depth: usize,
+) -> bool {
+    match visitor.enter(element, depth) {
+        VisitControl::Stop => return false,
+        VisitControl::SkipChildren => {}
+        VisitControl::Continue => {
+            for child in &element.children {
+                let keep_going = match child {
+                    XmlNode::Element(child) => visit(child, visitor, depth + 1),
+                    _ => {
+                        visitor.content(child, depth + 1);
+                        true
+                    }
+                };
+                if !keep_going {
+                    return false;
+                }
+            }
+        }
+    }
+    visitor.leave(element, depth);
+    true
+}
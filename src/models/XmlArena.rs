@@ -0,0 +1,261 @@
+use crate::models::DocumentType::DocumentType;
+use crate::models::Formatting::DocumentFormat;
+use crate::models::XmlDeclaration::XmlDeclaration;
+use crate::models::XmlDocument::XmlDocument;
+use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
+
+// Handle to a node in an XmlArena; only meaningful for the arena that issued it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ArenaNode {
+    // Elements keep their name, attributes and namespaces here; their children live in the
+    // arena, so the element's own `children` is always empty
+    value: XmlNode,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    prev_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+}
+
+// A document stored as a flat list of nodes linked to their parent and siblings, so the tree
+// can be walked upwards as well as downwards. Node ids stay valid for the arena's lifetime.
+#[derive(Debug, Clone, Default)]
+pub struct XmlArena {
+    pub declaration: Option<XmlDeclaration>,
+    pub doctype: Option<DocumentType>,
+    pub prolog: Vec<XmlNode>,
+    pub epilog: Vec<XmlNode>,
+    pub format: Option<DocumentFormat>,
+    nodes: Vec<ArenaNode>,
+    root: Option<NodeId>,
+}
+
+impl XmlArena {
+    pub fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    // Number of nodes below the prolog/epilog, the root included
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> &XmlNode {
+        &self.nodes[id.0].value
+    }
+
+    pub fn element(&self, id: NodeId) -> Option<&XmlElement> {
+        match self.node(id) {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].last_child
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].next_sibling
+    }
+
+    pub fn prev_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].prev_sibling
+    }
+
+    // Parent, grandparent and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&ancestor| self.parent(ancestor))
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.first_child(id), move |&child| self.next_sibling(child))
+    }
+
+    // Every node below `id` in document order, not including `id` itself
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.first_child(id), move |&node| {
+            self.next_in_document_order(node, id)
+        })
+    }
+
+    // Closest ancestor element with the given name, e.g. the selectionEntry of a constraint
+    pub fn find_ancestor_by_name(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        self.ancestors(id)
+            .find(|&ancestor| self.element(ancestor).is_some_and(|e| e.name == name))
+    }
+
+    // Rebuilds the owned element tree rooted at `id`
+    pub fn to_element(&self, id: NodeId) -> Option<XmlElement> {
+        let mut element = self.element(id)?.clone();
+        element.children = self.children(id).map(|child| self.to_node(child)).collect();
+        Some(element)
+    }
+
+    pub fn to_document(&self) -> XmlDocument {
+        XmlDocument {
+            declaration: self.declaration.clone(),
+            doctype: self.doctype.clone(),
+            prolog: self.prolog.clone(),
+            root: self.root.and_then(|root| self.to_element(root)),
+            epilog: self.epilog.clone(),
+            format: self.format.clone(),
+        }
+    }
+
+    fn to_node(&self, id: NodeId) -> XmlNode {
+        match self.to_element(id) {
+            Some(element) => XmlNode::Element(element),
+            None => self.node(id).clone(),
+        }
+    }
+
+    fn next_in_document_order(&self, node: NodeId, within: NodeId) -> Option<NodeId> {
+        if let Some(child) = self.first_child(node) {
+            return Some(child);
+        }
+        let mut current = node;
+        while current != within {
+            if let Some(sibling) = self.next_sibling(current) {
+                return Some(sibling);
+            }
+            current = self.parent(current)?;
+        }
+        None
+    }
+
+    pub(crate) fn element_mut(&mut self, id: NodeId) -> Option<&mut XmlElement> {
+        match &mut self.nodes[id.0].value {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_root(&mut self, root: NodeId) {
+        self.root = Some(root);
+    }
+
+    // Drops `id` and every node added after it; `id` must be a detached node whose subtree is
+    // all that follows it
+    pub(crate) fn truncate(&mut self, id: NodeId) {
+        self.nodes.truncate(id.0);
+    }
+
+    // Moves the nodes back out into an owned element tree, without cloning them
+    pub fn into_document(self) -> XmlDocument {
+        let mut links = Vec::with_capacity(self.nodes.len());
+        let mut values = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes {
+            links.push((node.first_child, node.next_sibling));
+            values.push(Some(node.value));
+        }
+        let root = self
+            .root
+            .and_then(|root| match take_node(&mut values, &links, root) {
+                XmlNode::Element(element) => Some(element),
+                _ => None,
+            });
+        XmlDocument {
+            declaration: self.declaration,
+            doctype: self.doctype,
+            prolog: self.prolog,
+            root,
+            epilog: self.epilog,
+            format: self.format,
+        }
+    }
+
+    // Moves `node` and everything below it into the arena as the last child of `parent`
+    pub(crate) fn append(&mut self, parent: Option<NodeId>, mut node: XmlNode) -> NodeId {
+        let children = match &mut node {
+            XmlNode::Element(element) => std::mem::take(&mut element.children),
+            _ => Vec::new(),
+        };
+
+        let id = NodeId(self.nodes.len());
+        let prev_sibling = parent.and_then(|parent| self.nodes[parent.0].last_child);
+        self.nodes.push(ArenaNode {
+            value: node,
+            parent,
+            first_child: None,
+            last_child: None,
+            prev_sibling,
+            next_sibling: None,
+        });
+
+        if let Some(parent) = parent {
+            match prev_sibling {
+                Some(prev) => self.nodes[prev.0].next_sibling = Some(id),
+                None => self.nodes[parent.0].first_child = Some(id),
+            }
+            self.nodes[parent.0].last_child = Some(id);
+        }
+
+        for child in children {
+            self.append(Some(id), child);
+        }
+        id
+    }
+}
+
+// Takes `id` out of `values`, moving its children back under it
+fn take_node(
+    values: &mut [Option<XmlNode>],
+    links: &[(Option<NodeId>, Option<NodeId>)],
+    id: NodeId,
+) -> XmlNode {
+    let mut node = values[id.0].take().expect("every node has a single parent");
+    if let XmlNode::Element(element) = &mut node {
+        let children = std::iter::successors(links[id.0].0, |child| links[child.0].1);
+        element.children = children
+            .map(|child| take_node(values, links, child))
+            .collect();
+    }
+    node
+}
+
+impl From<XmlDocument> for XmlArena {
+    fn from(document: XmlDocument) -> Self {
+        let mut arena = XmlArena {
+            declaration: document.declaration,
+            doctype: document.doctype,
+            prolog: document.prolog,
+            epilog: document.epilog,
+            format: document.format,
+            nodes: Vec::new(),
+            root: None,
+        };
+        arena.root = document
+            .root
+            .map(|root| arena.append(None, XmlNode::Element(root)));
+        arena
+    }
+}
+
+impl From<XmlArena> for XmlDocument {
+    fn from(arena: XmlArena) -> Self {
+        arena.into_document()
+    }
+}
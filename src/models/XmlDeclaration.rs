@@ -0,0 +1,16 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlDeclaration {
+    pub version: String,
+    pub encoding: Option<String>,
+    pub standalone: Option<bool>,
+}
+
+impl Default for XmlDeclaration {
+    fn default() -> Self {
+        Self {
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: None,
+        }
+    }
+}
@@ -1,8 +1,20 @@
+use crate::models::DocumentKind::DocumentKind;
+use crate::models::DocumentType::DocumentType;
+use crate::models::Formatting::DocumentFormat;
+use crate::models::XmlDeclaration::XmlDeclaration;
 use crate::models::XmlElement::XmlElement;
+use crate::models::XmlNode::XmlNode;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct XmlDocument {
+    pub declaration: Option<XmlDeclaration>,
+    pub doctype: Option<DocumentType>,
+    // Comments and processing instructions before and after the root element
+    pub prolog: Vec<XmlNode>,
     pub root: Option<XmlElement>,
+    pub epilog: Vec<XmlNode>,
+    // Source formatting, when parsed losslessly
+    pub format: Option<DocumentFormat>,
 }
 
 impl XmlDocument {
@@ -10,6 +22,15 @@ impl XmlDocument {
         self.root.as_ref()
     }
 
+    // Tells catalogues, rosters and game systems apart by the root element's namespace
+    pub fn document_kind(&self) -> DocumentKind {
+        DocumentKind::from_namespace(
+            self.root
+                .as_ref()
+                .and_then(|root| root.namespace.as_deref()),
+        )
+    }
+
     pub fn find_element_by_path(&self, path: &str) -> Option<&XmlElement> {
         let path_parts: Vec<&str> = path.split('/').collect();
         let mut current = self.get_root_element()?;
@@ -1,14 +1,53 @@
+use crate::models::Attribute::{Attribute, Attributes};
+use crate::models::EditError::EditError;
+use crate::models::Formatting::ElementFormat;
+use crate::models::NamespaceScope::NamespaceScope;
+use crate::models::Span::Span;
+use crate::models::Traversal::{self, BreadthFirst, Descendants, VisitControl, Visitor};
 use crate::models::XmlNode::XmlNode;
-use std::collections::HashMap;
+use crate::Tools::lexical_analysis::{is_valid_name, is_xml_char};
+use crate::Tools::parse_tokens::resolve_namespace;
 
 #[derive(Debug, Clone)]
 pub struct XmlElement {
     pub name: String,
-    pub attributes: HashMap<String, String>,
+    // In document order, so re-serialised files keep their attribute layout
+    pub attributes: Attributes,
     pub children: Vec<XmlNode>,
+    pub span: Option<Span>,
+    // Namespace URI the element's prefix (or the default namespace) resolves to
+    pub namespace: Option<String>,
+    // Prefix-to-URI bindings in scope at this element, inherited from its ancestors
+    pub namespaces: NamespaceScope,
+    // Source formatting, when parsed losslessly
+    pub format: Option<ElementFormat>,
 }
 
 impl XmlElement {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            attributes: Attributes::new(),
+            children: Vec::new(),
+            span: None,
+            namespace: None,
+            namespaces: NamespaceScope::default(),
+            format: None,
+        }
+    }
+
+    pub fn local_name(&self) -> &str {
+        split_qualified_name(&self.name).1
+    }
+
+    pub fn prefix(&self) -> Option<&str> {
+        split_qualified_name(&self.name).0
+    }
+
+    pub fn has_name(&self, namespace: Option<&str>, local_name: &str) -> bool {
+        self.local_name() == local_name && self.namespace.as_deref() == namespace
+    }
+
     pub fn find_child_by_name(&self, name: &str) -> Option<&XmlElement> {
         for child in &self.children {
             if let XmlNode::Element(element) = child {
@@ -20,11 +59,70 @@ impl XmlElement {
         None
     }
 
+    pub fn find_child_by_ns(
+        &self,
+        namespace: Option<&str>,
+        local_name: &str,
+    ) -> Option<&XmlElement> {
+        self.children.iter().find_map(|child| match child {
+            XmlNode::Element(element) if element.has_name(namespace, local_name) => Some(element),
+            _ => None,
+        })
+    }
+
+    pub fn child_elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.child_elements()
+            .filter(move |element| element.name == name)
+    }
+
+    // Every element below this one, depth-first in document order
+    pub fn descendants(&self) -> Descendants<'_> {
+        Descendants::new(self)
+    }
+
+    pub fn descendants_breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst::new(self)
+    }
+
+    // Descendants with the given name, e.g. every constraint in a catalogue
+    pub fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.descendants()
+            .filter(move |element| element.name == name)
+    }
+
+    pub fn find_by_attribute<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> impl Iterator<Item = &'a XmlElement> {
+        self.descendants()
+            .filter(move |element| element.get_attribute(key).is_some_and(|v| v == value))
+    }
+
+    // Walks this element and everything below it; returns Stop if the visitor stopped early
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) -> VisitControl {
+        if Traversal::visit(self, visitor, 0) {
+            VisitControl::Continue
+        } else {
+            VisitControl::Stop
+        }
+    }
+
     pub fn get_text_content(&self) -> String {
         let mut text_parts = Vec::new();
         for child in &self.children {
-            if let XmlNode::Text(content) = child {
-                text_parts.push(content.clone());
+            match child {
+                XmlNode::Text(content) | XmlNode::CData(content) => {
+                    text_parts.push(content.clone());
+                }
+                _ => {}
             }
         }
         text_parts.join("")
@@ -33,4 +131,267 @@ impl XmlElement {
     pub fn get_attribute(&self, name: &str) -> Option<&String> {
         self.attributes.get(name)
     }
+
+    pub fn attributes_in_order(&self) -> &[Attribute] {
+        self.attributes.as_slice()
+    }
+
+    // Changes an attribute's value where it stands, or appends it; returns the previous value.
+    // Setting xmlns or xmlns:prefix re-resolves the namespaces of this element and its
+    // descendants.
+    pub fn set_attribute(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<Option<String>, EditError> {
+        let name = name.into();
+        let value = value.into();
+        if !is_valid_name(&name) {
+            return Err(EditError::InvalidAttributeName(name));
+        }
+        check_chars(&value)?;
+        let declares_namespace = is_namespace_declaration(&name);
+        let previous = self.attributes.insert(name, value);
+        if declares_namespace {
+            self.redeclare_namespaces();
+        }
+        Ok(previous)
+    }
+
+    // Removing xmlns or xmlns:prefix falls back to the binding the parent had, if any
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let previous = self.attributes.remove(name);
+        if previous.is_some() && is_namespace_declaration(name) {
+            self.redeclare_namespaces();
+        }
+        previous
+    }
+
+    // Unprefixed attributes are in no namespace, whatever the default namespace is
+    pub fn get_attribute_ns(&self, namespace: Option<&str>, local_name: &str) -> Option<&String> {
+        self.attributes.iter().find_map(|(name, value)| {
+            let (prefix, local) = split_qualified_name(name);
+            let attribute_namespace = prefix.and_then(|prefix| self.namespaces.lookup(prefix));
+            (local == local_name && attribute_namespace == namespace).then_some(value)
+        })
+    }
+}
+
+// ============================================================================
+// EDITING
+// ============================================================================
+
+impl XmlElement {
+    pub fn append_child(&mut self, node: impl Into<XmlNode>) -> Result<(), EditError> {
+        self.insert_child_at(self.children.len(), node)
+    }
+
+    pub fn insert_child_at(
+        &mut self,
+        index: usize,
+        node: impl Into<XmlNode>,
+    ) -> Result<(), EditError> {
+        self.check_index(index, self.children.len() + 1)?;
+        let node = node.into();
+        validate_node(&node)?;
+        self.shift_raw_children(index, 1);
+        self.children.insert(index, node);
+        Ok(())
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> Result<XmlNode, EditError> {
+        self.check_index(index, self.children.len())?;
+        self.forget_raw_child(index);
+        self.shift_raw_children(index + 1, -1);
+        Ok(self.children.remove(index))
+    }
+
+    // Returns the node that was replaced
+    pub fn replace_child(
+        &mut self,
+        index: usize,
+        node: impl Into<XmlNode>,
+    ) -> Result<XmlNode, EditError> {
+        self.check_index(index, self.children.len())?;
+        let node = node.into();
+        validate_node(&node)?;
+        self.forget_raw_child(index);
+        Ok(std::mem::replace(&mut self.children[index], node))
+    }
+
+    // Replaces all children with a single text node, or none for an empty string
+    pub fn set_text(&mut self, text: impl Into<String>) -> Result<(), EditError> {
+        let text = text.into();
+        check_chars(&text)?;
+        self.children.clear();
+        if let Some(format) = self.format.as_mut() {
+            format.raw_children.clear();
+        }
+        if !text.is_empty() {
+            self.children.push(XmlNode::Text(text));
+        }
+        Ok(())
+    }
+
+    pub fn child_elements_mut(&mut self) -> impl Iterator<Item = &mut XmlElement> {
+        self.children.iter_mut().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    pub fn children_named_mut<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a mut XmlElement> {
+        self.child_elements_mut()
+            .filter(move |element| element.name == name)
+    }
+
+    pub fn find_child_by_name_mut(&mut self, name: &str) -> Option<&mut XmlElement> {
+        self.child_elements_mut()
+            .find(|element| element.name == name)
+    }
+
+    // First descendant with the given name, depth-first
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut XmlElement> {
+        self.find_descendant_mut(&|element| element.name == name)
+    }
+
+    pub fn find_by_attribute_mut(&mut self, key: &str, value: &str) -> Option<&mut XmlElement> {
+        self.find_descendant_mut(&|element| element.get_attribute(key).is_some_and(|v| v == value))
+    }
+
+    // Calls `edit` on every descendant, depth-first, before visiting its children
+    pub fn for_each_descendant_mut(&mut self, edit: &mut impl FnMut(&mut XmlElement)) {
+        for child in self.child_elements_mut() {
+            edit(child);
+            child.for_each_descendant_mut(edit);
+        }
+    }
+
+    fn find_descendant_mut(
+        &mut self,
+        predicate: &impl Fn(&XmlElement) -> bool,
+    ) -> Option<&mut XmlElement> {
+        for child in self.child_elements_mut() {
+            if predicate(child) {
+                return Some(child);
+            }
+            if let Some(found) = child.find_descendant_mut(predicate) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    // Applies this element's current declarations over the scope it inherited, then passes the
+    // result down to its descendants
+    fn redeclare_namespaces(&mut self) {
+        let inherited = self.namespaces.inherited();
+        resolve_namespace(self, Some(&inherited));
+        self.resolve_descendant_namespaces();
+    }
+
+    fn resolve_descendant_namespaces(&mut self) {
+        let scope = self.namespaces.clone();
+        for child in self.child_elements_mut() {
+            resolve_namespace(child, Some(&scope));
+            child.resolve_descendant_namespaces();
+        }
+    }
+
+    fn check_index(&self, index: usize, limit: usize) -> Result<(), EditError> {
+        if index < limit {
+            Ok(())
+        } else {
+            Err(EditError::IndexOutOfBounds {
+                index,
+                len: self.children.len(),
+            })
+        }
+    }
+
+    // Keeps lossless source text attached to the right children as the list changes
+    fn shift_raw_children(&mut self, from: usize, delta: isize) {
+        if let Some(format) = self.format.as_mut() {
+            for raw in format
+                .raw_children
+                .iter_mut()
+                .filter(|raw| raw.index >= from)
+            {
+                raw.index = raw.index.saturating_add_signed(delta);
+            }
+        }
+    }
+
+    fn forget_raw_child(&mut self, index: usize) {
+        if let Some(format) = self.format.as_mut() {
+            format.raw_children.retain(|raw| raw.index != index);
+        }
+    }
+}
+
+// Checks every name and piece of text in a node about to be added, including those of its
+// descendants, so the writer can only produce well-formed XML
+pub(crate) fn validate_node(node: &XmlNode) -> Result<(), EditError> {
+    match node {
+        XmlNode::Element(element) => {
+            if !is_valid_name(&element.name) {
+                return Err(EditError::InvalidElementName(element.name.clone()));
+            }
+            for attribute in element.attributes_in_order() {
+                if !is_valid_name(&attribute.name) {
+                    return Err(EditError::InvalidAttributeName(attribute.name.clone()));
+                }
+                check_chars(&attribute.value)?;
+            }
+            element.children.iter().try_for_each(validate_node)
+        }
+        XmlNode::Text(text) => check_chars(text),
+        XmlNode::Comment(comment) => {
+            check_chars(comment)?;
+            if comment.contains("--") || comment.ends_with('-') {
+                return Err(EditError::InvalidComment(comment.clone()));
+            }
+            Ok(())
+        }
+        XmlNode::CData(content) => {
+            check_chars(content)?;
+            if content.contains("]]>") {
+                return Err(EditError::InvalidCData(content.clone()));
+            }
+            Ok(())
+        }
+        XmlNode::ProcessingInstruction { target, data } => {
+            if !is_valid_name(target) || target.eq_ignore_ascii_case("xml") {
+                return Err(EditError::InvalidProcessingInstructionTarget(
+                    target.clone(),
+                ));
+            }
+            check_chars(data)?;
+            if data.contains("?>") {
+                return Err(EditError::InvalidProcessingInstructionData(data.clone()));
+            }
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn check_chars(text: &str) -> Result<(), EditError> {
+    match text.chars().find(|&ch| !is_xml_char(ch)) {
+        Some(ch) => Err(EditError::InvalidCharacter(ch)),
+        None => Ok(()),
+    }
+}
+
+fn is_namespace_declaration(name: &str) -> bool {
+    name == "xmlns" || name.starts_with("xmlns:")
+}
+
+pub fn split_qualified_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once(':') {
+        Some((prefix, local_name)) => (Some(prefix), local_name),
+        None => (None, name),
+    }
 }
@@ -5,4 +5,12 @@ pub enum XmlNode {
     Element(XmlElement),
     Text(String),
     Comment(String),
+    CData(String),
+    ProcessingInstruction { target: String, data: String },
+}
+
+impl From<XmlElement> for XmlNode {
+    fn from(element: XmlElement) -> Self {
+        XmlNode::Element(element)
+    }
 }
@@ -1,6 +1,18 @@
+pub mod Attribute;
 pub mod Constraint;
 pub mod ConstraintType;
+pub mod Diagnostic;
+pub mod DocumentKind;
+pub mod DocumentType;
+pub mod EditError;
+pub mod Formatting;
+pub mod NamespaceScope;
+pub mod Position;
+pub mod Span;
+pub mod Traversal;
 pub mod ValidationResult;
+pub mod XmlArena;
+pub mod XmlDeclaration;
 pub mod XmlDocument;
 pub mod XmlElement;
 pub mod XmlNode;
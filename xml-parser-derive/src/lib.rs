@@ -0,0 +1,261 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, ExprPath, Field, Fields, GenericArgument, LitStr,
+    PathArguments, Type,
+};
+
+// #[derive(FromXmlElement)] for structs with named fields. The generated impl calls the helpers
+// in xml_parser::Tools::mapping, which also documents the #[xml(...)] options
+#[proc_macro_derive(FromXmlElement, attributes(xml))]
+pub fn derive_from_xml_element(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(not_supported(input)),
+        },
+        _ => return Err(not_supported(input)),
+    };
+
+    let initializers = fields
+        .iter()
+        .map(field_initializer)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::xml_parser::Tools::mapping::FromXmlElement
+            for #name #type_generics #where_clause
+        {
+            fn from_xml_element(
+                element: &::xml_parser::models::XmlElement::XmlElement,
+            ) -> ::std::result::Result<Self, ::xml_parser::Tools::mapping::FromXmlError> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+fn not_supported(input: &DeriveInput) -> syn::Error {
+    syn::Error::new_spanned(
+        &input.ident,
+        "FromXmlElement can only be derived for structs with named fields",
+    )
+}
+
+// ============================================================================
+// FIELD OPTIONS
+// ============================================================================
+
+enum Source {
+    Attribute(String),
+    Child(String),
+    Text,
+}
+
+enum DefaultValue {
+    None,
+    Trait,
+    Function(ExprPath),
+}
+
+struct FieldOptions {
+    source: Source,
+    many: bool,
+    default: DefaultValue,
+    with: Option<ExprPath>,
+}
+
+fn field_options(field: &Field) -> syn::Result<FieldOptions> {
+    let mut source = None;
+    let mut many = false;
+    let mut default = DefaultValue::None;
+    let mut with = None;
+
+    for attribute in field.attrs.iter().filter(|a| a.path().is_ident("xml")) {
+        attribute.parse_nested_meta(|meta| {
+            let found = if meta.path.is_ident("attr") {
+                Source::Attribute(meta.value()?.parse::<LitStr>()?.value())
+            } else if meta.path.is_ident("child") {
+                Source::Child(meta.value()?.parse::<LitStr>()?.value())
+            } else if meta.path.is_ident("text") {
+                Source::Text
+            } else if meta.path.is_ident("many") {
+                many = true;
+                return Ok(());
+            } else if meta.path.is_ident("default") {
+                default = if meta.input.peek(syn::Token![=]) {
+                    DefaultValue::Function(meta.value()?.parse::<LitStr>()?.parse()?)
+                } else {
+                    DefaultValue::Trait
+                };
+                return Ok(());
+            } else if meta.path.is_ident("with") {
+                with = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                return Ok(());
+            } else {
+                return Err(
+                    meta.error("expected `attr`, `child`, `many`, `text`, `default` or `with`")
+                );
+            };
+
+            if source.replace(found).is_some() {
+                return Err(meta.error("only one of `attr`, `child` and `text` may be given"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let source = source.unwrap_or_else(|| {
+        let name = field.ident.as_ref().unwrap().to_string();
+        Source::Attribute(camel_case(name.trim_start_matches("r#")))
+    });
+    if many && !matches!(source, Source::Child(_)) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`many` needs `child = \"...\"`",
+        ));
+    }
+    if many && !matches!(default, DefaultValue::None) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`many` fields are already empty when nothing matches; remove `default`",
+        ));
+    }
+    if with.is_some() && matches!(source, Source::Child(_)) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`with` reads an attribute or text, not `child = \"...\"`",
+        ));
+    }
+    if option_inner(&field.ty).is_some() && !matches!(default, DefaultValue::None) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "Option fields are already None when missing; remove `default`",
+        ));
+    }
+
+    Ok(FieldOptions {
+        source,
+        many,
+        default,
+        with,
+    })
+}
+
+// include_child_selections -> includeChildSelections, the BattleScribe attribute spelling
+fn camel_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for (index, word) in name.split('_').filter(|word| !word.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            if index == 0 {
+                result.push(first);
+            } else {
+                result.extend(first.to_uppercase());
+            }
+            result.push_str(chars.as_str());
+        }
+    }
+    result
+}
+
+// ============================================================================
+// CODE GENERATION
+// ============================================================================
+
+fn field_initializer(field: &Field) -> syn::Result<TokenStream2> {
+    let options = field_options(field)?;
+    let name = field.ident.as_ref().unwrap();
+    let mapping = quote!(::xml_parser::Tools::mapping);
+
+    if options.many {
+        let Some(item) = generic_argument(&field.ty, "Vec") else {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`many` fields must be a Vec",
+            ));
+        };
+        let Source::Child(path) = &options.source else {
+            unreachable!("checked in field_options")
+        };
+        return Ok(quote! {
+            #name: #mapping::children::<#item>(element, #path)?
+        });
+    }
+
+    let optional = option_inner(&field.ty);
+    let value_type = optional.unwrap_or(&field.ty);
+    let parse = match &options.with {
+        Some(function) => quote!(#function),
+        None => quote!(<#value_type as #mapping::FromXmlValue>::from_xml_value),
+    };
+    let (lookup, missing) = match &options.source {
+        Source::Attribute(attribute) => (
+            quote!(#mapping::attribute_with(element, #attribute, #parse)?),
+            quote!(#mapping::FromXmlError::MissingAttribute {
+                path: element.name.clone(),
+                attribute: #attribute.to_string(),
+            }),
+        ),
+        Source::Child(path) => (
+            quote!(#mapping::child::<#value_type>(element, #path)?),
+            quote!(#mapping::FromXmlError::MissingChild {
+                path: element.name.clone(),
+                child: #path.to_string(),
+            }),
+        ),
+        Source::Text => (
+            quote!(#mapping::text_with(element, #parse)?),
+            quote!(#mapping::FromXmlError::MissingText {
+                path: element.name.clone(),
+            }),
+        ),
+    };
+
+    let value = match (&options.default, optional) {
+        (_, Some(_)) => lookup,
+        (DefaultValue::Trait, None) => quote!(#lookup.unwrap_or_default()),
+        (DefaultValue::Function(function), None) => quote!(#lookup.unwrap_or_else(#function)),
+        (DefaultValue::None, None) => quote! {
+            match #lookup {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => return ::std::result::Result::Err(#missing),
+            }
+        },
+    };
+    Ok(quote!(#name: #value))
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    generic_argument(ty, "Option")
+}
+
+// T in Wrapper<T>, matched by the last path segment so std::vec::Vec<T> works too
+fn generic_argument<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) if arguments.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}